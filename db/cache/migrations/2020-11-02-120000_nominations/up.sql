@@ -0,0 +1,10 @@
+-- History of asset nominations changed by renomination procedure
+
+create table sql_nominations(
+    id integer PRIMARY KEY not null,
+    sql_asset_id integer not null,
+    node_id text not null,
+    ticker text not null,
+    asset_name text not null,
+    asset_description text
+);
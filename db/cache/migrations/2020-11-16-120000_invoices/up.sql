@@ -0,0 +1,12 @@
+-- Invoices registered by the node and waiting for (or received) payment
+
+create table sql_invoices(
+    id integer PRIMARY KEY not null,
+    contract_id text not null,
+    txid text not null,
+    vout integer not null,
+    blinding bigint not null,
+    amount double not null,
+    expiry datetime,
+    paid boolean not null
+);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+
+drop index sql_ledger_contract_id;
+drop table sql_ledger;
@@ -0,0 +1,14 @@
+-- Ledger of the operations changing the amount of assets controlled by the node
+
+create table sql_ledger(
+    id integer PRIMARY KEY not null,
+    contract_id text not null,
+    node_id text not null,
+    operation integer not null,
+    timestamp datetime not null,
+    witness_txid text,
+    delta double not null,
+    counterparty text
+);
+
+create index sql_ledger_contract_id on sql_ledger(contract_id);
@@ -0,0 +1,10 @@
+-- This file should undo anything in `up.sql`
+
+drop index sql_invoices_outpoint;
+drop index sql_allocations_sql_allocation_utxo_id;
+drop index sql_allocation_utxo_outpoint;
+drop index sql_allocation_utxo_sql_asset_id;
+drop index sql_inflation_sql_asset_id;
+drop index sql_nominations_sql_asset_id;
+drop index sql_issues_sql_asset_id;
+drop index sql_assets_contract_id;
@@ -0,0 +1,10 @@
+-- Indexes for the row-level lookups done by the cache on each update
+
+create index sql_assets_contract_id on sql_assets(contract_id);
+create index sql_issues_sql_asset_id on sql_issues(sql_asset_id);
+create index sql_nominations_sql_asset_id on sql_nominations(sql_asset_id);
+create index sql_inflation_sql_asset_id on sql_inflation(sql_asset_id);
+create index sql_allocation_utxo_sql_asset_id on sql_allocation_utxo(sql_asset_id);
+create index sql_allocation_utxo_outpoint on sql_allocation_utxo(txid, vout);
+create index sql_allocations_sql_allocation_utxo_id on sql_allocations(sql_allocation_utxo_id);
+create index sql_invoices_outpoint on sql_invoices(txid, vout);
@@ -11,6 +11,7 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
+use chrono::NaiveDateTime;
 use regex::Regex;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -18,9 +19,12 @@ use serde::{Deserialize, Serialize};
 use lnpbp::bitcoin::util::psbt::PartiallySignedTransaction;
 use lnpbp::bitcoin::OutPoint;
 use lnpbp::bp::blind::OutpointReveal;
-use lnpbp::rgb::{Consignment, ContractId};
+use lnpbp::rgb::{Consignment, ContractId, NodeId};
 
-use crate::fungible::{ConsealCoins, OutpointCoins, SealCoins};
+use crate::fungible::{
+    AccountingValue, ConsealCoins, OutpointCoins, SealCoins, SelectionStrategy,
+};
+use crate::util::{KnownHistory, SealSpec};
 use crate::DataFormat;
 
 #[derive(Clone, Debug, Display, LnpApi)]
@@ -49,6 +53,45 @@ pub enum Request {
     #[lnp_api(type = 0x010d)]
     Forget(::lnpbp::bitcoin::OutPoint),
 
+    #[lnp_api(type = 0x010f)]
+    Inflate(crate::api::fungible::InflateApi),
+
+    #[lnp_api(type = 0x0111)]
+    Epoch(crate::api::fungible::EpochApi),
+
+    #[lnp_api(type = 0x0113)]
+    Burn(crate::api::fungible::BurnApi),
+
+    #[lnp_api(type = 0x0115)]
+    BurnReplace(crate::api::fungible::BurnReplaceApi),
+
+    #[lnp_api(type = 0x0117)]
+    Renominate(crate::api::fungible::RenominateApi),
+
+    #[lnp_api(type = 0x0119)]
+    SplitRights(crate::api::fungible::SplitRightsApi),
+
+    #[lnp_api(type = 0x011b)]
+    Pay(crate::api::fungible::PayApi),
+
+    #[lnp_api(type = 0x011d)]
+    Invoice(crate::api::fungible::InvoiceApi),
+
+    #[lnp_api(type = 0x011f)]
+    ListInvoices(Option<ContractId>),
+
+    #[lnp_api(type = 0x0121)]
+    BatchPay(crate::api::fungible::BatchPayApi),
+
+    #[lnp_api(type = 0x0123)]
+    History(crate::api::fungible::HistoryApi),
+
+    #[lnp_api(type = 0x0125)]
+    Balance(Option<ContractId>),
+
+    #[lnp_api(type = 0x0127)]
+    Reindex(),
+
     #[lnp_api(type = 0xFF01)]
     Sync(DataFormat),
 
@@ -127,6 +170,274 @@ pub struct TransferApi {
     /// know the actual seals and only know hashes derived from seal data and
     /// blinding entropy.
     pub theirs: Vec<ConsealCoins>,
+
+    /// Receiver's allocations to the outputs of the witness transaction
+    /// paying to the receiver's addresses.
+    ///
+    /// Their seals are disclosed to the receiver, who finds the allocations
+    /// by scanning its own addresses.
+    pub theirs_addressed: Vec<SealCoins>,
+
+    /// Destination for the change: either an output of the witness
+    /// transaction being built or an existing transaction output. Receives
+    /// the difference between the inputs and the rest of the allocations.
+    pub change: Option<SealSpec>,
+
+    /// Part of the contract history already known to the receiver, which
+    /// will not be included into the consignment
+    pub known_history: Option<KnownHistory>,
+}
+
+#[derive(Clone, PartialEq, StrictEncode, StrictDecode, Debug, Display)]
+#[display(Debug)]
+pub struct PayApi {
+    /// Asset contract id
+    pub contract_id: ContractId,
+
+    /// Base layer transaction structure to use
+    pub psbt: PartiallySignedTransaction,
+
+    /// Receiver's allocations; asset inputs are selected from the known
+    /// asset allocations to cover their total amount
+    pub theirs: Vec<ConsealCoins>,
+
+    /// Receiver's allocations to the outputs of the witness transaction
+    /// paying to the receiver's addresses
+    pub theirs_addressed: Vec<SealCoins>,
+
+    /// Strategy used for the input selection
+    pub strategy: SelectionStrategy,
+
+    /// Destination for the change, if the selected inputs exceed the
+    /// transferred amount: either an output of the witness transaction
+    /// being built or an existing transaction output
+    pub change: Option<SealSpec>,
+
+    /// Part of the contract history already known to the receiver, which
+    /// will not be included into the consignment
+    pub known_history: Option<KnownHistory>,
+}
+
+#[derive(Clone, PartialEq, StrictEncode, StrictDecode, Debug, Display)]
+#[display(Debug)]
+pub struct BatchPayApi {
+    /// Asset contract id
+    pub contract_id: ContractId,
+
+    /// Base layer transaction structure to use
+    pub psbt: PartiallySignedTransaction,
+
+    /// Asset inputs; if empty, inputs are selected from the known asset
+    /// allocations using the given strategy
+    pub inputs: Vec<OutPoint>,
+
+    /// Strategy used for the automatic input selection
+    pub strategy: SelectionStrategy,
+
+    /// Allocations to the blinded outputs of all receivers; each of them
+    /// gets a separate consignment
+    pub theirs: Vec<ConsealCoins>,
+
+    /// Allocations to the outputs of the witness transaction paying to the
+    /// receiver's addresses; each of them gets a separate consignment
+    pub theirs_addressed: Vec<SealCoins>,
+
+    /// Destination for the change, if the inputs exceed the transferred
+    /// amount: either an output of the witness transaction being built or
+    /// an existing transaction output
+    pub change: Option<SealSpec>,
+
+    /// Part of the contract history already known to the receivers, which
+    /// will not be included into the consignments
+    pub known_history: Option<KnownHistory>,
+}
+
+#[derive(Clone, PartialEq, StrictEncode, StrictDecode, Debug, Display)]
+#[display(Debug)]
+pub struct InflateApi {
+    /// Asset contract id
+    pub contract_id: ContractId,
+
+    /// Base layer transaction structure to use
+    pub psbt: PartiallySignedTransaction,
+
+    /// Transaction output with the assigned inflation right, which will be
+    /// spent by the secondary issue
+    pub inflation: OutPoint,
+
+    /// State transition which has assigned the inflation right; if absent,
+    /// it is looked up among the known asset issues
+    pub parent: Option<NodeId>,
+
+    /// Allocations of the issued assets to the local party
+    pub ours: Vec<SealCoins>,
+
+    /// Allocations of the issued assets to other parties, defined with
+    /// concealed seals
+    pub theirs: Vec<ConsealCoins>,
+
+    /// Transaction output receiving the rest of the inflation allowance.
+    ///
+    /// If absent, the part of the allowance which was not issued is
+    /// irreversibly destroyed.
+    pub remainder: Option<OutPoint>,
+}
+
+#[derive(Clone, PartialEq, StrictEncode, StrictDecode, Debug, Display)]
+#[display(Debug)]
+pub struct EpochApi {
+    /// Asset contract id
+    pub contract_id: ContractId,
+
+    /// Base layer transaction structure to use
+    pub psbt: PartiallySignedTransaction,
+
+    /// Transaction output with the assigned right to open the epoch
+    pub epoch: OutPoint,
+
+    /// State transition which has assigned the epoch right; if absent, the
+    /// right is assumed to be assigned by the asset genesis
+    pub parent: Option<NodeId>,
+
+    /// Transaction output receiving the right to open the next epoch
+    pub next_epoch: Option<OutPoint>,
+
+    /// Transaction output receiving the right to burn and replace assets
+    /// within the opened epoch
+    pub burn_replace: Option<OutPoint>,
+}
+
+#[derive(Clone, PartialEq, StrictEncode, StrictDecode, Debug, Display)]
+#[display(Debug)]
+pub struct BurnApi {
+    /// Asset contract id
+    pub contract_id: ContractId,
+
+    /// Base layer transaction structure to use
+    pub psbt: PartiallySignedTransaction,
+
+    /// Transaction output with the assigned burn-and-replace right
+    pub burn_right: OutPoint,
+
+    /// State transition which has assigned the burn-and-replace right
+    pub parent: NodeId,
+
+    /// Transaction outputs which assets are burned
+    pub burn_utxos: Vec<OutPoint>,
+
+    /// Transaction output receiving the burn-and-replace right for the
+    /// subsequent operations within the same epoch
+    pub next_burn: Option<OutPoint>,
+
+    /// Proof of the burned assets history: format, which must be one of
+    /// `HistoryProofFormat` values, and the proof data
+    pub history_proof: Option<(u8, Vec<u8>)>,
+}
+
+#[derive(Clone, PartialEq, StrictEncode, StrictDecode, Debug, Display)]
+#[display(Debug)]
+pub struct BurnReplaceApi {
+    /// Burn operation details
+    pub burn: BurnApi,
+
+    /// Allocations of the replacing assets to the local party
+    pub ours: Vec<SealCoins>,
+
+    /// Allocations of the replacing assets to other parties, defined with
+    /// concealed seals
+    pub theirs: Vec<ConsealCoins>,
+}
+
+#[derive(Clone, PartialEq, StrictEncode, StrictDecode, Debug, Display)]
+#[display(Debug)]
+pub struct RenominateApi {
+    /// Asset contract id
+    pub contract_id: ContractId,
+
+    /// Base layer transaction structure to use
+    pub psbt: PartiallySignedTransaction,
+
+    /// Transaction output with the assigned renomination right
+    pub renomination: OutPoint,
+
+    /// State transition which has assigned the renomination right; if
+    /// absent, it is looked up among the nodes defining known asset
+    /// nominations
+    pub parent: Option<NodeId>,
+
+    /// New asset ticker, if it is changed
+    pub ticker: Option<String>,
+
+    /// New asset name, if it is changed
+    pub name: Option<String>,
+
+    /// New asset description (contract text), if it is changed
+    pub description: Option<String>,
+
+    /// Transaction output receiving the renomination right for the
+    /// subsequent renominations. If absent, asset nomination becomes final.
+    pub next_renomination: Option<OutPoint>,
+}
+
+#[derive(Clone, PartialEq, StrictEncode, StrictDecode, Debug, Display)]
+#[display(Debug)]
+pub struct SplitRightsApi {
+    /// Asset contract id
+    pub contract_id: ContractId,
+
+    /// Base layer transaction structure to use
+    pub psbt: PartiallySignedTransaction,
+
+    /// Transaction outputs holding the rights which are split; all asset
+    /// rights and allocations assigned to them are closed by the transition
+    pub inputs: Vec<OutPoint>,
+
+    /// New seals for the inflation rights with the inflation allowance
+    /// assigned to each of them
+    pub inflation: Vec<OutpointCoins>,
+
+    /// New seal for the renomination right
+    pub renomination: Option<OutPoint>,
+
+    /// New seal for the epoch right
+    pub epoch: Option<OutPoint>,
+
+    /// Allows to destroy renomination and epoch rights assigned to the
+    /// inputs when no new seals are given for them
+    pub destroy_rights: bool,
+
+    /// New seals for the assets allocated to the inputs
+    pub assets: Vec<SealCoins>,
+}
+
+#[derive(Clone, PartialEq, StrictEncode, StrictDecode, Debug, Display)]
+#[display(Debug)]
+pub struct InvoiceApi {
+    /// Asset contract id
+    pub contract_id: ContractId,
+
+    /// Amount of the asset to receive
+    pub amount: AccountingValue,
+
+    /// Locally-controlled outputs which may receive the assets; a separate
+    /// blinded invoice outpoint is generated and registered for each of them
+    pub outpoints: Vec<OutPoint>,
+
+    /// Time after which the invoice can't be paid
+    pub expiry: Option<NaiveDateTime>,
+}
+
+#[derive(Clone, PartialEq, StrictEncode, StrictDecode, Debug, Display)]
+#[display(Debug)]
+pub struct HistoryApi {
+    /// Asset contract id
+    pub contract_id: ContractId,
+
+    /// Number of the most recent ledger entries to skip
+    pub offset: u32,
+
+    /// Maximum number of ledger entries to return
+    pub limit: u32,
 }
 
 #[derive(Clone, StrictEncode, StrictDecode, Debug, Display)]
@@ -135,11 +446,18 @@ pub struct AcceptApi {
     /// Raw consignment data
     pub consignment: Consignment,
 
-    /// Reveal outpoints data used during invoice creation
+    /// Reveal outpoints data used during invoice creation; outpoints of the
+    /// invoices registered by the node are revealed automatically
     pub reveal_outpoints: Vec<OutpointReveal>,
+
+    /// Locally-controlled outputs which may have received the assets: either
+    /// witness transaction outputs assigned assets with disclosed seals by
+    /// pay-to-address transfers, or outputs blinded with factors derived from
+    /// the node wallet seed; usually found by scanning own addresses
+    pub witness_outpoints: Vec<OutPoint>,
 }
 
-fn ticker_validator(name: &str) -> Result<(), String> {
+pub(crate) fn ticker_validator(name: &str) -> Result<(), String> {
     let re = Regex::new(r"^[A-Z]{3,8}$").expect("Regex parse failure");
     if !re.is_match(&name) {
         Err(
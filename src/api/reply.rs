@@ -14,10 +14,13 @@
 use std::collections::BTreeMap;
 
 use lnpbp::bitcoin::OutPoint;
+use lnpbp::bp::blind::OutpointHash;
 use lnpbp::bp::Psbt;
 use lnpbp::lnp;
-use lnpbp::rgb::{AtomicValue, Consignment, ContractId};
+use lnpbp::rgb::{Anchor, AtomicValue, Consignment, ContractId, Transition};
 
+use crate::fungible::AccountingAmount;
+use crate::util::ConsignmentStats;
 use crate::DataFormat;
 
 #[cfg(feature = "node")]
@@ -66,6 +69,39 @@ pub enum Reply {
 
     #[lnp_api(type = 0xFF0C)]
     Transfer(crate::api::reply::Transfer),
+
+    #[lnp_api(type = 0xFF0D)]
+    Extensions(Vec<::lnpbp::rgb::Extension>),
+
+    #[lnp_api(type = 0xFF0E)]
+    Anchors(Vec<::lnpbp::rgb::Anchor>),
+
+    #[lnp_api(type = 0xFF0F)]
+    BatchTransfer(crate::api::reply::BatchTransfer),
+
+    #[lnp_api(type = 0xFF10)]
+    Consignment(::lnpbp::rgb::Consignment),
+
+    #[lnp_api(type = 0xFF11)]
+    SchemaTypeNames(crate::util::SchemaTypeNames),
+
+    #[lnp_api(type = 0xFF12)]
+    Payment(crate::api::reply::Payment),
+
+    #[lnp_api(type = 0xFF13)]
+    Invoices(Vec<crate::fungible::InvoiceRecord>),
+
+    #[lnp_api(type = 0xFF14)]
+    BatchPayment(crate::api::reply::BatchPayment),
+
+    #[lnp_api(type = 0xFF15)]
+    History(Vec<crate::fungible::LedgerEntry>),
+
+    #[lnp_api(type = 0xFF16)]
+    Confirmations(BTreeMap<::lnpbp::bitcoin::Txid, u32>),
+
+    #[lnp_api(type = 0xFF17)]
+    Balances(BTreeMap<ContractId, crate::api::reply::Balance>),
     /* #[lnp_api(type = 0xFF0B)]
     ValidationStatus(::lnpbp::rgb::validation::Status), */
 }
@@ -105,6 +141,60 @@ pub struct SyncFormat(pub DataFormat, pub Vec<u8>);
 pub struct Transfer {
     pub consignment: Consignment,
     pub psbt: Psbt,
+    /// Size and structure of the consignment
+    pub stats: ConsignmentStats,
+}
+
+#[derive(Clone, Debug, Display, StrictEncode, StrictDecode, Error)]
+#[display(Debug)]
+pub struct Payment {
+    /// Asset inputs selected for the payment
+    pub inputs: Vec<OutPoint>,
+    /// Amount of the asset allocated to the change output
+    pub change: AtomicValue,
+    pub transfer: Transfer,
+}
+
+#[derive(Clone, Debug, Display, StrictEncode, StrictDecode, Error)]
+#[display(Debug)]
+pub struct BatchPayment {
+    /// Asset inputs spent by the payment
+    pub inputs: Vec<OutPoint>,
+    /// Amount of the asset allocated to the change output
+    pub change: AtomicValue,
+    /// Consignments for each of the receivers, containing only the receiver's
+    /// endpoint
+    pub consignments: BTreeMap<OutpointHash, Consignment>,
+    pub psbt: Psbt,
+}
+
+/// Amount of the asset controlled by the node, split by the state of the
+/// witness transactions
+#[derive(Clone, Debug, Display, StrictEncode, StrictDecode, Error)]
+#[display(Debug)]
+pub struct Balance {
+    /// Allocations on outputs of mined transactions
+    pub confirmed: AccountingAmount,
+    /// Allocations on outputs of transactions which are not mined yet, except
+    /// the change of the transfers sent by the node
+    pub unconfirmed_incoming: AccountingAmount,
+    /// Allocations spent by the node in transactions which are not mined yet,
+    /// including the change returned to the node
+    pub reserved_outgoing: AccountingAmount,
+    /// Amounts sent by the node in recently mined transactions
+    pub recently_spent: AccountingAmount,
+}
+
+#[derive(Clone, Debug, Display, StrictEncode, StrictDecode, Error)]
+#[display(Debug)]
+pub struct BatchTransfer {
+    /// Consignments for each of the contracts committed into the witness
+    /// transaction
+    pub consignments: BTreeMap<ContractId, Consignment>,
+    pub psbt: Psbt,
+    /// Non-concealed state transitions with their anchors, which has to be
+    /// kept by the sender for its own records
+    pub disclosure: BTreeMap<ContractId, (Anchor, Transition)>,
 }
 
 #[derive(Clone, Debug, Display, StrictEncode, StrictDecode, Error)]
@@ -16,7 +16,11 @@ use std::collections::BTreeMap;
 use lnpbp::bitcoin::util::psbt::PartiallySignedTransaction as Psbt;
 use lnpbp::bitcoin::OutPoint;
 use lnpbp::bp::blind::{OutpointHash, OutpointReveal};
-use lnpbp::rgb::{Consignment, ContractId, NodeId, Transition};
+use lnpbp::rgb::{
+    Consignment, ContractId, Extension, NodeId, Schema, Transition,
+};
+
+use crate::util::{KnownHistory, SchemaTypeNames};
 
 #[derive(Clone, Debug, Display, LnpApi)]
 #[lnp_api(encoding = "strict")]
@@ -32,6 +36,12 @@ pub enum Request {
     #[lnp_api(type = 0x0105)]
     ReadSchema(::lnpbp::rgb::SchemaId),
 
+    #[lnp_api(type = 0x0107)]
+    RegisterSchema(crate::api::stash::SchemaRegistration),
+
+    #[lnp_api(type = 0x0109)]
+    ReadSchemaTypeNames(::lnpbp::rgb::SchemaId),
+
     #[lnp_api(type = 0x0201)]
     AddGenesis(::lnpbp::rgb::Genesis),
 
@@ -44,6 +54,18 @@ pub enum Request {
     #[lnp_api(type = 0x0301)]
     ReadTransitions(Vec<::lnpbp::rgb::NodeId>),
 
+    #[lnp_api(type = 0x0303)]
+    ReadExtensions(Vec<::lnpbp::rgb::NodeId>),
+
+    #[lnp_api(type = 0x0305)]
+    ReadAnchors(Vec<::lnpbp::rgb::AnchorId>),
+
+    #[lnp_api(type = 0x0307)]
+    ReadHistory(crate::api::stash::HistoryRequest),
+
+    #[lnp_api(type = 0x0309)]
+    ReadContract(::lnpbp::rgb::ContractId),
+
     #[lnp_api(type = 0x0401)]
     Consign(crate::api::stash::ConsignRequest),
 
@@ -55,6 +77,26 @@ pub enum Request {
 
     #[lnp_api(type = 0x0407)]
     Forget(Vec<(::lnpbp::rgb::NodeId, u16)>),
+
+    #[lnp_api(type = 0x0409)]
+    ConsignBatch(crate::api::stash::BatchConsignRequest),
+
+    #[lnp_api(type = 0x040b)]
+    ConsignExtension(crate::api::stash::ExtensionConsignRequest),
+
+    #[lnp_api(type = 0x040d)]
+    ConsignGenesis(crate::api::stash::GenesisConsignRequest),
+
+    #[lnp_api(type = 0x0501)]
+    Confirmations(Vec<::lnpbp::bitcoin::Txid>),
+}
+
+/// Schema accompanied with human-readable names of its types
+#[derive(Clone, StrictEncode, StrictDecode, Debug, Display)]
+#[display(Debug)]
+pub struct SchemaRegistration {
+    pub schema: Schema,
+    pub type_names: SchemaTypeNames,
 }
 
 #[derive(Clone, StrictEncode, StrictDecode, Debug, Display)]
@@ -64,10 +106,59 @@ pub struct ConsignRequest {
     pub inputs: Vec<OutPoint>,
     pub transition: Transition,
     pub other_transition_ids: BTreeMap<ContractId, NodeId>,
+    /// State transitions of other contracts listed in `other_transition_ids`
+    /// which are stored in the stash together with the main transition
+    pub other_transitions: Vec<Transition>,
+    pub outpoints: Vec<OutpointHash>,
+    pub psbt: Psbt,
+    /// Part of the contract history already known to the receiver, which
+    /// will not be included into the consignment
+    pub known_history: Option<KnownHistory>,
+}
+
+/// State transition for a single contract which has to be committed into a
+/// witness transaction shared with other contracts
+#[derive(Clone, StrictEncode, StrictDecode, Debug, Display)]
+#[display(Debug)]
+pub struct ContractTransition {
+    pub contract_id: ContractId,
+    pub transition: Transition,
     pub outpoints: Vec<OutpointHash>,
+}
+
+#[derive(Clone, StrictEncode, StrictDecode, Debug, Display)]
+#[display(Debug)]
+pub struct BatchConsignRequest {
+    /// State transitions for all contracts committed to the same witness
+    /// transaction; there must be no more than a single transition per
+    /// contract
+    pub transitions: Vec<ContractTransition>,
     pub psbt: Psbt,
 }
 
+#[derive(Clone, StrictEncode, StrictDecode, Debug, Display)]
+#[display(Debug)]
+pub struct ExtensionConsignRequest {
+    pub contract_id: ContractId,
+    pub extension: Extension,
+    pub outpoints: Vec<OutpointHash>,
+}
+
+#[derive(Clone, StrictEncode, StrictDecode, Debug, Display)]
+#[display(Debug)]
+pub struct GenesisConsignRequest {
+    pub contract_id: ContractId,
+    pub outpoints: Vec<OutpointHash>,
+}
+
+/// Request for the part of the contract history leading to the given nodes
+#[derive(Clone, StrictEncode, StrictDecode, Debug, Display)]
+#[display(Debug)]
+pub struct HistoryRequest {
+    pub contract_id: ContractId,
+    pub node_ids: Vec<NodeId>,
+}
+
 #[derive(Clone, StrictEncode, StrictDecode, Debug, Display)]
 #[display(Debug)]
 pub struct MergeRequest {
@@ -18,7 +18,7 @@ use std::path::PathBuf;
 use lnpbp::bp;
 use lnpbp::lnp::transport::zmqsocket::ZmqSocketAddr;
 
-use super::{fungible, stash, Error, Runtime};
+use super::{consignment, fungible, stash, Error, Runtime};
 use crate::constants::*;
 
 #[derive(Clap, Clone, Debug, Display)]
@@ -70,6 +70,20 @@ pub enum Command {
         subcommand: stash::GenesisCommand,
     },
 
+    /// Reads state transitions, extensions and anchors from the stash
+    Stash {
+        /// Subcommand specifying particular operation
+        #[clap(subcommand)]
+        subcommand: stash::StashCommand,
+    },
+
+    /// Offline operations on consignment files
+    Consignment {
+        /// Subcommand specifying particular operation
+        #[clap(subcommand)]
+        subcommand: consignment::ConsignmentCommand,
+    },
+
     /// Operations on fungible RGB assets (RGB-20 standard)
     Fungible {
         /// Subcommand specifying particular operation
@@ -131,6 +145,8 @@ impl Command {
             Command::Fungible { subcommand } => subcommand.exec(runtime),
             Command::Schema { subcommand } => subcommand.exec(runtime),
             Command::Genesis { subcommand } => subcommand.exec(runtime),
+            Command::Stash { subcommand } => subcommand.exec(runtime),
+            Command::Consignment { subcommand } => subcommand.exec(runtime),
         }
     }
 }
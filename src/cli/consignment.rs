@@ -0,0 +1,317 @@
+// RGB standard library
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::path::PathBuf;
+
+use lnpbp::rgb::prelude::*;
+
+use super::graph::ContractGraph;
+use crate::api::Reply;
+use crate::cli::{Error, OutputFormat, Runtime};
+use crate::fungible::Asset;
+use crate::util::file::ReadWrite;
+use crate::util::{ConsignmentStats, SchemaTypeNames};
+
+#[derive(Clap, Clone, Debug, Display)]
+#[display(Debug)]
+pub enum ConsignmentCommand {
+    /// Decodes consignment file and prints its human-readable summary
+    Inspect {
+        /// Format for information output
+        #[clap(short, long, arg_enum, default_value = "yaml")]
+        format: OutputFormat,
+
+        /// Consignment file to inspect
+        consignment: PathBuf,
+    },
+
+    /// Exports the history contained in the consignment file as a graph
+    Graph {
+        /// Format for information output
+        #[clap(short, long, arg_enum, default_value = "dot")]
+        format: OutputFormat,
+
+        /// Consignment file to export
+        consignment: PathBuf,
+    },
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(crate = "serde_crate")]
+struct ConsignmentInfo {
+    contract_id: String,
+    schema_id: String,
+    transitions_count: usize,
+    extensions_count: usize,
+    anchors_count: usize,
+    size: usize,
+    asset: Option<Asset>,
+    genesis: Vec<AssignmentInfo>,
+    transitions: Vec<NodeInfo>,
+    extensions: Vec<NodeInfo>,
+    anchors: Vec<AnchorInfo>,
+    endpoints: Vec<EndpointInfo>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(crate = "serde_crate")]
+struct NodeInfo {
+    node_id: String,
+    node_type: String,
+    anchor_id: Option<String>,
+    parents: Vec<String>,
+    assignments: Vec<AssignmentInfo>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(crate = "serde_crate")]
+pub(super) struct AssignmentInfo {
+    pub rights_type: String,
+    pub index: usize,
+    pub seal: String,
+    /// Amount is `None` when it is concealed or when the state is not a
+    /// fungible one
+    pub amount: Option<AtomicValue>,
+    pub concealed: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(crate = "serde_crate")]
+struct AnchorInfo {
+    anchor_id: String,
+    witness_txid: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(crate = "serde_crate")]
+struct EndpointInfo {
+    node_id: String,
+    seal: String,
+}
+
+impl ConsignmentCommand {
+    pub fn exec(self, runtime: Runtime) -> Result<(), Error> {
+        match self {
+            ConsignmentCommand::Inspect {
+                format,
+                ref consignment,
+            } => self.exec_inspect(runtime, format, consignment.clone()),
+            ConsignmentCommand::Graph {
+                format,
+                ref consignment,
+            } => self.exec_graph(runtime, format, consignment.clone()),
+        }
+    }
+
+    fn exec_graph(
+        &self,
+        mut runtime: Runtime,
+        format: OutputFormat,
+        filename: PathBuf,
+    ) -> Result<(), Error> {
+        let consignment = read_consignment(filename)?;
+        let type_names = registered_type_names(
+            &mut runtime,
+            consignment.genesis.schema_id(),
+        )?;
+        println!(
+            "{}",
+            ContractGraph::with(&consignment, &type_names).output(format)?
+        );
+        Ok(())
+    }
+
+    fn exec_inspect(
+        &self,
+        mut runtime: Runtime,
+        format: OutputFormat,
+        filename: PathBuf,
+    ) -> Result<(), Error> {
+        let consignment = read_consignment(filename)?;
+        let type_names = registered_type_names(
+            &mut runtime,
+            consignment.genesis.schema_id(),
+        )?;
+        let info = ConsignmentInfo::with(&consignment, &type_names)?;
+        let output = match format {
+            OutputFormat::Yaml => serde_yaml::to_string(&info)?,
+            OutputFormat::Json => serde_json::to_string(&info)?,
+            OutputFormat::Toml => toml::to_string(&info)?,
+            _ => Err(Error::FormatNotSupported)?,
+        };
+        println!("{}", output);
+        Ok(())
+    }
+}
+
+impl ConsignmentInfo {
+    fn with(
+        consignment: &Consignment,
+        type_names: &SchemaTypeNames,
+    ) -> Result<Self, Error> {
+        let genesis = &consignment.genesis;
+        let stats = ConsignmentStats::with(consignment)?;
+
+        let anchors = consignment
+            .state_transitions
+            .iter()
+            .map(|(anchor, _)| {
+                (anchor.anchor_id().to_string(), anchor.txid.to_string())
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        Ok(Self {
+            contract_id: genesis.contract_id().to_string(),
+            schema_id: genesis.schema_id().to_string(),
+            transitions_count: stats.transitions,
+            extensions_count: stats.extensions,
+            anchors_count: stats.anchors,
+            size: stats.size,
+            asset: Asset::try_from(genesis.clone()).ok(),
+            genesis: assignments_info(genesis, type_names),
+            transitions: consignment
+                .state_transitions
+                .iter()
+                .map(|(anchor, transition)| NodeInfo {
+                    node_id: transition.node_id().to_string(),
+                    node_type: type_name(
+                        &type_names.transition_types,
+                        transition.transition_type() as usize,
+                    ),
+                    anchor_id: Some(anchor.anchor_id().to_string()),
+                    parents: parents_info(transition),
+                    assignments: assignments_info(transition, type_names),
+                })
+                .collect(),
+            extensions: consignment
+                .state_extensions
+                .iter()
+                .map(|extension| NodeInfo {
+                    node_id: extension.node_id().to_string(),
+                    node_type: type_name(
+                        &type_names.extension_types,
+                        extension.extension_type() as usize,
+                    ),
+                    anchor_id: None,
+                    parents: parents_info(extension),
+                    assignments: assignments_info(extension, type_names),
+                })
+                .collect(),
+            anchors: anchors
+                .into_iter()
+                .map(|(anchor_id, witness_txid)| AnchorInfo {
+                    anchor_id,
+                    witness_txid,
+                })
+                .collect(),
+            endpoints: consignment
+                .endpoints
+                .iter()
+                .map(|(node_id, seal)| EndpointInfo {
+                    node_id: node_id.to_string(),
+                    seal: seal.to_string(),
+                })
+                .collect(),
+        })
+    }
+}
+
+fn read_consignment(filename: PathBuf) -> Result<Consignment, Error> {
+    debug!("Reading consignment from file {:?}", &filename);
+    Consignment::read_file(filename.clone()).map_err(|err| {
+        Error::InputFileFormatError(
+            format!("{:?}", filename),
+            format!("{}", err),
+        )
+    })
+}
+
+/// Requests type names for the schema from the schema registry of the stash;
+/// for the schemata without registered names numeric type ids are used
+pub(super) fn registered_type_names(
+    runtime: &mut Runtime,
+    schema_id: SchemaId,
+) -> Result<SchemaTypeNames, Error> {
+    Ok(match &*runtime.schema_type_names(schema_id)? {
+        Reply::SchemaTypeNames(type_names) => type_names.clone(),
+        _ => SchemaTypeNames::default(),
+    })
+}
+
+/// Resolves schema type id into its name, falling back to the numeric id for
+/// the types without a name
+pub(super) fn type_name(names: &BTreeMap<usize, String>, ty: usize) -> String {
+    names
+        .get(&ty)
+        .cloned()
+        .unwrap_or_else(|| format!("{:#06x}", ty))
+}
+
+fn parents_info(node: &impl Node) -> Vec<String> {
+    node.parent_owned_rights()
+        .keys()
+        .chain(node.parent_public_rights().keys())
+        .map(NodeId::to_string)
+        .collect()
+}
+
+pub(super) fn assignments_info(
+    node: &impl Node,
+    type_names: &SchemaTypeNames,
+) -> Vec<AssignmentInfo> {
+    let mut info = vec![];
+    for (ty, assignments) in node.owned_rights() {
+        let rights_type =
+            type_name(&type_names.owned_right_types, *ty as usize);
+        match assignments {
+            Assignments::Declarative(set) => {
+                info.extend(set.iter().enumerate().map(|(index, a)| {
+                    AssignmentInfo {
+                        rights_type: rights_type.clone(),
+                        index,
+                        seal: a.seal_definition_confidential().to_string(),
+                        amount: None,
+                        concealed: a.assigned_state().is_none(),
+                    }
+                }))
+            }
+            Assignments::DiscreteFiniteField(set) => {
+                info.extend(set.iter().enumerate().map(|(index, a)| {
+                    let amount = a.assigned_state().map(|state| state.value);
+                    AssignmentInfo {
+                        rights_type: rights_type.clone(),
+                        index,
+                        seal: a.seal_definition_confidential().to_string(),
+                        amount,
+                        concealed: amount.is_none(),
+                    }
+                }))
+            }
+            Assignments::CustomData(set) => {
+                info.extend(set.iter().enumerate().map(|(index, a)| {
+                    AssignmentInfo {
+                        rights_type: rights_type.clone(),
+                        index,
+                        seal: a.seal_definition_confidential().to_string(),
+                        amount: None,
+                        concealed: a.assigned_state().is_none(),
+                    }
+                }))
+            }
+        }
+    }
+    info
+}
@@ -11,26 +11,36 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
+use chrono::{Duration, Utc};
+use core::str::FromStr;
 use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 
 use lnpbp::bitcoin::consensus::{Decodable, Encodable};
+use lnpbp::bitcoin::secp256k1::SecretKey;
 use lnpbp::bitcoin::util::psbt::PartiallySignedTransaction;
-use lnpbp::bitcoin::OutPoint;
+use lnpbp::bitcoin::{Address, OutPoint};
 use lnpbp::bp::blind::OutpointReveal;
 use lnpbp::bp::psbt::ProprietaryKeyMap;
 use lnpbp::client_side_validation::Conceal;
+use lnpbp::hex::FromHex;
 use lnpbp::rgb::prelude::*;
 use lnpbp::strict_encoding::{strict_deserialize, strict_serialize};
 
 use super::{Error, OutputFormat, Runtime};
-use crate::api::fungible::{AcceptApi, Issue, TransferApi};
+use crate::api::fungible::{
+    ticker_validator, AcceptApi, BatchPayApi, BurnApi, BurnReplaceApi,
+    EpochApi, HistoryApi, InflateApi, InvoiceApi, Issue, PayApi, RenominateApi,
+    SplitRightsApi, TransferApi,
+};
 use crate::api::{reply, Reply};
 use crate::fungible::{
-    AccountingValue, Asset, ConsealCoins, Invoice, Outpoint, SealCoins,
+    AccountingValue, Asset, ConsealCoins, Invoice, Outpoint, OutpointCoins,
+    OutpointDescriptor, SealCoins, SelectionStrategy,
 };
 use crate::util::file::ReadWrite;
+use crate::util::{KnownHistory, SealSpec};
 use crate::DataFormat;
 
 #[derive(Clap, Clone, Debug, Display)]
@@ -65,9 +75,64 @@ pub enum Command {
     /// Create an invoice
     Invoice(InvoiceCli),
 
+    /// Lists invoices registered by the node together with their status
+    Invoices {
+        /// Show only invoices for the given asset
+        #[clap(parse(try_from_str = ContractId::from_bech32_str))]
+        asset: Option<ContractId>,
+    },
+
+    /// Shows ledger of the operations changing the amount of the asset
+    /// controlled by the node, starting from the most recent one
+    History {
+        /// Bech32 representation of the asset ID
+        #[clap(parse(try_from_str = ContractId::from_bech32_str))]
+        asset: ContractId,
+
+        /// Number of the most recent entries to skip
+        #[clap(short, long, default_value = "0")]
+        offset: u32,
+
+        /// Maximum number of entries to show
+        #[clap(short, long, default_value = "20")]
+        limit: u32,
+    },
+
+    /// Shows amounts of the assets controlled by the node
+    Balance {
+        /// Show balance only for the given asset
+        #[clap(parse(try_from_str = ContractId::from_bech32_str))]
+        asset: Option<ContractId>,
+    },
+
     /// Do a transfer of some requested asset to another party
     Transfer(TransferCli),
 
+    /// Pays multiple invoices for the same asset with a single witness
+    /// transaction, producing a separate consignment for each of the invoices
+    BatchPay(BatchPayCli),
+
+    /// Issues additional amount of an asset by spending inflation right
+    Inflate(InflateCli),
+
+    /// Opens new epoch for burning and replacing assets
+    Epoch(EpochCli),
+
+    /// Burns assets allocated to the given outputs
+    Burn(BurnCli),
+
+    /// Burns assets allocated to the given outputs and replaces them with
+    /// new allocations
+    BurnReplace(BurnReplaceCli),
+
+    /// Changes asset ticker, name and/or description by spending
+    /// renomination right
+    Renominate(RenominateCli),
+
+    /// Moves asset rights and allocations from the given outputs to new
+    /// outputs, splitting them between the outputs when needed
+    SplitRights(SplitRightsCli),
+
     /// Do a transfer of some requested asset to another party
     Validate {
         /// Consignment file
@@ -80,11 +145,15 @@ pub enum Command {
         consignment: PathBuf,
 
         /// Locally-controlled outpoint (specified when the invoice was
-        /// created)
-        outpoint: OutPoint,
+        /// created) or, for pay-to-address invoices, the witness transaction
+        /// output paying to the invoice address; not required for invoices
+        /// registered by the node
+        outpoint: Option<OutPoint>,
 
-        /// Outpoint blinding factor (generated when the invoice was created)
-        blinding_factor: u64,
+        /// Outpoint blinding factor (generated when the invoice was created);
+        /// absent for pay-to-address invoices and for blinding factors derived
+        /// from the node wallet seed
+        blinding_factor: Option<u64>,
     },
 
     Forget {
@@ -92,6 +161,9 @@ pub enum Command {
         /// has to be forgotten
         outpoint: OutPoint,
     },
+
+    /// Rebuilds cached asset data from the contract data kept in the stash
+    Reindex,
 }
 
 #[derive(Clap, Clone, PartialEq, Debug, Display)]
@@ -106,15 +178,52 @@ pub struct InvoiceCli {
 
     /// Receive assets to a given bitcoin address or UTXO
     pub outpoint: OutPoint,
+
+    /// Alternative bitcoin addresses or UTXOs, which may receive the assets
+    /// instead of the main one
+    #[clap(long = "alt")]
+    pub alternatives: Vec<OutpointDescriptor>,
+
+    /// Invoice validity period, in seconds
+    #[clap(short, long)]
+    pub expiry: Option<i64>,
+
+    /// Human-readable payment description
+    #[clap(short, long)]
+    pub memo: Option<String>,
+
+    /// Merchant payment reference id
+    #[clap(long = "ref")]
+    pub merchant_ref: Option<String>,
+
+    /// Endpoint of the node accepting consignments for the invoice
+    #[clap(long)]
+    pub endpoint: Option<String>,
+
+    /// File with hex-encoded node private key for signing the invoice
+    #[clap(long)]
+    pub sign: Option<PathBuf>,
 }
 
 #[derive(Clap, Clone, PartialEq, Debug, Display)]
 #[display(Debug)]
 pub struct TransferCli {
-    /// Asset inputs
+    /// Asset inputs; if none are given, inputs are selected automatically
+    /// from the known asset allocations
     #[clap(short = 'i', long = "input", min_values = 1)]
     pub inputs: Vec<OutPoint>,
 
+    /// Strategy for the automatic input selection: largest-first,
+    /// fewest-inputs or privacy
+    #[clap(long, default_value = "fewest-inputs")]
+    pub strategy: SelectionStrategy,
+
+    /// Output receiving the change, if the inputs exceed the transferred
+    /// and allocated amounts; either <vout> of the witness transaction or
+    /// an existing <txid>:<vout>
+    #[clap(short, long)]
+    pub change: Option<SealSpec>,
+
     /// Adds additional asset allocations; MUST use transaction inputs
     /// controlled by the local party
     #[clap(short, long)]
@@ -131,6 +240,269 @@ pub struct TransferCli {
 
     /// File to save updated partially-signed bitcoin transaction to
     pub transaction: PathBuf,
+
+    /// Ids of the contract nodes already known to the receiver, which will
+    /// not be included into the consignment
+    #[clap(long = "known", parse(try_from_str = NodeId::from_hex))]
+    pub known_nodes: Vec<NodeId>,
+}
+
+#[derive(Clap, Clone, PartialEq, Debug, Display)]
+#[display(Debug)]
+pub struct BatchPayCli {
+    /// Asset inputs; if none are given, inputs are selected automatically
+    /// from the known asset allocations
+    #[clap(short = 'i', long = "input", min_values = 1)]
+    pub inputs: Vec<OutPoint>,
+
+    /// Strategy for the automatic input selection: largest-first,
+    /// fewest-inputs or privacy
+    #[clap(long, default_value = "fewest-inputs")]
+    pub strategy: SelectionStrategy,
+
+    /// Output receiving the change, if the inputs exceed the transferred
+    /// amount; either <vout> of the witness transaction or an existing
+    /// <txid>:<vout>
+    #[clap(short, long)]
+    pub change: Option<SealSpec>,
+
+    /// Ids of the contract nodes already known to the receivers, which will
+    /// not be included into the consignments
+    #[clap(long = "known", parse(try_from_str = NodeId::from_hex))]
+    pub known_nodes: Vec<NodeId>,
+
+    /// Read partially-signed transaction prototype
+    pub prototype: PathBuf,
+
+    /// Directory to save consignments to; each consignment is named after
+    /// the receiver's blinded outpoint
+    pub consignments: PathBuf,
+
+    /// File to save updated partially-signed bitcoin transaction to
+    pub transaction: PathBuf,
+
+    /// Invoices to pay; all of them must be for the same asset
+    #[clap(required = true, min_values = 1)]
+    pub invoices: Vec<Invoice>,
+}
+
+#[derive(Clap, Clone, PartialEq, Debug, Display)]
+#[display(Debug)]
+pub struct InflateCli {
+    /// Asset to inflate
+    #[clap(parse(try_from_str = ContractId::from_bech32_str))]
+    pub asset: ContractId,
+
+    /// Output with the assigned inflation right which will be spent
+    pub inflation: OutPoint,
+
+    /// Id of the state transition which has assigned the inflation right; if
+    /// absent, it is looked up among the known asset issues
+    #[clap(long, parse(try_from_str = NodeId::from_hex))]
+    pub parent: Option<NodeId>,
+
+    /// Allocations of the issued assets to locally-controlled outputs, in
+    /// form of <amount>@<txid>:<vout>
+    #[clap(short, long)]
+    pub allocate: Vec<SealCoins>,
+
+    /// Allocations of the issued assets to blinded outputs of other parties,
+    /// in form of <amount>@<blinded_utxo>
+    #[clap(short, long)]
+    pub send: Vec<ConsealCoins>,
+
+    /// Output receiving the rest of the inflation allowance; if absent, the
+    /// allowance which was not issued is destroyed
+    #[clap(short, long)]
+    pub remainder: Option<OutPoint>,
+
+    /// Read partially-signed transaction prototype
+    pub prototype: PathBuf,
+
+    /// File to save consignment to
+    pub consignment: PathBuf,
+
+    /// File to save updated partially-signed bitcoin transaction to
+    pub transaction: PathBuf,
+}
+
+#[derive(Clap, Clone, PartialEq, Debug, Display)]
+#[display(Debug)]
+pub struct EpochCli {
+    /// Asset for which the epoch is opened
+    #[clap(parse(try_from_str = ContractId::from_bech32_str))]
+    pub asset: ContractId,
+
+    /// Output with the assigned epoch right which will be spent
+    pub epoch: OutPoint,
+
+    /// Id of the state transition which has assigned the epoch right; if
+    /// absent, the right is assumed to be assigned by the asset genesis
+    #[clap(long, parse(try_from_str = NodeId::from_hex))]
+    pub parent: Option<NodeId>,
+
+    /// Output receiving the right to open the next epoch
+    #[clap(short, long)]
+    pub next_epoch: Option<OutPoint>,
+
+    /// Output receiving the right to burn and replace assets within the
+    /// opened epoch
+    #[clap(short, long)]
+    pub burn_replace: Option<OutPoint>,
+
+    /// Read partially-signed transaction prototype
+    pub prototype: PathBuf,
+
+    /// File to save consignment to
+    pub consignment: PathBuf,
+
+    /// File to save updated partially-signed bitcoin transaction to
+    pub transaction: PathBuf,
+}
+
+#[derive(Clap, Clone, PartialEq, Debug, Display)]
+#[display(Debug)]
+pub struct BurnCli {
+    /// Asset to burn
+    #[clap(parse(try_from_str = ContractId::from_bech32_str))]
+    pub asset: ContractId,
+
+    /// Output with the assigned burn-and-replace right which will be spent
+    pub burn_right: OutPoint,
+
+    /// Id of the state transition which has assigned the burn-and-replace
+    /// right
+    #[clap(parse(try_from_str = NodeId::from_hex))]
+    pub parent: NodeId,
+
+    /// Outputs which assets are burned
+    #[clap(short = 'u', long = "utxo", min_values = 1)]
+    pub burn_utxos: Vec<OutPoint>,
+
+    /// Output receiving the burn-and-replace right for the subsequent
+    /// operations within the same epoch
+    #[clap(short, long)]
+    pub next_burn: Option<OutPoint>,
+
+    /// Format of the burned assets history proof
+    #[clap(long, requires = "proof")]
+    pub proof_format: Option<u8>,
+
+    /// File containing burned assets history proof
+    #[clap(long, requires = "proof-format")]
+    pub proof: Option<PathBuf>,
+
+    /// Read partially-signed transaction prototype
+    pub prototype: PathBuf,
+
+    /// File to save consignment to
+    pub consignment: PathBuf,
+
+    /// File to save updated partially-signed bitcoin transaction to
+    pub transaction: PathBuf,
+}
+
+#[derive(Clap, Clone, PartialEq, Debug, Display)]
+#[display(Debug)]
+pub struct BurnReplaceCli {
+    #[clap(flatten)]
+    pub burn: BurnCli,
+
+    /// Allocations of the replacing assets to locally-controlled outputs, in
+    /// form of <amount>@<txid>:<vout>
+    #[clap(short, long)]
+    pub allocate: Vec<SealCoins>,
+
+    /// Allocations of the replacing assets to blinded outputs of other
+    /// parties, in form of <amount>@<blinded_utxo>
+    #[clap(short, long)]
+    pub send: Vec<ConsealCoins>,
+}
+
+#[derive(Clap, Clone, PartialEq, Debug, Display)]
+#[display(Debug)]
+pub struct RenominateCli {
+    /// Asset to renominate
+    #[clap(parse(try_from_str = ContractId::from_bech32_str))]
+    pub asset: ContractId,
+
+    /// Output with the assigned renomination right which will be spent
+    pub renomination: OutPoint,
+
+    /// Id of the state transition which has assigned the renomination right;
+    /// if absent, it is looked up among the known asset nominations
+    #[clap(long, parse(try_from_str = NodeId::from_hex))]
+    pub parent: Option<NodeId>,
+
+    /// New asset ticker (up to 8 characters, always converted to uppercase)
+    #[clap(short, long, validator = ticker_validator)]
+    pub ticker: Option<String>,
+
+    /// New asset name (up to 32 characters)
+    #[clap(short, long)]
+    pub name: Option<String>,
+
+    /// New asset description
+    #[clap(short, long)]
+    pub description: Option<String>,
+
+    /// Output receiving the renomination right for the subsequent
+    /// renominations; if absent, asset nomination becomes final
+    #[clap(long = "next")]
+    pub next_renomination: Option<OutPoint>,
+
+    /// Read partially-signed transaction prototype
+    pub prototype: PathBuf,
+
+    /// File to save consignment to
+    pub consignment: PathBuf,
+
+    /// File to save updated partially-signed bitcoin transaction to
+    pub transaction: PathBuf,
+}
+
+#[derive(Clap, Clone, PartialEq, Debug, Display)]
+#[display(Debug)]
+pub struct SplitRightsCli {
+    /// Asset which rights are split
+    #[clap(parse(try_from_str = ContractId::from_bech32_str))]
+    pub asset: ContractId,
+
+    /// Outputs holding the rights which will be spent
+    #[clap(short = 'i', long = "input", min_values = 1)]
+    pub inputs: Vec<OutPoint>,
+
+    /// New outputs for the inflation rights, in form of
+    /// <allowance>@<txid>:<vout>
+    #[clap(long)]
+    pub inflation: Vec<OutpointCoins>,
+
+    /// New output for the renomination right
+    #[clap(long)]
+    pub renomination: Option<OutPoint>,
+
+    /// New output for the epoch right
+    #[clap(long)]
+    pub epoch: Option<OutPoint>,
+
+    /// Destroy renomination and epoch rights of the inputs for which no new
+    /// outputs are given
+    #[clap(long)]
+    pub destroy_rights: bool,
+
+    /// New allocations of the assets assigned to the inputs, in form of
+    /// <amount>@<txid>:<vout>
+    #[clap(short, long)]
+    pub allocate: Vec<SealCoins>,
+
+    /// Read partially-signed transaction prototype
+    pub prototype: PathBuf,
+
+    /// File to save consignment to
+    pub consignment: PathBuf,
+
+    /// File to save updated partially-signed bitcoin transaction to
+    pub transaction: PathBuf,
 }
 
 impl Command {
@@ -146,6 +518,20 @@ impl Command {
             Command::Invoice(invoice) => invoice.exec(runtime),
             Command::Issue(issue) => issue.exec(runtime),
             Command::Transfer(transfer) => transfer.exec(runtime),
+            Command::BatchPay(batch) => batch.exec(runtime),
+            Command::Inflate(inflate) => inflate.exec(runtime),
+            Command::Epoch(epoch) => epoch.exec(runtime),
+            Command::Burn(burn) => burn.exec(runtime),
+            Command::BurnReplace(burn_replace) => burn_replace.exec(runtime),
+            Command::Renominate(renominate) => renominate.exec(runtime),
+            Command::SplitRights(split) => split.exec(runtime),
+            Command::Invoices { asset } => self.exec_invoices(runtime, asset),
+            Command::Balance { asset } => self.exec_balance(runtime, asset),
+            Command::History {
+                asset,
+                offset,
+                limit,
+            } => self.exec_history(runtime, asset, offset, limit),
             Command::Validate { ref consignment } => {
                 self.exec_validate(runtime, consignment.clone())
             }
@@ -160,6 +546,7 @@ impl Command {
                 blinding_factor,
             ),
             Command::Forget { outpoint } => self.exec_forget(runtime, outpoint),
+            Command::Reindex => self.exec_reindex(runtime),
         }
     }
 
@@ -186,7 +573,8 @@ impl Command {
                         map! {
                             "id" => a.id().to_string(),
                             "ticker" => a.ticker().clone(),
-                            "name" => a.name().clone()
+                            "name" => a.name().clone(),
+                            "description" => a.description().clone().unwrap_or_default()
                         }
                     })
                     .collect();
@@ -269,6 +657,113 @@ impl Command {
         Ok(())
     }
 
+    fn exec_invoices(
+        &self,
+        mut runtime: Runtime,
+        asset_id: Option<ContractId>,
+    ) -> Result<(), Error> {
+        match &*runtime.list_invoices(asset_id)? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::Invoices(invoices) => {
+                for invoice in invoices {
+                    print!(
+                        "{} {} {}@{} {}",
+                        invoice.contract_id(),
+                        invoice.outpoint(),
+                        invoice.amount(),
+                        invoice.outpoint_hash(),
+                        invoice.status()
+                    );
+                    if let Some(expiry) = invoice.expiry() {
+                        print!(" {}", expiry);
+                    }
+                    println!();
+                }
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn exec_history(
+        &self,
+        mut runtime: Runtime,
+        contract_id: ContractId,
+        offset: u32,
+        limit: u32,
+    ) -> Result<(), Error> {
+        let api = HistoryApi {
+            contract_id,
+            offset,
+            limit,
+        };
+        match &*runtime.history(api)? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::History(entries) => {
+                for entry in entries {
+                    print!(
+                        "{} {} {:+} {}",
+                        entry.timestamp(),
+                        entry.operation(),
+                        entry.delta(),
+                        entry.node_id()
+                    );
+                    if let Some(txid) = entry.witness_txid() {
+                        print!(" {}", txid);
+                    }
+                    if let Some(counterparty) = entry.counterparty() {
+                        print!(" {}", Outpoint::BlindedUtxo(*counterparty));
+                    }
+                    println!();
+                }
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn exec_balance(
+        &self,
+        mut runtime: Runtime,
+        asset_id: Option<ContractId>,
+    ) -> Result<(), Error> {
+        match &*runtime.balance(asset_id)? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::Balances(balances) => {
+                for (contract_id, balance) in balances {
+                    println!(
+                        "{} confirmed: {}, incoming: {}, outgoing: {}, spent: {}",
+                        contract_id,
+                        balance.confirmed.accounting_value(),
+                        balance.unconfirmed_incoming.accounting_value(),
+                        balance.reserved_outgoing.accounting_value(),
+                        balance.recently_spent.accounting_value()
+                    );
+                }
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+        Ok(())
+    }
+
     fn exec_validate(
         &self,
         mut runtime: Runtime,
@@ -306,8 +801,8 @@ impl Command {
         &self,
         mut runtime: Runtime,
         filename: PathBuf,
-        outpoint: OutPoint,
-        blinding_factor: u64,
+        outpoint: Option<OutPoint>,
+        blinding_factor: Option<u64>,
     ) -> Result<(), Error> {
         info!("Accepting asset transfer...");
 
@@ -321,24 +816,40 @@ impl Command {
             })?;
         trace!("{:?}", strict_serialize(&consignment));
 
-        let api = if let Some((_, outpoint_hash)) = consignment.endpoints.get(0)
-        {
-            let outpoint_reveal = OutpointReveal {
-                blinding: blinding_factor,
-                txid: outpoint.txid,
-                vout: outpoint.vout as u32,
-            };
-            if outpoint_reveal.conceal() != *outpoint_hash {
-                eprintln!("The provided outpoint and blinding factors does not match outpoint from the consignment");
-                Err(Error::DataInconsistency)?
+        let api = match (outpoint, blinding_factor) {
+            (Some(outpoint), Some(blinding_factor)) => {
+                let (_, outpoint_hash) =
+                    consignment.endpoints.get(0).ok_or_else(|| {
+                        eprintln!("Consignment does not contain any endpoints");
+                        Error::DataInconsistency
+                    })?;
+                let outpoint_reveal = OutpointReveal {
+                    blinding: blinding_factor,
+                    txid: outpoint.txid,
+                    vout: outpoint.vout as u32,
+                };
+                if outpoint_reveal.conceal() != *outpoint_hash {
+                    eprintln!("The provided outpoint and blinding factors does not match outpoint from the consignment");
+                    Err(Error::DataInconsistency)?
+                }
+                AcceptApi {
+                    consignment,
+                    reveal_outpoints: vec![outpoint_reveal],
+                    witness_outpoints: vec![],
+                }
             }
-            AcceptApi {
+            (Some(outpoint), None) => AcceptApi {
                 consignment,
-                reveal_outpoints: vec![outpoint_reveal],
-            }
-        } else {
-            eprintln!("Currently, this command-line tool is unable to accept consignments containing more than a single locally-controlled output point");
-            Err(Error::UnsupportedFunctionality)?
+                reveal_outpoints: vec![],
+                witness_outpoints: vec![outpoint],
+            },
+            // Endpoints are matched against the invoices registered by the
+            // node
+            (None, _) => AcceptApi {
+                consignment,
+                reveal_outpoints: vec![],
+                witness_outpoints: vec![],
+            },
         };
 
         match &*runtime.accept(api)? {
@@ -383,6 +894,26 @@ impl Command {
 
         Ok(())
     }
+
+    fn exec_reindex(&self, mut runtime: Runtime) -> Result<(), Error> {
+        info!("Rebuilding cached asset data from the stash...");
+
+        match &*runtime.reindex()? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::Success => {
+                eprintln!("Asset data successfully rebuilt.");
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl Issue {
@@ -409,117 +940,535 @@ impl Issue {
 }
 
 impl InvoiceCli {
-    pub fn exec(self, _: Runtime) -> Result<(), Error> {
+    pub fn exec(self, mut runtime: Runtime) -> Result<(), Error> {
         info!("Generating invoice ...");
         debug!("{}", self.clone());
 
-        let outpoint_reveal = OutpointReveal::from(self.outpoint);
-        let invoice = Invoice {
+        // Blinded outpoints are generated and registered by the node, so it
+        // will be able to accept the payment without the blinding factors
+        let mut outpoints = vec![self.outpoint];
+        outpoints.extend(self.alternatives.iter().filter_map(|alternative| {
+            match alternative {
+                OutpointDescriptor::Utxo(outpoint) => Some(*outpoint),
+                OutpointDescriptor::Address(_) => None,
+            }
+        }));
+        let expiry = self
+            .expiry
+            .map(|period| Utc::now() + Duration::seconds(period));
+        let api = InvoiceApi {
             contract_id: self.asset,
-            outpoint: Outpoint::BlindedUtxo(outpoint_reveal.conceal()),
             amount: self.amount,
+            outpoints,
+            expiry: expiry.map(|expiry| expiry.naive_utc()),
+        };
+
+        let records = match &*runtime.invoice(api)? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+                return Ok(());
+            }
+            Reply::Invoices(records) => records.clone(),
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+                return Ok(());
+            }
         };
 
+        let mut blinded = records
+            .iter()
+            .map(|record| Outpoint::BlindedUtxo(record.outpoint_hash()));
+        let mut invoice = Invoice::with(
+            self.asset,
+            blinded.next().ok_or(Error::DataInconsistency)?,
+            self.amount,
+        );
+        for alternative in self.alternatives {
+            invoice.alternatives.push(match alternative {
+                OutpointDescriptor::Utxo(_) => {
+                    blinded.next().ok_or(Error::DataInconsistency)?
+                }
+                OutpointDescriptor::Address(address) => {
+                    Outpoint::Address(address)
+                }
+            });
+        }
+        invoice.expiry = expiry;
+        invoice.memo = self.memo;
+        invoice.merchant_ref = self.merchant_ref;
+        invoice.endpoint = self.endpoint;
+        if let Some(filename) = self.sign {
+            let node_key = fs::read_to_string(&filename)
+                .ok()
+                .and_then(|hex| SecretKey::from_str(hex.trim()).ok())
+                .ok_or_else(|| {
+                    Error::InputFileFormatError(
+                        format!("{:?}", filename),
+                        s!("hex-encoded private key is expected"),
+                    )
+                })?;
+            invoice.sign(&node_key);
+        }
+
         eprint!("Invoice: ");
         println!("{}", invoice);
-        eprint!("Outpoint blinding factor: ");
-        println!("{}", outpoint_reveal.blinding);
+        for record in records {
+            eprint!("Outpoint {} blinding factor: ", record.outpoint());
+            println!("{}", record.blinding());
+        }
 
         Ok(())
     }
 }
 
 impl TransferCli {
-    #[allow(unreachable_code)]
     pub fn exec(self, mut runtime: Runtime) -> Result<(), Error> {
         info!("Transferring asset ...");
         debug!("{}", self.clone());
+        if let Some(ref memo) = self.invoice.memo {
+            eprintln!("Invoice memo: {}", memo);
+        }
 
-        let seal_confidential = match self.invoice.outpoint {
-            Outpoint::BlindedUtxo(outpoint_hash) => outpoint_hash,
-            Outpoint::Address(_address) => {
-                // To do a pay-to-address, we need to spend some bitcoins,
-                // which we have to take from somewhere. While payee can
-                // provide us with additional input, it's not part of the
-                // invoicing protocol + does not make a lot of sense, since
-                // the same input can be simply used by Utxo scheme
-                unimplemented!();
-                SealDefinition::WitnessVout {
-                    vout: 0,
-                    blinding: 0,
+        let psbt = read_psbt(self.prototype.clone())?;
+        let mut theirs = vec![];
+        let mut theirs_addressed = vec![];
+        add_invoice_allocation(
+            &psbt,
+            &self.invoice,
+            &mut theirs,
+            &mut theirs_addressed,
+        )?;
+
+        let known_history = known_history(&self.known_nodes);
+
+        let reply = if self.inputs.is_empty() {
+            runtime.pay(PayApi {
+                psbt,
+                contract_id: self.invoice.contract_id,
+                theirs,
+                theirs_addressed,
+                strategy: self.strategy,
+                change: self.change,
+                known_history,
+            })?
+        } else {
+            runtime.transfer(TransferApi {
+                psbt,
+                contract_id: self.invoice.contract_id,
+                inputs: self.inputs.clone(),
+                ours: self.allocate.clone(),
+                theirs,
+                theirs_addressed,
+                change: self.change,
+                known_history,
+            })?
+        };
+        info!("Reply: {}", reply);
+        match &*reply {
+            Reply::Failure(failure) => {
+                eprintln!("Transfer failed: {}", failure);
+            }
+            Reply::Transfer(transfer) => self.write_transfer(transfer)?,
+            Reply::Payment(payment) => {
+                eprintln!("Selected asset inputs:");
+                for outpoint in &payment.inputs {
+                    eprintln!("\t{}", outpoint);
                 }
-                .conceal()
+                eprintln!("Change: {}", payment.change);
+                self.write_transfer(&payment.transfer)?;
             }
-        };
+            _ => (),
+        }
 
-        debug!(
-            "Reading partially-signed transaction from file {:?}",
-            self.prototype
+        Ok(())
+    }
+
+    fn write_transfer(&self, transfer: &reply::Transfer) -> Result<(), Error> {
+        trace!("{:?}", strict_serialize(&transfer.consignment));
+        transfer.consignment.write_file(self.consignment.clone())?;
+        let out_file = fs::File::create(&self.transaction)
+            .expect("can't create output transaction file");
+        transfer.psbt.consensus_encode(out_file)?;
+        eprintln!("Consignment contains {}", transfer.stats);
+        println!(
+            "Transfer succeeded, consignment data are written to {:?}, partially signed witness transaction to {:?}",
+            self.consignment, self.transaction
         );
-        let filepath = format!("{:?}", &self.prototype);
-        let file = fs::File::open(self.prototype)
-            .map_err(|_| Error::InputFileIoError(format!("{:?}", filepath)))?;
-        let mut psbt = PartiallySignedTransaction::consensus_decode(file)
-            .map_err(|err| {
-                Error::InputFileFormatError(
-                    format!("{:?}", filepath),
-                    format!("{}", err),
-                )
-            })?;
+        Ok(())
+    }
+}
 
-        for (index, output) in &mut psbt.outputs.iter_mut().enumerate() {
-            if let Some(key) = output.hd_keypaths.keys().next() {
-                let key = key.clone();
-                output.insert_proprietary_key(
-                    b"RGB".to_vec(),
-                    PSBT_OUT_PUBKEY,
-                    vec![],
-                    &key.key,
-                );
-                debug!("Output #{} commitment key will be {}", index, key);
-            } else {
-                warn!(
-                    "No public key information found for output #{}; \
-                    LNPBP1/2 commitment will be impossible.\
-                    In order to allow commitment pls add known keys derivation \
-                    information to PSBT output map",
-                    index
-                );
-            }
+impl BatchPayCli {
+    pub fn exec(self, mut runtime: Runtime) -> Result<(), Error> {
+        info!("Paying invoices ...");
+        debug!("{}", self.clone());
+
+        let contract_id = self.invoices[0].contract_id;
+        if self
+            .invoices
+            .iter()
+            .any(|invoice| invoice.contract_id != contract_id)
+        {
+            eprintln!("All invoices must be issued for the same asset");
+            Err(Error::DataInconsistency)?
         }
-        trace!("{:?}", psbt);
 
-        let api = TransferApi {
-            psbt,
-            contract_id: self.invoice.contract_id,
-            inputs: self.inputs,
-            ours: self.allocate,
-            theirs: vec![ConsealCoins {
-                coins: self.invoice.amount,
-                seal_confidential,
-            }],
-        };
+        let psbt = read_psbt(self.prototype.clone())?;
+        let mut theirs = vec![];
+        let mut theirs_addressed = vec![];
+        for invoice in &self.invoices {
+            add_invoice_allocation(
+                &psbt,
+                invoice,
+                &mut theirs,
+                &mut theirs_addressed,
+            )?;
+        }
 
-        let reply = runtime.transfer(api)?;
+        let reply = runtime.batch_pay(BatchPayApi {
+            contract_id,
+            psbt,
+            inputs: self.inputs.clone(),
+            strategy: self.strategy,
+            theirs,
+            theirs_addressed,
+            change: self.change,
+            known_history: known_history(&self.known_nodes),
+        })?;
         info!("Reply: {}", reply);
         match &*reply {
             Reply::Failure(failure) => {
-                eprintln!("Transfer failed: {}", failure);
+                eprintln!("Payment failed: {}", failure);
             }
-            Reply::Transfer(transfer) => {
-                trace!("{:?}", strict_serialize(&transfer.consignment));
-                transfer.consignment.write_file(self.consignment.clone())?;
+            Reply::BatchPayment(payment) => {
+                eprintln!("Asset inputs:");
+                for outpoint in &payment.inputs {
+                    eprintln!("\t{}", outpoint);
+                }
+                eprintln!("Change: {}", payment.change);
+                fs::create_dir_all(&self.consignments).map_err(|_| {
+                    Error::InputFileIoError(format!("{:?}", self.consignments))
+                })?;
+                for (endpoint, consignment) in &payment.consignments {
+                    let filename = self
+                        .consignments
+                        .join(endpoint.to_string())
+                        .with_extension("rgb");
+                    consignment.write_file(filename.clone())?;
+                    eprintln!(
+                        "Consignment for {} is written to {:?}",
+                        endpoint, filename
+                    );
+                }
                 let out_file = fs::File::create(&self.transaction)
                     .expect("can't create output transaction file");
-                transfer.psbt.consensus_encode(out_file)?;
+                payment.psbt.consensus_encode(out_file)?;
                 println!(
-                    "Transfer succeeded, consignment data are written to {:?}, partially signed witness transaction to {:?}",
-                    self.consignment, self.transaction
+                    "Payment succeeded, {} consignments are written to {:?}, partially signed witness transaction to {:?}",
+                    payment.consignments.len(), self.consignments, self.transaction
+                );
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
                 );
             }
-            _ => (),
         }
 
         Ok(())
     }
 }
+
+impl InflateCli {
+    pub fn exec(self, mut runtime: Runtime) -> Result<(), Error> {
+        info!("Inflating asset ...");
+        debug!("{}", self.clone());
+
+        let psbt = read_psbt(self.prototype)?;
+
+        let api = InflateApi {
+            psbt,
+            contract_id: self.asset,
+            inflation: self.inflation,
+            parent: self.parent,
+            ours: self.allocate,
+            theirs: self.send,
+            remainder: self.remainder,
+        };
+
+        let reply = runtime.inflate(api)?;
+        info!("Reply: {}", reply);
+        write_transfer(&reply, "Inflation", self.consignment, self.transaction)
+    }
+}
+
+impl EpochCli {
+    pub fn exec(self, mut runtime: Runtime) -> Result<(), Error> {
+        info!("Opening epoch ...");
+        debug!("{}", self.clone());
+
+        let api = EpochApi {
+            psbt: read_psbt(self.prototype)?,
+            contract_id: self.asset,
+            epoch: self.epoch,
+            parent: self.parent,
+            next_epoch: self.next_epoch,
+            burn_replace: self.burn_replace,
+        };
+
+        let reply = runtime.epoch(api)?;
+        info!("Reply: {}", reply);
+        write_transfer(
+            &reply,
+            "Epoch opening",
+            self.consignment,
+            self.transaction,
+        )
+    }
+}
+
+impl BurnCli {
+    pub fn exec(self, mut runtime: Runtime) -> Result<(), Error> {
+        info!("Burning asset ...");
+        debug!("{}", self.clone());
+
+        let consignment = self.consignment.clone();
+        let transaction = self.transaction.clone();
+        let reply = runtime.burn(self.into_api()?)?;
+        info!("Reply: {}", reply);
+        write_transfer(&reply, "Burn", consignment, transaction)
+    }
+
+    fn into_api(self) -> Result<BurnApi, Error> {
+        let history_proof = match (self.proof_format, self.proof) {
+            (Some(format), Some(filename)) => {
+                let proof = fs::read(&filename).map_err(|_| {
+                    Error::InputFileIoError(format!("{:?}", filename))
+                })?;
+                Some((format, proof))
+            }
+            (None, None) => None,
+            _ => {
+                eprintln!(
+                    "History proof must be provided together with its format"
+                );
+                Err(Error::DataInconsistency)?
+            }
+        };
+        Ok(BurnApi {
+            psbt: read_psbt(self.prototype)?,
+            contract_id: self.asset,
+            burn_right: self.burn_right,
+            parent: self.parent,
+            burn_utxos: self.burn_utxos,
+            next_burn: self.next_burn,
+            history_proof,
+        })
+    }
+}
+
+impl BurnReplaceCli {
+    pub fn exec(self, mut runtime: Runtime) -> Result<(), Error> {
+        info!("Burning and replacing asset ...");
+        debug!("{}", self.clone());
+
+        let consignment = self.burn.consignment.clone();
+        let transaction = self.burn.transaction.clone();
+        let api = BurnReplaceApi {
+            burn: self.burn.into_api()?,
+            ours: self.allocate,
+            theirs: self.send,
+        };
+
+        let reply = runtime.burn_replace(api)?;
+        info!("Reply: {}", reply);
+        write_transfer(&reply, "Burn and replace", consignment, transaction)
+    }
+}
+
+impl RenominateCli {
+    pub fn exec(self, mut runtime: Runtime) -> Result<(), Error> {
+        info!("Renominating asset ...");
+        debug!("{}", self.clone());
+
+        let api = RenominateApi {
+            psbt: read_psbt(self.prototype)?,
+            contract_id: self.asset,
+            renomination: self.renomination,
+            parent: self.parent,
+            ticker: self.ticker,
+            name: self.name,
+            description: self.description,
+            next_renomination: self.next_renomination,
+        };
+
+        let reply = runtime.renominate(api)?;
+        info!("Reply: {}", reply);
+        write_transfer(
+            &reply,
+            "Renomination",
+            self.consignment,
+            self.transaction,
+        )
+    }
+}
+
+impl SplitRightsCli {
+    pub fn exec(self, mut runtime: Runtime) -> Result<(), Error> {
+        info!("Splitting asset rights ...");
+        debug!("{}", self.clone());
+
+        let api = SplitRightsApi {
+            psbt: read_psbt(self.prototype)?,
+            contract_id: self.asset,
+            inputs: self.inputs,
+            inflation: self.inflation,
+            renomination: self.renomination,
+            epoch: self.epoch,
+            destroy_rights: self.destroy_rights,
+            assets: self.allocate,
+        };
+
+        let reply = runtime.split_rights(api)?;
+        info!("Reply: {}", reply);
+        write_transfer(
+            &reply,
+            "Rights split",
+            self.consignment,
+            self.transaction,
+        )
+    }
+}
+
+/// Saves consignment and witness transaction from the server reply
+fn write_transfer(
+    reply: &Reply,
+    operation: &str,
+    consignment: PathBuf,
+    transaction: PathBuf,
+) -> Result<(), Error> {
+    match reply {
+        Reply::Failure(failure) => {
+            eprintln!("{} failed: {}", operation, failure);
+        }
+        Reply::Transfer(transfer) => {
+            trace!("{:?}", strict_serialize(&transfer.consignment));
+            transfer.consignment.write_file(consignment.clone())?;
+            let out_file = fs::File::create(&transaction)
+                .expect("can't create output transaction file");
+            transfer.psbt.consensus_encode(out_file)?;
+            eprintln!("Consignment contains {}", transfer.stats);
+            println!(
+                "{} succeeded, consignment data are written to {:?}, partially signed witness transaction to {:?}",
+                operation, consignment, transaction
+            );
+        }
+        _ => {
+            eprintln!(
+                "Unexpected server error; probably you connecting with outdated client version"
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Reads partially-signed transaction prototype from the file and prepares
+/// its outputs for holding LNPBP1/2 commitments
+fn read_psbt(prototype: PathBuf) -> Result<PartiallySignedTransaction, Error> {
+    debug!(
+        "Reading partially-signed transaction from file {:?}",
+        prototype
+    );
+    let filepath = format!("{:?}", &prototype);
+    let file = fs::File::open(prototype)
+        .map_err(|_| Error::InputFileIoError(format!("{:?}", filepath)))?;
+    let mut psbt =
+        PartiallySignedTransaction::consensus_decode(file).map_err(|err| {
+            Error::InputFileFormatError(
+                format!("{:?}", filepath),
+                format!("{}", err),
+            )
+        })?;
+
+    for (index, output) in &mut psbt.outputs.iter_mut().enumerate() {
+        if let Some(key) = output.hd_keypaths.keys().next() {
+            let key = key.clone();
+            output.insert_proprietary_key(
+                b"RGB".to_vec(),
+                PSBT_OUT_PUBKEY,
+                vec![],
+                &key.key,
+            );
+            debug!("Output #{} commitment key will be {}", index, key);
+        } else {
+            warn!(
+                "No public key information found for output #{}; \
+                LNPBP1/2 commitment will be impossible.\
+                In order to allow commitment pls add known keys derivation \
+                information to PSBT output map",
+                index
+            );
+        }
+    }
+    trace!("{:?}", psbt);
+    Ok(psbt)
+}
+
+/// Finds witness transaction output paying to the given address
+/// Adds allocation paying the invoice either to the blinded outpoint or to
+/// the witness transaction output paying to the invoice address
+fn add_invoice_allocation(
+    psbt: &PartiallySignedTransaction,
+    invoice: &Invoice,
+    theirs: &mut Vec<ConsealCoins>,
+    theirs_addressed: &mut Vec<SealCoins>,
+) -> Result<(), Error> {
+    match &invoice.outpoint {
+        Outpoint::BlindedUtxo(outpoint_hash) => theirs.push(ConsealCoins {
+            coins: invoice.amount,
+            seal_confidential: *outpoint_hash,
+        }),
+        Outpoint::Address(address) => {
+            // Assets are assigned to the witness transaction output paying to
+            // the invoice address, which must be added to the transaction
+            // prototype by the payer wallet
+            let vout = address_vout(psbt, address).ok_or_else(|| {
+                eprintln!(
+                    "Transaction prototype must contain an output paying to the invoice address {}",
+                    address
+                );
+                Error::DataInconsistency
+            })?;
+            theirs_addressed.push(SealCoins {
+                coins: invoice.amount,
+                vout,
+                txid: None,
+            })
+        }
+    };
+    Ok(())
+}
+
+fn known_history(known_nodes: &[NodeId]) -> Option<KnownHistory> {
+    if known_nodes.is_empty() {
+        None
+    } else {
+        Some(KnownHistory {
+            node_ids: known_nodes.iter().cloned().collect(),
+        })
+    }
+}
+
+fn address_vout(
+    psbt: &PartiallySignedTransaction,
+    address: &Address,
+) -> Option<u32> {
+    psbt.global
+        .unsigned_tx
+        .output
+        .iter()
+        .position(|txout| txout.script_pubkey == address.script_pubkey())
+        .map(|vout| vout as u32)
+}
@@ -0,0 +1,219 @@
+// RGB standard library
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use lnpbp::rgb::prelude::*;
+
+use super::consignment::{assignments_info, type_name, AssignmentInfo};
+use super::{Error, OutputFormat};
+use crate::util::SchemaTypeNames;
+
+/// Contract history in form of a directed acyclic graph, where nodes are
+/// genesis, state transitions and extensions, and edges point from parent
+/// to child nodes
+#[derive(Clone, Debug, Serialize)]
+#[serde(crate = "serde_crate")]
+pub struct ContractGraph {
+    contract_id: String,
+    nodes: Vec<GraphNode>,
+    edges: Vec<GraphEdge>,
+    /// Transitions grouped by witness transaction id
+    witnesses: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(crate = "serde_crate")]
+struct GraphNode {
+    node_id: String,
+    node_kind: &'static str,
+    node_type: String,
+    state: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(crate = "serde_crate")]
+struct GraphEdge {
+    parent: String,
+    child: String,
+    rights_kind: &'static str,
+    rights_type: String,
+    /// Index of the assignment in the parent node; absent for public rights
+    index: Option<u16>,
+}
+
+impl ContractGraph {
+    pub fn with(
+        consignment: &Consignment,
+        type_names: &SchemaTypeNames,
+    ) -> Self {
+        let genesis = &consignment.genesis;
+        let mut graph = Self {
+            contract_id: genesis.contract_id().to_string(),
+            nodes: vec![],
+            edges: vec![],
+            witnesses: bmap! {},
+        };
+
+        graph.add_node(genesis, "genesis", s!("genesis"), type_names);
+        for (anchor, transition) in &consignment.state_transitions {
+            graph.add_node(
+                transition,
+                "transition",
+                type_name(
+                    &type_names.transition_types,
+                    transition.transition_type() as usize,
+                ),
+                type_names,
+            );
+            graph
+                .witnesses
+                .entry(anchor.txid.to_string())
+                .or_insert(vec![])
+                .push(transition.node_id().to_string());
+        }
+        for extension in &consignment.state_extensions {
+            graph.add_node(
+                extension,
+                "extension",
+                type_name(
+                    &type_names.extension_types,
+                    extension.extension_type() as usize,
+                ),
+                type_names,
+            );
+        }
+        graph
+    }
+
+    fn add_node(
+        &mut self,
+        node: &impl Node,
+        node_kind: &'static str,
+        node_type: String,
+        type_names: &SchemaTypeNames,
+    ) {
+        let node_id = node.node_id().to_string();
+        for (parent_id, rights) in node.parent_owned_rights() {
+            for (ty, indexes) in rights {
+                for index in indexes {
+                    self.edges.push(GraphEdge {
+                        parent: parent_id.to_string(),
+                        child: node_id.clone(),
+                        rights_kind: "owned",
+                        rights_type: type_name(
+                            &type_names.owned_right_types,
+                            *ty as usize,
+                        ),
+                        index: Some(*index),
+                    });
+                }
+            }
+        }
+        for (parent_id, rights) in node.parent_public_rights() {
+            for ty in rights {
+                self.edges.push(GraphEdge {
+                    parent: parent_id.to_string(),
+                    child: node_id.clone(),
+                    rights_kind: "public",
+                    rights_type: type_name(
+                        &type_names.public_right_types,
+                        *ty as usize,
+                    ),
+                    index: None,
+                });
+            }
+        }
+        self.nodes.push(GraphNode {
+            node_id,
+            node_kind,
+            node_type,
+            state: assignments_info(node, type_names)
+                .iter()
+                .map(state_label)
+                .collect(),
+        });
+    }
+
+    /// Renders graph in Graphviz DOT format
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        // Writing into a string can't fail
+        let _ = writeln!(dot, "digraph \"{}\" {{", self.contract_id);
+        let _ = writeln!(dot, "    rankdir=LR;");
+        let _ = writeln!(dot, "    node [shape=box];");
+        for (no, (txid, node_ids)) in self.witnesses.iter().enumerate() {
+            let _ = writeln!(dot, "    subgraph cluster_{} {{", no);
+            let _ = writeln!(dot, "        label=\"witness {}\";", txid);
+            for node_id in node_ids {
+                let _ = writeln!(dot, "        \"{}\";", node_id);
+            }
+            let _ = writeln!(dot, "    }}");
+        }
+        for node in &self.nodes {
+            let mut label = format!(
+                "{} {}\\n{}",
+                node.node_kind, node.node_type, node.node_id
+            );
+            for state in &node.state {
+                label += &format!("\\n{}", state);
+            }
+            let _ = writeln!(
+                dot,
+                "    \"{}\" [label=\"{}\"];",
+                node.node_id, label
+            );
+        }
+        for edge in &self.edges {
+            let label = match edge.index {
+                Some(index) => format!("{} #{}", edge.rights_type, index),
+                None => edge.rights_type.clone(),
+            };
+            let style = if edge.rights_kind == "public" {
+                ", style=dashed"
+            } else {
+                ""
+            };
+            let _ = writeln!(
+                dot,
+                "    \"{}\" -> \"{}\" [label=\"{}\"{}];",
+                edge.parent, edge.child, label, style
+            );
+        }
+        dot += "}\n";
+        dot
+    }
+
+    pub fn output(&self, format: OutputFormat) -> Result<String, Error> {
+        Ok(match format {
+            OutputFormat::Dot => self.to_dot(),
+            OutputFormat::Json => serde_json::to_string(self)?,
+            OutputFormat::Yaml => serde_yaml::to_string(self)?,
+            _ => Err(Error::FormatNotSupported)?,
+        })
+    }
+}
+
+fn state_label(info: &AssignmentInfo) -> String {
+    match (info.amount, info.concealed) {
+        (Some(amount), _) => {
+            format!("{} #{}: {}", info.rights_type, info.index, amount)
+        }
+        (None, true) => {
+            format!("{} #{}: concealed", info.rights_type, info.index)
+        }
+        (None, false) => format!("{} #{}", info.rights_type, info.index),
+    }
+}
@@ -12,8 +12,10 @@
 // If not, see <https://opensource.org/licenses/MIT>.
 
 mod config;
+pub mod consignment;
 mod error;
 pub mod fungible;
+mod graph;
 mod runtime;
 pub mod stash;
 
@@ -32,4 +34,5 @@ pub enum OutputFormat {
     Bech32,
     PrettyPrint,
     StrictEncode,
+    Dot,
 }
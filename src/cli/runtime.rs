@@ -14,15 +14,23 @@
 use std::sync::Arc;
 
 use lnpbp::bitcoin::OutPoint;
+use lnpbp::bp::blind::OutpointHash;
 use lnpbp::lnp::transport::zmqsocket::ZmqType;
 use lnpbp::lnp::{
     session, transport, CreateUnmarshaller, PlainTranscoder, Session,
     TypedEnum, Unmarshall, Unmarshaller,
 };
-use lnpbp::rgb::{Consignment, ContractId, Genesis, SchemaId};
+use lnpbp::rgb::{
+    AnchorId, Consignment, ContractId, Extension, Genesis, NodeId, Schema,
+    SchemaId,
+};
 
 use super::{Config, Error};
-use crate::api::fungible::{self, AcceptApi, Issue, TransferApi};
+use crate::api::fungible::{
+    self, AcceptApi, BatchPayApi, BurnApi, BurnReplaceApi, EpochApi,
+    HistoryApi, InflateApi, InvoiceApi, Issue, PayApi, RenominateApi,
+    SplitRightsApi, TransferApi,
+};
 use crate::api::stash;
 use crate::api::Reply;
 use crate::cli::OutputFormat;
@@ -89,6 +97,19 @@ impl Runtime {
         Ok(self.stash_command(stash::Request::ListGeneses())?)
     }
 
+    #[inline]
+    pub fn add_schema(&mut self, schema: Schema) -> Result<Arc<Reply>, Error> {
+        Ok(self.stash_command(stash::Request::AddSchema(schema))?)
+    }
+
+    #[inline]
+    pub fn add_genesis(
+        &mut self,
+        genesis: Genesis,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.stash_command(stash::Request::AddGenesis(genesis))?)
+    }
+
     #[inline]
     pub fn schema(&mut self, schema_id: SchemaId) -> Result<Arc<Reply>, Error> {
         Ok(self.stash_command(stash::Request::ReadSchema(schema_id))?)
@@ -102,6 +123,76 @@ impl Runtime {
         Ok(self.stash_command(stash::Request::ReadGenesis(contract_id))?)
     }
 
+    #[inline]
+    pub fn transitions(
+        &mut self,
+        node_ids: Vec<NodeId>,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.stash_command(stash::Request::ReadTransitions(node_ids))?)
+    }
+
+    #[inline]
+    pub fn extensions(
+        &mut self,
+        node_ids: Vec<NodeId>,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.stash_command(stash::Request::ReadExtensions(node_ids))?)
+    }
+
+    #[inline]
+    pub fn anchors(
+        &mut self,
+        anchor_ids: Vec<AnchorId>,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.stash_command(stash::Request::ReadAnchors(anchor_ids))?)
+    }
+
+    #[inline]
+    pub fn history(
+        &mut self,
+        contract_id: ContractId,
+        node_ids: Vec<NodeId>,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.stash_command(stash::Request::ReadHistory(
+            stash::HistoryRequest {
+                contract_id,
+                node_ids,
+            },
+        ))?)
+    }
+
+    #[inline]
+    pub fn contract(
+        &mut self,
+        contract_id: ContractId,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.stash_command(stash::Request::ReadContract(contract_id))?)
+    }
+
+    #[inline]
+    pub fn consign_extension(
+        &mut self,
+        contract_id: ContractId,
+        extension: Extension,
+        outpoints: Vec<OutpointHash>,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.stash_command(stash::Request::ConsignExtension(
+            stash::ExtensionConsignRequest {
+                contract_id,
+                extension,
+                outpoints,
+            },
+        ))?)
+    }
+
+    #[inline]
+    pub fn schema_type_names(
+        &mut self,
+        schema_id: SchemaId,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.stash_command(stash::Request::ReadSchemaTypeNames(schema_id))?)
+    }
+
     #[inline]
     pub fn list(
         &mut self,
@@ -143,6 +234,95 @@ impl Runtime {
         Ok(self.fungible_command(fungible::Request::Transfer(transfer))?)
     }
 
+    #[inline]
+    pub fn pay(&mut self, pay: PayApi) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::Pay(pay))?)
+    }
+
+    #[inline]
+    pub fn batch_pay(
+        &mut self,
+        batch: BatchPayApi,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::BatchPay(batch))?)
+    }
+
+    #[inline]
+    pub fn invoice(
+        &mut self,
+        invoice: InvoiceApi,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::Invoice(invoice))?)
+    }
+
+    #[inline]
+    pub fn list_invoices(
+        &mut self,
+        contract_id: Option<ContractId>,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self
+            .fungible_command(fungible::Request::ListInvoices(contract_id))?)
+    }
+
+    #[inline]
+    pub fn history(
+        &mut self,
+        history: HistoryApi,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::History(history))?)
+    }
+
+    #[inline]
+    pub fn balance(
+        &mut self,
+        contract_id: Option<ContractId>,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::Balance(contract_id))?)
+    }
+
+    #[inline]
+    pub fn inflate(
+        &mut self,
+        inflate: InflateApi,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::Inflate(inflate))?)
+    }
+
+    #[inline]
+    pub fn epoch(&mut self, epoch: EpochApi) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::Epoch(epoch))?)
+    }
+
+    #[inline]
+    pub fn burn(&mut self, burn: BurnApi) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::Burn(burn))?)
+    }
+
+    #[inline]
+    pub fn burn_replace(
+        &mut self,
+        burn_replace: BurnReplaceApi,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self
+            .fungible_command(fungible::Request::BurnReplace(burn_replace))?)
+    }
+
+    #[inline]
+    pub fn renominate(
+        &mut self,
+        renominate: RenominateApi,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::Renominate(renominate))?)
+    }
+
+    #[inline]
+    pub fn split_rights(
+        &mut self,
+        split: SplitRightsApi,
+    ) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::SplitRights(split))?)
+    }
+
     #[inline]
     pub fn validate(
         &mut self,
@@ -156,6 +336,11 @@ impl Runtime {
         Ok(self.fungible_command(fungible::Request::Accept(accept))?)
     }
 
+    #[inline]
+    pub fn reindex(&mut self) -> Result<Arc<Reply>, Error> {
+        Ok(self.fungible_command(fungible::Request::Reindex())?)
+    }
+
     #[inline]
     pub fn forget(&mut self, outpoint: OutPoint) -> Result<Arc<Reply>, Error> {
         Ok(self.fungible_command(fungible::Request::Forget(outpoint))?)
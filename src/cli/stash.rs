@@ -11,10 +11,26 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
-use lnpbp::rgb::{ContractId, SchemaId, ToBech32};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use lnpbp::bp::blind::OutpointHash;
+use lnpbp::hex::{FromHex, ToHex};
+use lnpbp::rgb::{
+    AnchorId, ContractId, Extension, Genesis, Node, NodeId, Schema, SchemaId,
+    ToBech32,
+};
+use lnpbp::strict_encoding::{strict_serialize, StrictEncode};
 
 use crate::api::Reply;
+use crate::cli::consignment::registered_type_names;
+use crate::cli::graph::ContractGraph;
 use crate::cli::{Error, OutputFormat, Runtime};
+use crate::util::file::ReadWrite;
+use crate::DataFormat;
 
 #[derive(Clap, Clone, Debug, Display)]
 #[display(Debug)]
@@ -35,6 +51,16 @@ pub enum SchemaCommand {
         #[clap()]
         schema_id: SchemaId,
     },
+
+    /// Imports schema from a file
+    Import {
+        /// Format of the input file
+        #[clap(short, long, default_value = "strict-encode")]
+        format: DataFormat,
+
+        /// File containing schema data
+        file: PathBuf,
+    },
 }
 
 #[derive(Clap, Clone, Debug, Display)]
@@ -56,6 +82,91 @@ pub enum GenesisCommand {
         #[clap()]
         contract_id: ContractId,
     },
+
+    /// Imports genesis from a file or Bech32 string
+    Import {
+        /// Format of the input file; ignored for Bech32 input
+        #[clap(short, long, default_value = "strict-encode")]
+        format: DataFormat,
+
+        /// File containing genesis data or Bech32 representation of genesis
+        genesis: String,
+    },
+}
+
+#[derive(Clap, Clone, Debug, Display)]
+#[display(Debug)]
+pub enum StashCommand {
+    /// Export state transition data
+    Transition {
+        /// Format for information output
+        #[clap(short, long, arg_enum, default_value = "yaml")]
+        format: OutputFormat,
+
+        /// State transition id
+        #[clap(parse(try_from_str = NodeId::from_hex))]
+        node_id: NodeId,
+    },
+
+    /// Export state extension data
+    Extension {
+        /// Format for information output
+        #[clap(short, long, arg_enum, default_value = "yaml")]
+        format: OutputFormat,
+
+        /// State extension id
+        #[clap(parse(try_from_str = NodeId::from_hex))]
+        node_id: NodeId,
+    },
+
+    /// Creates consignment for a state extension created by this node and
+    /// keeps the extension in the stash
+    ConsignExtension {
+        /// Format of the extension file
+        #[clap(short, long, default_value = "strict-encode")]
+        format: DataFormat,
+
+        #[clap()]
+        contract_id: ContractId,
+
+        /// File containing state extension data
+        extension: PathBuf,
+
+        /// File to save consignment to
+        consignment: PathBuf,
+
+        /// Blinded seals defined by the extension which are exposed to the
+        /// receiver
+        #[clap(parse(try_from_str = OutpointHash::from_hex))]
+        outpoints: Vec<OutpointHash>,
+    },
+
+    /// Export anchor data
+    Anchor {
+        /// Format for information output
+        #[clap(short, long, arg_enum, default_value = "yaml")]
+        format: OutputFormat,
+
+        /// Anchor id
+        #[clap(parse(try_from_str = AnchorId::from_hex))]
+        anchor_id: AnchorId,
+    },
+
+    /// Export contract history as a graph
+    Graph {
+        /// Format for information output
+        #[clap(short, long, arg_enum, default_value = "dot")]
+        format: OutputFormat,
+
+        #[clap()]
+        contract_id: ContractId,
+
+        /// Ids of the state transitions or extensions to trace the history
+        /// from; if none are given the whole contract history known to the
+        /// stash is exported
+        #[clap(parse(try_from_str = NodeId::from_hex))]
+        node_ids: Vec<NodeId>,
+    },
 }
 
 impl SchemaCommand {
@@ -65,9 +176,37 @@ impl SchemaCommand {
             SchemaCommand::Export { format, schema_id } => {
                 self.exec_export(runtime, format, schema_id)
             }
+            SchemaCommand::Import { format, ref file } => {
+                self.exec_import(runtime, format, file.clone())
+            }
         }
     }
 
+    fn exec_import(
+        &self,
+        mut runtime: Runtime,
+        format: DataFormat,
+        filename: PathBuf,
+    ) -> Result<(), Error> {
+        let schema: Schema = read_file(filename, format)?;
+        info!("Importing schema {} ...", schema.schema_id());
+
+        match &*runtime.add_schema(schema)? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::Success => {
+                eprintln!("Schema successfully imported");
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+        Ok(())
+    }
+
     fn exec_list(
         &self,
         mut runtime: Runtime,
@@ -132,9 +271,45 @@ impl GenesisCommand {
                 format,
                 contract_id,
             } => self.exec_export(runtime, format, contract_id),
+            GenesisCommand::Import {
+                format,
+                ref genesis,
+            } => self.exec_import(runtime, format, genesis.clone()),
         }
     }
 
+    fn exec_import(
+        &self,
+        mut runtime: Runtime,
+        format: DataFormat,
+        genesis: String,
+    ) -> Result<(), Error> {
+        let filename = PathBuf::from(&genesis);
+        let genesis: Genesis = if filename.is_file() {
+            read_file(filename, format)?
+        } else {
+            Genesis::from_str(&genesis).map_err(|err| {
+                Error::InputFileFormatError(genesis.clone(), format!("{}", err))
+            })?
+        };
+        info!("Importing genesis {} ...", genesis.contract_id());
+
+        match &*runtime.add_genesis(genesis)? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::Success => {
+                eprintln!("Genesis successfully imported");
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+        Ok(())
+    }
+
     fn exec_list(
         &self,
         mut runtime: Runtime,
@@ -190,3 +365,179 @@ impl GenesisCommand {
         Ok(())
     }
 }
+
+impl StashCommand {
+    pub fn exec(self, mut runtime: Runtime) -> Result<(), Error> {
+        match self {
+            StashCommand::Transition { format, node_id } => print_item(
+                &*runtime.transitions(vec![node_id])?,
+                format,
+                |reply| match reply {
+                    Reply::Transitions(transitions) => Some(transitions),
+                    _ => None,
+                },
+            ),
+            StashCommand::Extension { format, node_id } => print_item(
+                &*runtime.extensions(vec![node_id])?,
+                format,
+                |reply| match reply {
+                    Reply::Extensions(extensions) => Some(extensions),
+                    _ => None,
+                },
+            ),
+            StashCommand::ConsignExtension {
+                format,
+                contract_id,
+                ref extension,
+                ref consignment,
+                ref outpoints,
+            } => self.exec_consign_extension(
+                runtime,
+                format,
+                contract_id,
+                extension.clone(),
+                consignment.clone(),
+                outpoints.clone(),
+            ),
+            StashCommand::Anchor { format, anchor_id } => print_item(
+                &*runtime.anchors(vec![anchor_id])?,
+                format,
+                |reply| match reply {
+                    Reply::Anchors(anchors) => Some(anchors),
+                    _ => None,
+                },
+            ),
+            StashCommand::Graph {
+                format,
+                contract_id,
+                ref node_ids,
+            } => {
+                self.exec_graph(runtime, format, contract_id, node_ids.clone())
+            }
+        }
+    }
+
+    fn exec_consign_extension(
+        &self,
+        mut runtime: Runtime,
+        format: DataFormat,
+        contract_id: ContractId,
+        extension: PathBuf,
+        consignment: PathBuf,
+        outpoints: Vec<OutpointHash>,
+    ) -> Result<(), Error> {
+        let extension: Extension = read_file(extension, format)?;
+        info!("Consigning state extension {} ...", extension.node_id());
+
+        match &*runtime.consign_extension(contract_id, extension, outpoints)? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::Consignment(data) => {
+                data.write_file(consignment.clone())?;
+                eprintln!("Consignment is written to {:?}", consignment);
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn exec_graph(
+        &self,
+        mut runtime: Runtime,
+        format: OutputFormat,
+        contract_id: ContractId,
+        node_ids: Vec<NodeId>,
+    ) -> Result<(), Error> {
+        let reply = if node_ids.is_empty() {
+            runtime.contract(contract_id)?
+        } else {
+            runtime.history(contract_id, node_ids)?
+        };
+        match &*reply {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::Consignment(consignment) => {
+                let type_names = registered_type_names(
+                    &mut runtime,
+                    consignment.genesis.schema_id(),
+                )?;
+                println!(
+                    "{}",
+                    ContractGraph::with(consignment, &type_names)
+                        .output(format)?
+                );
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Prints the first item from the stash reply, which is extracted by the
+/// `item` function, in the given format
+fn print_item<T>(
+    reply: &Reply,
+    format: OutputFormat,
+    item: impl Fn(&Reply) -> Option<&Vec<T>>,
+) -> Result<(), Error>
+where
+    T: Serialize + StrictEncode,
+{
+    if let Reply::Failure(failure) = reply {
+        eprintln!("Server returned error: {}", failure);
+        return Ok(());
+    }
+    let items = match item(reply) {
+        Some(items) => items,
+        None => {
+            eprintln!(
+                "Unexpected server error; probably you connecting with outdated client version"
+            );
+            return Ok(());
+        }
+    };
+    let item = items.first().ok_or(Error::DataInconsistency)?;
+    let output = match format {
+        OutputFormat::Yaml => serde_yaml::to_string(item)?,
+        OutputFormat::Json => serde_json::to_string(item)?,
+        OutputFormat::StrictEncode => strict_serialize(item)?.to_hex(),
+        _ => Err(Error::FormatNotSupported)?,
+    };
+    println!("{}", output);
+    Ok(())
+}
+
+/// Reads schema or genesis data from a file in the given format
+fn read_file<T>(filename: PathBuf, format: DataFormat) -> Result<T, Error>
+where
+    T: ReadWrite + DeserializeOwned,
+{
+    debug!("Reading {} data from file {:?}", format, &filename);
+    if let DataFormat::StrictEncode = format {
+        return T::read_file(filename.clone()).map_err(|err| {
+            Error::InputFileFormatError(
+                format!("{:?}", filename),
+                format!("{}", err),
+            )
+        });
+    }
+    let data = fs::read(&filename).map_err(|err| {
+        Error::InputFileIoError(format!("{:?}: {}", filename, err))
+    })?;
+    Ok(match format {
+        DataFormat::Yaml => serde_yaml::from_slice(&data)?,
+        DataFormat::Json => serde_json::from_slice(&data)?,
+        DataFormat::Toml => toml::from_slice(&data)?,
+        DataFormat::StrictEncode => unreachable!(),
+    })
+}
@@ -23,6 +23,8 @@ pub const RGB_NETWORK: &'static str = "testnet";
 pub const STASHD_STASH: &'static str = "{data_dir}/{network}/stash/{id}/";
 pub const STASHD_INDEX: &'static str =
     "{data_dir}/{network}/stash/{id}/index.dat";
+pub const STASHD_REGISTRY: &'static str =
+    "{data_dir}/{network}/stash/{id}/registry.dat";
 pub const STASHD_P2P_ENDPOINT: &'static str = "lnp://{node_id}@0.0.0.0:13000";
 pub const STASHD_RPC_ENDPOINT: &'static str =
     "lnpz:{data_dir}/{network}/stashd.rpc";
@@ -30,6 +32,7 @@ pub const STASHD_PUB_ENDPOINT: &'static str =
     "lnpz:{data_dir}/{network}/stashd.pub";
 
 pub const FUNGIBLED_CACHE: &'static str = "{data_dir}/{network}/cache/fungible";
+pub const FUNGIBLED_SEED: &'static str = "{data_dir}/{network}/fungible.seed";
 pub const FUNGIBLED_RPC_ENDPOINT: &'static str =
     "lnpz:{data_dir}/{network}/fungibled.rpc";
 pub const FUNGIBLED_PUB_ENDPOINT: &'static str =
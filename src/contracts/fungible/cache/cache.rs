@@ -18,14 +18,16 @@ use std::collections::BTreeMap;
 use super::sql::SqlCacheError;
 use super::FileCacheError;
 use crate::error::{BootstrapError, ServiceErrorDomain};
-use crate::fungible::Asset;
+use crate::fungible::{Asset, InvoiceRecord, LedgerEntry};
 use crate::util::file::FileMode;
 
+/// Cache of the asset data shared with the clients. Data are returned by
+/// value, so implementations are not required to keep them in memory.
 pub trait Cache {
     type Error: ::std::error::Error + Into<ServiceErrorDomain>;
 
-    fn assets(&self) -> Result<Vec<&Asset>, Self::Error>;
-    fn asset(&self, id: ContractId) -> Result<&Asset, Self::Error>;
+    fn assets(&self) -> Result<Vec<Asset>, Self::Error>;
+    fn asset(&self, id: ContractId) -> Result<Asset, Self::Error>;
     fn has_asset(&self, id: ContractId) -> Result<bool, Self::Error>;
     fn add_asset(&mut self, asset: Asset) -> Result<bool, Self::Error>;
     fn remove_asset(&mut self, id: ContractId) -> Result<bool, Self::Error>;
@@ -41,6 +43,29 @@ pub trait Cache {
         &self,
         outpoint: bitcoin::OutPoint,
     ) -> Result<BTreeMap<ContractId, Vec<AtomicValue>>, CacheError>;
+
+    /// Returns all invoices registered by the node
+    fn invoices(&self) -> Result<Vec<InvoiceRecord>, Self::Error>;
+
+    /// Registers invoice or updates already registered invoice for the same
+    /// outpoint and blinding factor. Returns whether the invoice was already
+    /// known.
+    fn add_invoice(
+        &mut self,
+        invoice: InvoiceRecord,
+    ) -> Result<bool, Self::Error>;
+
+    /// Returns ledger entries for a given asset in the order they were added
+    fn history(
+        &self,
+        contract_id: ContractId,
+    ) -> Result<Vec<LedgerEntry>, Self::Error>;
+
+    /// Appends entry to the ledger of the asset
+    fn add_ledger_entry(
+        &mut self,
+        entry: LedgerEntry,
+    ) -> Result<(), Self::Error>;
 }
 
 #[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
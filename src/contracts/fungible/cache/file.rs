@@ -14,22 +14,20 @@
 #[cfg(feature = "serde")]
 use serde_json;
 use std::collections::BTreeMap;
-#[cfg(any(
-    feature = "serde_yaml",
-    feature = "serde_json",
-    feature = "toml"
-))]
 use std::io::{Read, Write};
 use std::path::PathBuf;
 use std::{fs, io};
 
 use lnpbp::bitcoin;
 use lnpbp::rgb::prelude::*;
-use lnpbp::strict_encoding::{strict_serialize, StrictDecode, StrictEncode};
+use lnpbp::strict_encoding::{
+    strict_deserialize, strict_serialize, StrictDecode, StrictEncode,
+};
 
 use super::Cache;
+use crate::contracts::fungible::data::LegacyAsset;
 use crate::fungible::cache::CacheError;
-use crate::fungible::Asset;
+use crate::fungible::{Asset, InvoiceRecord, LedgerEntry};
 use crate::util::file::*;
 use crate::DataFormat;
 
@@ -84,6 +82,20 @@ impl FileCacheConfig {
             .join("assets")
             .with_extension(self.data_format.extension())
     }
+
+    #[inline]
+    pub fn invoices_filename(&self) -> PathBuf {
+        self.assets_dir()
+            .join("invoices")
+            .with_extension(self.data_format.extension())
+    }
+
+    #[inline]
+    pub fn ledger_filename(&self) -> PathBuf {
+        self.assets_dir()
+            .join("ledger")
+            .with_extension(self.data_format.extension())
+    }
 }
 
 /// Keeps all source/binary RGB contract data, stash etc
@@ -91,6 +103,8 @@ impl FileCacheConfig {
 pub struct FileCache {
     config: FileCacheConfig,
     assets: BTreeMap<ContractId, Asset>,
+    invoices: Vec<InvoiceRecord>,
+    ledger: BTreeMap<ContractId, Vec<LedgerEntry>>,
 }
 
 impl FileCache {
@@ -117,6 +131,8 @@ impl FileCache {
         let mut me = Self {
             config,
             assets: bmap![],
+            invoices: vec![],
+            ledger: bmap![],
         };
         let filename = me.config.assets_filename();
         if filename.exists() {
@@ -125,6 +141,20 @@ impl FileCache {
             debug!("Initializing assets file {:?} ...", filename.to_str());
             me.save()?;
         }
+        let filename = me.config.invoices_filename();
+        if filename.exists() {
+            me.load_invoices()?;
+        } else {
+            debug!("Initializing invoices file {:?} ...", filename.to_str());
+            me.save_invoices()?;
+        }
+        let filename = me.config.ledger_filename();
+        if filename.exists() {
+            me.load_ledger()?;
+        } else {
+            debug!("Initializing ledger file {:?} ...", filename.to_str());
+            me.save_ledger()?;
+        }
 
         Ok(me)
     }
@@ -144,8 +174,17 @@ impl FileCache {
                 f.read_to_string(&mut data)?;
                 toml::from_str(&data)?
             }
-            DataFormat::StrictEncode => StrictDecode::strict_decode(&mut f)?,
+            DataFormat::StrictEncode => {
+                let mut data = vec![];
+                f.read_to_end(&mut data)?;
+                decode_assets(&data)?
+            }
         };
+        // Caches created before nomination history was introduced do not
+        // contain it
+        self.assets
+            .values_mut()
+            .for_each(Asset::restore_nominations);
         Ok(())
     }
 
@@ -162,12 +201,91 @@ impl FileCache {
             #[cfg(feature = "toml")]
             DataFormat::Toml => f.write_all(&toml::to_vec(&self.assets)?)?,
             DataFormat::StrictEncode => {
+                f.write_all(ASSETS_FORMAT_PREFIX)?;
                 self.assets.strict_encode(&mut f)?;
             }
         }
         Ok(())
     }
 
+    fn load_invoices(&mut self) -> Result<(), FileCacheError> {
+        debug!("Reading invoices information ...");
+        let filename = self.config.invoices_filename();
+        let mut f = file(filename, FileMode::Read)?;
+        self.invoices = match self.config.data_format {
+            #[cfg(feature = "serde_yaml")]
+            DataFormat::Yaml => serde_yaml::from_reader(&f)?,
+            #[cfg(feature = "serde_json")]
+            DataFormat::Json => serde_json::from_reader(&f)?,
+            #[cfg(feature = "toml")]
+            DataFormat::Toml => {
+                let mut data = String::new();
+                f.read_to_string(&mut data)?;
+                toml::from_str(&data)?
+            }
+            DataFormat::StrictEncode => StrictDecode::strict_decode(&mut f)?,
+        };
+        Ok(())
+    }
+
+    pub fn save_invoices(&self) -> Result<(), FileCacheError> {
+        trace!("Saving invoices information ...");
+        let filename = self.config.invoices_filename();
+        let _ = fs::remove_file(&filename);
+        let mut f = file(filename, FileMode::Create)?;
+        match self.config.data_format {
+            #[cfg(feature = "serde_yaml")]
+            DataFormat::Yaml => serde_yaml::to_writer(&f, &self.invoices)?,
+            #[cfg(feature = "serde_json")]
+            DataFormat::Json => serde_json::to_writer(&f, &self.invoices)?,
+            #[cfg(feature = "toml")]
+            DataFormat::Toml => f.write_all(&toml::to_vec(&self.invoices)?)?,
+            DataFormat::StrictEncode => {
+                self.invoices.strict_encode(&mut f)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn load_ledger(&mut self) -> Result<(), FileCacheError> {
+        debug!("Reading ledger information ...");
+        let filename = self.config.ledger_filename();
+        let mut f = file(filename, FileMode::Read)?;
+        self.ledger = match self.config.data_format {
+            #[cfg(feature = "serde_yaml")]
+            DataFormat::Yaml => serde_yaml::from_reader(&f)?,
+            #[cfg(feature = "serde_json")]
+            DataFormat::Json => serde_json::from_reader(&f)?,
+            #[cfg(feature = "toml")]
+            DataFormat::Toml => {
+                let mut data = String::new();
+                f.read_to_string(&mut data)?;
+                toml::from_str(&data)?
+            }
+            DataFormat::StrictEncode => StrictDecode::strict_decode(&mut f)?,
+        };
+        Ok(())
+    }
+
+    pub fn save_ledger(&self) -> Result<(), FileCacheError> {
+        trace!("Saving ledger information ...");
+        let filename = self.config.ledger_filename();
+        let _ = fs::remove_file(&filename);
+        let mut f = file(filename, FileMode::Create)?;
+        match self.config.data_format {
+            #[cfg(feature = "serde_yaml")]
+            DataFormat::Yaml => serde_yaml::to_writer(&f, &self.ledger)?,
+            #[cfg(feature = "serde_json")]
+            DataFormat::Json => serde_json::to_writer(&f, &self.ledger)?,
+            #[cfg(feature = "toml")]
+            DataFormat::Toml => f.write_all(&toml::to_vec(&self.ledger)?)?,
+            DataFormat::StrictEncode => {
+                self.ledger.strict_encode(&mut f)?;
+            }
+        }
+        Ok(())
+    }
+
     pub fn export(
         &self,
         data_format: Option<DataFormat>,
@@ -186,18 +304,38 @@ impl FileCache {
     }
 }
 
+/// Prefix of the strict-encoded assets data, distinguishing it from the
+/// unversioned data layout used before nomination history was introduced
+const ASSETS_FORMAT_PREFIX: &[u8] = b"RGBASSETS\x01";
+
+/// Decodes strict-encoded assets, falling back to the data layout used before
+/// nomination history was introduced for the data without version prefix
+fn decode_assets(
+    data: &[u8],
+) -> Result<BTreeMap<ContractId, Asset>, FileCacheError> {
+    if data.starts_with(ASSETS_FORMAT_PREFIX) {
+        return Ok(strict_deserialize(&data[ASSETS_FORMAT_PREFIX.len()..])?);
+    }
+    debug!("Assets data are not versioned; reading them in legacy format");
+    let legacy: BTreeMap<ContractId, LegacyAsset> = strict_deserialize(data)?;
+    Ok(legacy
+        .into_iter()
+        .map(|(id, asset)| (id, Asset::from(asset)))
+        .collect())
+}
+
 impl Cache for FileCache {
     type Error = CacheError;
 
-    fn assets(&self) -> Result<Vec<&Asset>, CacheError> {
-        Ok(self.assets.values().collect())
+    fn assets(&self) -> Result<Vec<Asset>, CacheError> {
+        Ok(self.assets.values().cloned().collect())
     }
 
     #[inline]
-    fn asset(&self, id: ContractId) -> Result<&Asset, CacheError> {
-        Ok(self.assets.get(&id).ok_or(CacheError::DataIntegrityError(
-            "Asset is not known".to_string(),
-        ))?)
+    fn asset(&self, id: ContractId) -> Result<Asset, CacheError> {
+        Ok(self.assets.get(&id).cloned().ok_or(
+            CacheError::DataIntegrityError("Asset is not known".to_string()),
+        )?)
     }
 
     #[inline]
@@ -224,7 +362,11 @@ impl Cache for FileCache {
     ) -> Result<BTreeMap<bitcoin::OutPoint, Vec<AtomicValue>>, CacheError> {
         // Process known_allocation map to produce the intended map
         let result: BTreeMap<bitcoin::OutPoint, Vec<AtomicValue>> = self
-            .asset(contract_id)?
+            .assets
+            .get(&contract_id)
+            .ok_or(CacheError::DataIntegrityError(
+                "Asset is not known".to_string(),
+            ))?
             .known_allocations()
             .into_iter()
             .map(|(outpoint, allocations)| {
@@ -244,8 +386,7 @@ impl Cache for FileCache {
     ) -> Result<BTreeMap<ContractId, Vec<AtomicValue>>, CacheError> {
         let mut result = BTreeMap::new();
 
-        for asset in self.assets()?.into_iter().cloned().collect::<Vec<Asset>>()
-        {
+        for asset in self.assets.values() {
             let allocations: Vec<AtomicValue> =
                 match asset.known_allocations().get(&outpoint) {
                     Some(allocations) => allocations
@@ -260,16 +401,94 @@ impl Cache for FileCache {
 
         Ok(result)
     }
+
+    #[inline]
+    fn invoices(&self) -> Result<Vec<InvoiceRecord>, CacheError> {
+        Ok(self.invoices.clone())
+    }
+
+    fn add_invoice(
+        &mut self,
+        invoice: InvoiceRecord,
+    ) -> Result<bool, CacheError> {
+        let existed = match self.invoices.iter_mut().find(|known| {
+            known.outpoint() == invoice.outpoint()
+                && known.blinding() == invoice.blinding()
+        }) {
+            Some(known) => {
+                *known = invoice;
+                true
+            }
+            None => {
+                self.invoices.push(invoice);
+                false
+            }
+        };
+        self.save_invoices()?;
+        Ok(existed)
+    }
+
+    #[inline]
+    fn history(
+        &self,
+        contract_id: ContractId,
+    ) -> Result<Vec<LedgerEntry>, CacheError> {
+        Ok(self.ledger.get(&contract_id).cloned().unwrap_or_default())
+    }
+
+    fn add_ledger_entry(
+        &mut self,
+        entry: LedgerEntry,
+    ) -> Result<(), CacheError> {
+        self.ledger
+            .entry(*entry.contract_id())
+            .or_insert(vec![])
+            .push(entry);
+        self.save_ledger()?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::super::sql::{SqlCache, SqlCacheConfig};
     use super::*;
+    use crate::contracts::fungible::processor;
     use lnpbp::bp::TaggedHash;
+    use lnpbp::hashes::Hash;
     use lnpbp::hex::FromHex;
     use std::env;
 
+    #[test]
+    fn test_versioned_assets_decoding() {
+        let (asset, _) = processor::issue(
+            lnpbp::bp::Chain::Testnet3,
+            s!("TST"),
+            s!("Test asset"),
+            None,
+            0,
+            vec![(
+                bitcoin::OutPoint {
+                    txid: bitcoin::Txid::from_inner([0x01; 32]),
+                    vout: 0,
+                },
+                10000,
+            )],
+            bmap! {},
+            None,
+            None,
+        )
+        .unwrap();
+        let assets = bmap! { *asset.id() => asset };
+
+        let data = [
+            ASSETS_FORMAT_PREFIX,
+            &strict_serialize(&assets).unwrap()[..],
+        ]
+        .concat();
+        assert_eq!(decode_assets(&data).unwrap(), assets);
+    }
+
     #[test]
     #[ignore]
     fn test_filecache_mappings() {
@@ -307,7 +526,7 @@ mod test {
         // Save the test assets inside FileCache
         // You should see an assets.json file in the DataDir
         for asset in assets {
-            filecache.add_asset(asset.clone()).unwrap();
+            filecache.add_asset(asset).unwrap();
         }
 
         // -------------------------------------------------
@@ -5,16 +5,22 @@ use cache_schema::sql_allocation_utxo::dsl::sql_allocation_utxo as sql_allocatio
 use cache_schema::sql_allocations::dsl::sql_allocations as sql_allocation_table;
 use cache_schema::sql_assets::dsl::sql_assets as sql_asset_table;
 use cache_schema::sql_inflation::dsl::sql_inflation as sql_inflation_table;
+use cache_schema::sql_invoices::dsl::sql_invoices as sql_invoice_table;
 use cache_schema::sql_issues::dsl::sql_issues as sql_issue_table;
+use cache_schema::sql_ledger::dsl::sql_ledger as sql_ledger_table;
+use cache_schema::sql_nominations::dsl::sql_nominations as sql_nomination_table;
 use cache_schema::*;
 
 use super::sql::SqlCacheError;
 use crate::contracts::fungible::data::{
-    AccountingAmount, AccountingValue, Allocation, Asset,
+    AccountingAmount, AccountingValue, Allocation, Asset, InvoiceRecord,
+    LedgerEntry,
 };
+use diesel::dsl::max;
 use diesel::prelude::*;
 use lnpbp::bitcoin::{OutPoint, Txid};
 use lnpbp::hex::{FromHex, ToHex};
+use num_traits::ToPrimitive;
 /// All the sqlite table structures are defined here.
 /// There are 5 tables namely Asset, Issue, Inflation, AllocationUtxo
 /// and Allocation. The Asset is the major table, and all other tables
@@ -46,17 +52,13 @@ impl SqlAsset {
         asset: &Asset,
         connection: &SqliteConnection,
     ) -> Result<Self, SqlCacheError> {
-        // Find the last entry and increase index by 1
-        let last_asset = sql_asset_table
-            .load::<SqlAsset>(connection)?
-            .last()
-            .cloned();
+        // Find the largest id and increase it by 1
+        let last_id = sql_asset_table
+            .select(max(sql_assets::id))
+            .first::<Option<i32>>(connection)?;
 
         Ok(Self {
-            id: match last_asset {
-                Some(asset) => asset.id + 1,
-                None => 0,
-            },
+            id: last_id.map(|id| id + 1).unwrap_or(0),
             contract_id: asset.id().clone().to_hex(),
             ticker: asset.ticker().clone(),
             asset_name: asset.name().clone(),
@@ -95,11 +97,12 @@ impl SqlInflation {
         table_asset: &SqlAsset,
         connection: &SqliteConnection,
     ) -> Result<Vec<Self>, SqlCacheError> {
-        // Find the last inflation entry and increase id from there
-        let last_inflation = sql_inflation_table
-            .load::<SqlInflation>(connection)?
-            .last()
-            .cloned();
+        // Find the largest inflation entry id and increase id from there
+        let next_id = sql_inflation_table
+            .select(max(sql_inflation::id))
+            .first::<Option<i32>>(connection)?
+            .map(|id| id + 1)
+            .unwrap_or(0);
 
         let known_inflations = asset.known_inflation();
 
@@ -108,10 +111,7 @@ impl SqlInflation {
         // Create Inflation table entries from Asset known_inflation data
         for (index, item) in known_inflations.into_iter().enumerate() {
             let sql_inflation = Self {
-                id: match last_inflation.clone() {
-                    Some(inflation) => inflation.id + index as i32 + 1,
-                    None => 0 + index as i32,
-                },
+                id: next_id + index as i32,
                 sql_asset_id: table_asset.id,
                 outpoint_txid: Some(item.0.txid.to_hex()),
                 outpoint_vout: Some(item.0.vout as i32),
@@ -121,20 +121,10 @@ impl SqlInflation {
             result.push(sql_inflation);
         }
 
-        // We need to keep track on the last added item id
-        // in the above inflation entry as we need to add
-        // unknown inflation entry next.
-        let last_added_id;
-
-        if let Some(item) = result.last() {
-            last_added_id = item.id;
-        } else {
-            last_added_id = 0;
-        }
-
-        // Push the unknown inflation entry with txid and vout as None.
+        // Push the unknown inflation entry with txid and vout as None, right
+        // after the known inflation entries
         result.push(Self {
-            id: last_added_id + 1,
+            id: next_id + result.len() as i32,
             sql_asset_id: table_asset.id,
             outpoint_txid: None,
             outpoint_vout: None,
@@ -213,11 +203,12 @@ impl SqlIssue {
         table_asset: &SqlAsset,
         connection: &SqliteConnection,
     ) -> Result<Vec<Self>, SqlCacheError> {
-        // get the last issue and increase id from there
-        let last_issue = sql_issue_table
-            .load::<SqlIssue>(connection)?
-            .last()
-            .cloned();
+        // get the largest issue id and increase id from there
+        let next_id = sql_issue_table
+            .select(max(sql_issues::id))
+            .first::<Option<i32>>(connection)?
+            .map(|id| id + 1)
+            .unwrap_or(0);
 
         let asset_issues = asset.known_issues();
 
@@ -225,10 +216,7 @@ impl SqlIssue {
 
         for (index, issue) in asset_issues.into_iter().enumerate() {
             result.push(Self {
-                id: match last_issue.clone() {
-                    Some(issue) => issue.id + index as i32 + 1,
-                    None => 0 + index as i32,
-                },
+                id: next_id + index as i32,
                 sql_asset_id: table_asset.id,
                 node_id: issue.id().to_hex(),
                 contract_id: issue.asset_id().to_hex(),
@@ -247,6 +235,135 @@ impl SqlIssue {
     }
 }
 
+#[derive(Queryable, Insertable, Identifiable, Associations, Clone, Debug)]
+#[table_name = "sql_nominations"]
+#[belongs_to(SqlAsset)]
+pub struct SqlNomination {
+    pub id: i32,
+    pub sql_asset_id: i32,
+    pub node_id: String,
+    pub ticker: String,
+    pub asset_name: String,
+    pub asset_description: Option<String>,
+}
+
+impl SqlNomination {
+    /// Create vector of Nomination table entries from a given Asset data
+    pub fn from_asset(
+        asset: &Asset,
+        table_asset: &SqlAsset,
+        connection: &SqliteConnection,
+    ) -> Result<Vec<Self>, SqlCacheError> {
+        // get the largest nomination id and increase id from there
+        let next_id = sql_nomination_table
+            .select(max(sql_nominations::id))
+            .first::<Option<i32>>(connection)?
+            .map(|id| id + 1)
+            .unwrap_or(0);
+
+        let mut result = vec![];
+
+        for (index, nomination) in
+            asset.known_nominations().into_iter().enumerate()
+        {
+            result.push(Self {
+                id: next_id + index as i32,
+                sql_asset_id: table_asset.id,
+                node_id: nomination.id().to_hex(),
+                ticker: nomination.ticker().clone(),
+                asset_name: nomination.name().clone(),
+                asset_description: nomination.description().clone(),
+            })
+        }
+        Ok(result)
+    }
+}
+
+/// Invoices registered by the node are kept in a separate table, not
+/// associated with the Asset entries, since an invoice may be created for an
+/// asset which is not known to the node yet.
+#[derive(Queryable, Insertable, Identifiable, Clone, Debug)]
+#[table_name = "sql_invoices"]
+pub struct SqlInvoice {
+    pub id: i32,
+    pub contract_id: String,
+    pub txid: String,
+    pub vout: i32,
+    pub blinding: i64,
+    pub amount: f64,
+    pub expiry: Option<chrono::NaiveDateTime>,
+    pub paid: bool,
+}
+
+impl SqlInvoice {
+    /// Create an Invoice table entry from a given invoice record
+    pub fn from_invoice(
+        invoice: &InvoiceRecord,
+        connection: &SqliteConnection,
+    ) -> Result<Self, SqlCacheError> {
+        // Find the largest id and increase it by 1
+        let last_id = sql_invoice_table
+            .select(max(sql_invoices::id))
+            .first::<Option<i32>>(connection)?;
+
+        Ok(Self {
+            id: last_id.map(|id| id + 1).unwrap_or(0),
+            contract_id: invoice.contract_id().to_hex(),
+            txid: invoice.outpoint().txid.to_hex(),
+            vout: invoice.outpoint().vout as i32,
+            // Sqlite has no unsigned integers, so the blinding factor bits are
+            // stored as a signed value
+            blinding: *invoice.blinding() as i64,
+            amount: *invoice.amount(),
+            expiry: *invoice.expiry(),
+            paid: *invoice.paid(),
+        })
+    }
+}
+
+/// Ledger entries are keyed by the contract id instead of the Asset entry, so
+/// the ledger is not touched when the asset data are rewritten. The ledger is
+/// append-only: entries are never updated or removed.
+#[derive(Queryable, Insertable, Identifiable, Clone, Debug)]
+#[table_name = "sql_ledger"]
+pub struct SqlLedgerEntry {
+    pub id: i32,
+    pub contract_id: String,
+    pub node_id: String,
+    pub operation: i32,
+    pub timestamp: chrono::NaiveDateTime,
+    pub witness_txid: Option<String>,
+    pub delta: f64,
+    pub counterparty: Option<String>,
+}
+
+impl SqlLedgerEntry {
+    /// Create a Ledger table entry from a given ledger entry
+    pub fn from_ledger_entry(
+        entry: &LedgerEntry,
+        connection: &SqliteConnection,
+    ) -> Result<Self, SqlCacheError> {
+        // Find the largest id and increase it by 1
+        let last_id = sql_ledger_table
+            .select(max(sql_ledger::id))
+            .first::<Option<i32>>(connection)?;
+
+        Ok(Self {
+            id: last_id.map(|id| id + 1).unwrap_or(0),
+            contract_id: entry.contract_id().to_hex(),
+            node_id: entry.node_id().to_hex(),
+            operation: entry
+                .operation()
+                .to_i32()
+                .expect("Ledger operation always fits into i32"),
+            timestamp: *entry.timestamp(),
+            witness_txid: entry.witness_txid().map(|txid| txid.to_hex()),
+            delta: *entry.delta(),
+            counterparty: entry.counterparty().map(|hash| hash.to_hex()),
+        })
+    }
+}
+
 /// AllocationUtxo and Allocation are associated tables with each other.
 /// Every AllocationUtxo is associated with an Asset entry.
 /// Every Allocation is associated with an AllocationUtxo.
@@ -296,16 +413,18 @@ pub fn create_allocation_from_asset(
     table_asset: &SqlAsset,
     connection: &SqliteConnection,
 ) -> Result<(Vec<SqlAllocationUtxo>, Vec<SqlAllocation>), SqlCacheError> {
-    // get the last allocationutxo and allocation id
-    // increase id from there
-    let last_alloc_utxo = sql_allocation_utxo_table
-        .load::<SqlAllocationUtxo>(connection)?
-        .last()
-        .cloned();
-    let last_alloc = sql_allocation_table
-        .load::<SqlAllocation>(connection)?
-        .last()
-        .cloned();
+    // get the largest allocationutxo and allocation ids
+    // increase ids from there
+    let next_utxo_id = sql_allocation_utxo_table
+        .select(max(sql_allocation_utxo::id))
+        .first::<Option<i32>>(connection)?
+        .map(|id| id + 1)
+        .unwrap_or(0);
+    let next_alloc_id = sql_allocation_table
+        .select(max(sql_allocations::id))
+        .first::<Option<i32>>(connection)?
+        .map(|id| id + 1)
+        .unwrap_or(0);
 
     let allocations = asset.known_allocations();
 
@@ -315,10 +434,7 @@ pub fn create_allocation_from_asset(
     let mut added_allocations = 0;
 
     for (index, item) in allocations.into_iter().enumerate() {
-        let this_utxo_id = match last_alloc_utxo.clone() {
-            Some(alloc_utxo) => alloc_utxo.id + index as i32 + 1,
-            None => 0 + index as i32,
-        };
+        let this_utxo_id = next_utxo_id + index as i32;
         utxos.push(SqlAllocationUtxo {
             id: this_utxo_id,
             sql_asset_id: table_asset.id,
@@ -327,12 +443,7 @@ pub fn create_allocation_from_asset(
         });
         for (index, alloc) in item.1.into_iter().enumerate() {
             allocation_vec.push(SqlAllocation {
-                id: match last_alloc.clone() {
-                    Some(alloc) => {
-                        alloc.id + added_allocations + index as i32 + 1
-                    }
-                    None => 0 + added_allocations + index as i32,
-                },
+                id: next_alloc_id + added_allocations + index as i32,
                 sql_allocation_utxo_id: this_utxo_id,
                 node_id: alloc.node_id().to_hex(),
                 assignment_index: alloc.index().clone() as i32,
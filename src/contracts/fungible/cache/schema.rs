@@ -44,6 +44,19 @@ table! {
     }
 }
 
+table! {
+    sql_invoices (id) {
+        id -> Integer,
+        contract_id -> Text,
+        txid -> Text,
+        vout -> Integer,
+        blinding -> BigInt,
+        amount -> Double,
+        expiry -> Nullable<Timestamp>,
+        paid -> Bool,
+    }
+}
+
 table! {
     sql_issues (id) {
         id -> Integer,
@@ -56,10 +69,36 @@ table! {
     }
 }
 
+table! {
+    sql_ledger (id) {
+        id -> Integer,
+        contract_id -> Text,
+        node_id -> Text,
+        operation -> Integer,
+        timestamp -> Timestamp,
+        witness_txid -> Nullable<Text>,
+        delta -> Double,
+        counterparty -> Nullable<Text>,
+    }
+}
+
+table! {
+    sql_nominations (id) {
+        id -> Integer,
+        sql_asset_id -> Integer,
+        node_id -> Text,
+        ticker -> Text,
+        asset_name -> Text,
+        asset_description -> Nullable<Text>,
+    }
+}
+
 allow_tables_to_appear_in_same_query!(
     sql_allocation_utxo,
     sql_allocations,
     sql_assets,
     sql_inflation,
     sql_issues,
+    sql_ledger,
+    sql_nominations,
 );
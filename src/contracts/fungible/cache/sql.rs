@@ -12,7 +12,7 @@
 // If not, see <https://opensource.org/licenses/MIT>.
 
 use diesel::prelude::*;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::PathBuf;
 use std::str::FromStr;
 use std::{fmt, fs, fs::File};
@@ -27,12 +27,15 @@ use cache_schema::sql_allocation_utxo::dsl::sql_allocation_utxo as sql_allocatio
 use cache_schema::sql_allocations::dsl::sql_allocations as sql_allocation_table;
 use cache_schema::sql_assets::dsl::sql_assets as sql_asset_table;
 use cache_schema::sql_inflation::dsl::sql_inflation as sql_inflation_table;
+use cache_schema::sql_invoices::dsl::sql_invoices as sql_invoice_table;
 use cache_schema::sql_issues::dsl::sql_issues as sql_issue_table;
+use cache_schema::sql_ledger::dsl::sql_ledger as sql_ledger_table;
+use cache_schema::sql_nominations::dsl::sql_nominations as sql_nomination_table;
 
 use super::cache::{Cache, CacheError};
 use super::models::*;
 use crate::contracts::fungible::cache::schema as cache_schema;
-use crate::contracts::fungible::data::Asset;
+use crate::contracts::fungible::data::{Asset, InvoiceRecord, LedgerEntry};
 
 #[derive(Debug, Display, Error, From)]
 #[display(inner)]
@@ -80,15 +83,16 @@ impl SqlCacheConfig {
     }
 }
 
-/// Keeps all source/binary RGB contract data, stash etc
+/// Keeps all source/binary RGB contract data, stash etc. All the data are
+/// read from and written to the database on request; nothing is kept in
+/// memory.
 pub struct SqlCache {
     connection: SqliteConnection,
-    assets: HashMap<ContractId, Asset>,
 }
 
 impl fmt::Display for SqlCache {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{:#?}", self.assets)
+        write!(f, "{:#?}", self.assets().map_err(|_| fmt::Error)?)
     }
 }
 
@@ -115,175 +119,330 @@ impl SqlCache {
 
         // check for cached db file
         let filename = config.assets_filename();
-
-        if filename.exists() {
-            // Create connection to db
-            let connection = SqliteConnection::establish(
-                config
-                    .assets_filename()
-                    .to_str()
-                    .ok_or(SqlCacheError::NotFound)?,
-            )
-            .expect(&format!("Error connecting to asset.db"));
-
-            let mut sql_cache = Self {
-                connection,
-                assets: map![],
-            };
-
-            sql_cache.load()?;
-
-            Ok(sql_cache)
-        } else {
-            // If cached database not found create one and return empty cache
+        if !filename.exists() {
+            // If cached database not found create one
             debug!("Initializing assets file {:?} ...", filename.to_str());
             File::create(config.assets_filename())?;
-
-            // Create connection to db
-            let connection = SqliteConnection::establish(
-                config
-                    .assets_filename()
-                    .to_str()
-                    .ok_or(SqlCacheError::NotFound)?,
-            )
-            .expect(&format!("Error connecting to asset.db"));
-
-            let sql_cache = Self {
-                connection,
-                assets: map![],
-            };
-
-            Ok(sql_cache)
         }
-    }
 
-    pub fn load(&mut self) -> Result<(), SqlCacheError> {
-        // get the assets recorded in db
-        let assets = sql_asset_table.load::<SqlAsset>(&self.connection)?;
+        // Create connection to db
+        let connection = SqliteConnection::establish(
+            config
+                .assets_filename()
+                .to_str()
+                .ok_or(SqlCacheError::NotFound)?,
+        )
+        .expect(&format!("Error connecting to asset.db"));
 
-        let mut asset_map = HashMap::new();
+        Ok(Self { connection })
+    }
 
-        for asset in assets {
-            asset_map.insert(
-                ContractId::from_str(&asset.contract_id[..])?,
-                Asset::from_sql_asset(&asset, &self.connection)?,
-            );
-        }
+    /// Returns Asset table entry for the given contract id, if any
+    fn table_asset(
+        &self,
+        id: ContractId,
+    ) -> Result<Option<SqlAsset>, SqlCacheError> {
+        use cache_schema::sql_assets::dsl::contract_id;
+
+        Ok(sql_asset_table
+            .filter(contract_id.eq(id.to_hex()))
+            .first::<SqlAsset>(&self.connection)
+            .optional()?)
+    }
 
-        self.assets = asset_map;
+    /// Writes a new asset with all its associated entries
+    fn insert_asset(&self, asset: &Asset) -> Result<(), SqlCacheError> {
+        let table_asset = SqlAsset::from_asset(asset, &self.connection)?;
+        diesel::insert_into(sql_asset_table)
+            .values(table_asset.clone())
+            .execute(&self.connection)?;
+        self.update_asset_entries(asset, &table_asset)
+    }
 
-        Ok(())
+    /// Updates already known asset. Only the associated entries which were
+    /// added or removed since the last update are written.
+    fn update_asset(
+        &self,
+        asset: &Asset,
+        table_asset: &SqlAsset,
+    ) -> Result<(), SqlCacheError> {
+        let mut updated = SqlAsset::from_asset(asset, &self.connection)?;
+        updated.id = table_asset.id;
+        diesel::replace_into(sql_asset_table)
+            .values(updated)
+            .execute(&self.connection)?;
+        self.update_asset_entries(asset, table_asset)
     }
 
-    /// Deletes and recreates the full database with updated cache
-    pub fn save(&self) -> Result<(), SqlCacheError> {
-        // Delet the existing data
-        diesel::delete(sql_asset_table).execute(&self.connection)?;
-        diesel::delete(sql_issue_table).execute(&self.connection)?;
-        diesel::delete(sql_inflation_table).execute(&self.connection)?;
-        diesel::delete(sql_allocation_utxo_table).execute(&self.connection)?;
-        diesel::delete(sql_allocation_table).execute(&self.connection)?;
-
-        // Create and write table entries from updated cached data
-        for item in self.assets.clone().into_iter() {
-            let table_asset = SqlAsset::from_asset(&item.1, &self.connection)?;
-            let table_issues =
-                SqlIssue::from_asset(&item.1, &table_asset, &self.connection)?;
-
-            let table_inflations = SqlInflation::from_asset(
-                &item.1,
-                &table_asset,
-                &self.connection,
-            )?;
-
-            let (table_utxos, table_allocations) =
-                create_allocation_from_asset(
-                    &item.1,
-                    &table_asset,
-                    &self.connection,
-                )?;
-
-            diesel::insert_into(sql_asset_table)
-                .values(table_asset)
-                .execute(&self.connection)?;
+    /// Brings Issue, Nomination, Inflation, AllocationUtxo and Allocation
+    /// entries associated with the table Asset in sync with the asset data.
+    /// The entries are matched by their content, so the unchanged ones are
+    /// not touched.
+    fn update_asset_entries(
+        &self,
+        asset: &Asset,
+        table_asset: &SqlAsset,
+    ) -> Result<(), SqlCacheError> {
+        let connection = &self.connection;
+
+        let known =
+            SqlIssue::belonging_to(table_asset).load::<SqlIssue>(connection)?;
+        let issues = SqlIssue::from_asset(asset, table_asset, connection)?;
+        let keys = issues
+            .iter()
+            .map(|issue| issue.node_id.clone())
+            .collect::<BTreeSet<_>>();
+        let known_keys = known
+            .iter()
+            .map(|issue| issue.node_id.clone())
+            .collect::<BTreeSet<_>>();
+        for issue in known.iter().filter(|issue| !keys.contains(&issue.node_id))
+        {
+            diesel::delete(issue).execute(connection)?;
+        }
+        for issue in issues
+            .into_iter()
+            .filter(|issue| !known_keys.contains(&issue.node_id))
+        {
+            diesel::insert_into(sql_issue_table)
+                .values(issue)
+                .execute(connection)?;
+        }
 
-            for issue in table_issues {
-                diesel::insert_into(sql_issue_table)
-                    .values(issue)
-                    .execute(&self.connection)?;
-            }
+        let known = SqlNomination::belonging_to(table_asset)
+            .load::<SqlNomination>(connection)?;
+        let nominations =
+            SqlNomination::from_asset(asset, table_asset, connection)?;
+        let keys = nominations
+            .iter()
+            .map(|nomination| nomination.node_id.clone())
+            .collect::<BTreeSet<_>>();
+        let known_keys = known
+            .iter()
+            .map(|nomination| nomination.node_id.clone())
+            .collect::<BTreeSet<_>>();
+        for nomination in known
+            .iter()
+            .filter(|nomination| !keys.contains(&nomination.node_id))
+        {
+            diesel::delete(nomination).execute(connection)?;
+        }
+        for nomination in nominations
+            .into_iter()
+            .filter(|nomination| !known_keys.contains(&nomination.node_id))
+        {
+            diesel::insert_into(sql_nomination_table)
+                .values(nomination)
+                .execute(connection)?;
+        }
 
-            for inflation in table_inflations {
-                diesel::insert_into(sql_inflation_table)
-                    .values(inflation)
-                    .execute(&self.connection)?;
-            }
+        let inflation_key = |inflation: &SqlInflation| {
+            (
+                inflation.outpoint_txid.clone(),
+                inflation.outpoint_vout,
+                inflation.accounting_amount,
+            )
+        };
+        let known = SqlInflation::belonging_to(table_asset)
+            .load::<SqlInflation>(connection)?;
+        let inflations =
+            SqlInflation::from_asset(asset, table_asset, connection)?;
+        let keys = inflations
+            .iter()
+            .map(inflation_key)
+            .collect::<BTreeSet<_>>();
+        let known_keys =
+            known.iter().map(inflation_key).collect::<BTreeSet<_>>();
+        for inflation in known
+            .iter()
+            .filter(|inflation| !keys.contains(&inflation_key(inflation)))
+        {
+            diesel::delete(inflation).execute(connection)?;
+        }
+        for inflation in inflations
+            .into_iter()
+            .filter(|inflation| !known_keys.contains(&inflation_key(inflation)))
+        {
+            diesel::insert_into(sql_inflation_table)
+                .values(inflation)
+                .execute(connection)?;
+        }
 
-            for utxo in table_utxos {
-                diesel::insert_into(sql_allocation_utxo_table)
-                    .values(utxo)
-                    .execute(&self.connection)?;
+        let known_utxos = SqlAllocationUtxo::belonging_to(table_asset)
+            .load::<SqlAllocationUtxo>(connection)?;
+        let known_allocations = SqlAllocation::belonging_to(&known_utxos)
+            .load::<SqlAllocation>(connection)?;
+        let (utxos, mut allocations) =
+            create_allocation_from_asset(asset, table_asset, connection)?;
+
+        // Already known outputs keep their entries, so the allocations are
+        // re-associated with them
+        let known_utxo_ids = known_utxos
+            .iter()
+            .map(|utxo| ((utxo.txid.clone(), utxo.vout), utxo.id))
+            .collect::<BTreeMap<_, _>>();
+        let mut utxo_ids = BTreeMap::new();
+        let mut utxo_keys = BTreeSet::new();
+        for utxo in utxos {
+            let key = (utxo.txid.clone(), utxo.vout);
+            match known_utxo_ids.get(&key) {
+                Some(known_id) => {
+                    utxo_ids.insert(utxo.id, *known_id);
+                }
+                None => {
+                    utxo_ids.insert(utxo.id, utxo.id);
+                    diesel::insert_into(sql_allocation_utxo_table)
+                        .values(utxo)
+                        .execute(connection)?;
+                }
             }
+            utxo_keys.insert(key);
+        }
+        for allocation in &mut allocations {
+            allocation.sql_allocation_utxo_id =
+                utxo_ids[&allocation.sql_allocation_utxo_id];
+        }
 
-            for allocation in table_allocations {
-                diesel::insert_into(sql_allocation_table)
-                    .values(allocation)
-                    .execute(&self.connection)?;
-            }
+        let allocation_key = |allocation: &SqlAllocation| {
+            (
+                allocation.sql_allocation_utxo_id,
+                allocation.node_id.clone(),
+                allocation.assignment_index,
+            )
+        };
+        let keys = allocations
+            .iter()
+            .map(allocation_key)
+            .collect::<BTreeSet<_>>();
+        let known_keys = known_allocations
+            .iter()
+            .map(allocation_key)
+            .collect::<BTreeSet<_>>();
+        for allocation in known_allocations
+            .iter()
+            .filter(|allocation| !keys.contains(&allocation_key(allocation)))
+        {
+            diesel::delete(allocation).execute(connection)?;
+        }
+        for allocation in allocations.into_iter().filter(|allocation| {
+            !known_keys.contains(&allocation_key(allocation))
+        }) {
+            diesel::insert_into(sql_allocation_table)
+                .values(allocation)
+                .execute(connection)?;
+        }
+        for utxo in known_utxos
+            .iter()
+            .filter(|utxo| !utxo_keys.contains(&(utxo.txid.clone(), utxo.vout)))
+        {
+            diesel::delete(utxo).execute(connection)?;
         }
 
         Ok(())
     }
+
+    /// Deletes the asset with all its associated entries
+    fn delete_asset(
+        &self,
+        table_asset: &SqlAsset,
+    ) -> Result<(), SqlCacheError> {
+        let connection = &self.connection;
+        let utxos = SqlAllocationUtxo::belonging_to(table_asset)
+            .load::<SqlAllocationUtxo>(connection)?;
+        diesel::delete(SqlAllocation::belonging_to(&utxos))
+            .execute(connection)?;
+        diesel::delete(SqlAllocationUtxo::belonging_to(table_asset))
+            .execute(connection)?;
+        diesel::delete(SqlInflation::belonging_to(table_asset))
+            .execute(connection)?;
+        diesel::delete(SqlNomination::belonging_to(table_asset))
+            .execute(connection)?;
+        diesel::delete(SqlIssue::belonging_to(table_asset))
+            .execute(connection)?;
+        diesel::delete(table_asset).execute(connection)?;
+        Ok(())
+    }
 }
 
 impl Cache for SqlCache {
     type Error = CacheError;
 
-    fn assets(&self) -> Result<Vec<&Asset>, CacheError> {
-        Ok(self.assets.values().collect())
+    fn assets(&self) -> Result<Vec<Asset>, CacheError> {
+        Ok(sql_asset_table
+            .load::<SqlAsset>(&self.connection)
+            .map_err(SqlCacheError::from)?
+            .iter()
+            .map(|table_asset| {
+                Asset::from_sql_asset(table_asset, &self.connection)
+            })
+            .collect::<Result<_, _>>()?)
     }
 
-    #[inline]
-    fn asset(&self, id: ContractId) -> Result<&Asset, CacheError> {
-        Ok(self.assets.get(&id).ok_or(CacheError::DataIntegrityError(
-            "Asset is not known".to_string(),
-        ))?)
+    fn asset(&self, id: ContractId) -> Result<Asset, CacheError> {
+        let table_asset = self.table_asset(id)?.ok_or(
+            CacheError::DataIntegrityError("Asset is not known".to_string()),
+        )?;
+        Ok(Asset::from_sql_asset(&table_asset, &self.connection)?)
     }
 
     #[inline]
     fn has_asset(&self, id: ContractId) -> Result<bool, CacheError> {
-        Ok(self.assets.contains_key(&id))
+        Ok(self.table_asset(id)?.is_some())
     }
 
     fn add_asset(&mut self, asset: Asset) -> Result<bool, CacheError> {
-        let exists = self.assets.insert(*asset.id(), asset).is_some();
-        self.save()?;
-        Ok(exists)
+        Ok(self
+            .connection
+            .transaction::<_, SqlCacheError, _>(|| {
+                match self.table_asset(*asset.id())? {
+                    Some(table_asset) => {
+                        self.update_asset(&asset, &table_asset)?;
+                        Ok(true)
+                    }
+                    None => {
+                        self.insert_asset(&asset)?;
+                        Ok(false)
+                    }
+                }
+            })?)
     }
 
-    #[inline]
     fn remove_asset(&mut self, id: ContractId) -> Result<bool, CacheError> {
-        let existed = self.assets.remove(&id).is_some();
-        self.save()?;
-        Ok(existed)
+        Ok(self
+            .connection
+            .transaction::<_, SqlCacheError, _>(|| {
+                match self.table_asset(id)? {
+                    Some(table_asset) => {
+                        self.delete_asset(&table_asset)?;
+                        Ok(true)
+                    }
+                    None => Ok(false),
+                }
+            })?)
     }
 
     fn asset_allocations(
         &self,
         contract_id: ContractId,
     ) -> Result<BTreeMap<bitcoin::OutPoint, Vec<AtomicValue>>, CacheError> {
-        // Process known_allocation map to produce the intended map
-        let result: BTreeMap<bitcoin::OutPoint, Vec<AtomicValue>> = self
-            .asset(contract_id)?
-            .known_allocations()
-            .into_iter()
-            .map(|(outpoint, allocations)| {
-                (
-                    *outpoint,
-                    allocations.into_iter().map(|a| a.value().value).collect(),
-                )
-            })
-            .collect();
+        let table_asset = self.table_asset(contract_id)?.ok_or(
+            CacheError::DataIntegrityError("Asset is not known".to_string()),
+        )?;
+
+        // Process allocations of the asset to produce the intended map
+        let result: BTreeMap<bitcoin::OutPoint, Vec<AtomicValue>> =
+            read_allocations(&table_asset, &self.connection)?
+                .into_iter()
+                .map(|(outpoint, allocations)| {
+                    (
+                        outpoint,
+                        allocations
+                            .into_iter()
+                            .map(|a| a.value().value)
+                            .collect(),
+                    )
+                })
+                .collect();
 
         Ok(result)
     }
@@ -342,6 +501,69 @@ impl Cache for SqlCache {
 
         Ok(result)
     }
+
+    fn invoices(&self) -> Result<Vec<InvoiceRecord>, CacheError> {
+        Ok(sql_invoice_table
+            .load::<SqlInvoice>(&self.connection)
+            .map_err(SqlCacheError::from)?
+            .into_iter()
+            .map(InvoiceRecord::from_sql_invoice)
+            .collect::<Result<_, _>>()?)
+    }
+
+    fn add_invoice(
+        &mut self,
+        invoice: InvoiceRecord,
+    ) -> Result<bool, CacheError> {
+        use cache_schema::sql_invoices::dsl::{blinding, txid, vout};
+
+        Ok(self.connection.transaction::<_, SqlCacheError, _>(|| {
+            let mut table_invoice =
+                SqlInvoice::from_invoice(&invoice, &self.connection)?;
+            let known = sql_invoice_table
+                .filter(txid.eq(&table_invoice.txid))
+                .filter(vout.eq(table_invoice.vout))
+                .filter(blinding.eq(table_invoice.blinding))
+                .first::<SqlInvoice>(&self.connection)
+                .optional()?;
+            if let Some(ref known) = known {
+                table_invoice.id = known.id;
+            }
+            diesel::replace_into(sql_invoice_table)
+                .values(table_invoice)
+                .execute(&self.connection)?;
+            Ok(known.is_some())
+        })?)
+    }
+
+    fn history(
+        &self,
+        contract_id: ContractId,
+    ) -> Result<Vec<LedgerEntry>, CacheError> {
+        use cache_schema::sql_ledger::dsl;
+
+        Ok(sql_ledger_table
+            .filter(dsl::contract_id.eq(contract_id.to_hex()))
+            .order(dsl::id.asc())
+            .load::<SqlLedgerEntry>(&self.connection)
+            .map_err(SqlCacheError::from)?
+            .into_iter()
+            .map(LedgerEntry::from_sql_ledger_entry)
+            .collect::<Result<_, _>>()?)
+    }
+
+    fn add_ledger_entry(
+        &mut self,
+        entry: LedgerEntry,
+    ) -> Result<(), CacheError> {
+        let table_entry =
+            SqlLedgerEntry::from_ledger_entry(&entry, &self.connection)?;
+        diesel::insert_into(sql_ledger_table)
+            .values(table_entry)
+            .execute(&self.connection)
+            .map_err(SqlCacheError::from)?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -919,4 +1141,92 @@ mod test {
         // Assert caclulation meets expectation
         assert_eq!(expected_map, allocation_map_calculated);
     }
+
+    #[test]
+    #[ignore]
+    fn test_sqlite_incremental_update() {
+        let database_url = env::var("DATABASE_URL").expect(
+            "Environment Variable 'DATABASE_URL' must be set to run this test",
+        );
+        let filepath = PathBuf::from(&database_url[..]);
+        let config = SqlCacheConfig { data_dir: filepath };
+        let mut cache = SqlCache::new(&config).unwrap();
+
+        let allocation_ids = |cache: &SqlCache| {
+            sql_allocation_table
+                .load::<SqlAllocation>(&cache.connection)
+                .unwrap()
+                .into_iter()
+                .map(|allocation| allocation.id)
+                .collect::<BTreeSet<_>>()
+        };
+        let utxo_ids = |cache: &SqlCache| {
+            sql_allocation_utxo_table
+                .load::<SqlAllocationUtxo>(&cache.connection)
+                .unwrap()
+                .into_iter()
+                .map(|utxo| utxo.id)
+                .collect::<BTreeSet<_>>()
+        };
+
+        let contract_id = ContractId::from_hex(
+            "5bb162c7c84fa69bd263a12b277b82155787a03537691619fed731432f6855dc",
+        )
+        .unwrap();
+        let original = cache.asset(contract_id).unwrap();
+        let known_allocation_ids = allocation_ids(&cache);
+        let known_utxo_ids = utxo_ids(&cache);
+
+        // Adding allocations to a known and to a new output
+        let known_outpoint = bitcoin::OutPoint {
+            txid: bitcoin::Txid::from_hex(
+                "fc63f797af718cc5a11988f69507701d5fe84e58cdd900e1b02856c0ea5a058a",
+            )
+            .unwrap(),
+            vout: 3,
+        };
+        let new_outpoint = bitcoin::OutPoint {
+            txid: bitcoin::Txid::from_hex(
+                "4a1a5b7a5b6c1e2a8e2f6b8c9d0e1f2a3b4c5d6e7f8091a2b3c4d5e6f7a8b9c0",
+            )
+            .unwrap(),
+            vout: 1,
+        };
+        let value = original.allocations(&known_outpoint).unwrap()[0]
+            .value()
+            .clone();
+        let node_id = NodeId::from_hex(
+            "0e2f4a6c8e0a2c4e6a8c0e2a4c6e8a0c2e4a6c8e0a2c4e6a8c0e2a4c6e8a0c2e",
+        )
+        .unwrap();
+        let mut asset = original.clone();
+        asset.add_allocation(known_outpoint, node_id, 100, value.clone());
+        asset.add_allocation(new_outpoint, node_id, 101, value);
+
+        assert!(cache.add_asset(asset.clone()).unwrap());
+        assert_eq!(
+            cache.asset(contract_id).unwrap().known_allocations(),
+            asset.known_allocations()
+        );
+
+        // Entries of the unchanged allocations and outputs are kept as is
+        let updated_allocation_ids = allocation_ids(&cache);
+        assert!(updated_allocation_ids.is_superset(&known_allocation_ids));
+        assert_eq!(
+            updated_allocation_ids.len(),
+            known_allocation_ids.len() + 2
+        );
+        let updated_utxo_ids = utxo_ids(&cache);
+        assert!(updated_utxo_ids.is_superset(&known_utxo_ids));
+        assert_eq!(updated_utxo_ids.len(), known_utxo_ids.len() + 1);
+
+        // Restoring the original asset removes only the added entries
+        assert!(cache.add_asset(original.clone()).unwrap());
+        assert_eq!(
+            cache.asset(contract_id).unwrap().known_allocations(),
+            original.known_allocations()
+        );
+        assert_eq!(allocation_ids(&cache), known_allocation_ids);
+        assert_eq!(utxo_ids(&cache), known_utxo_ids);
+    }
 }
@@ -46,6 +46,12 @@ pub struct Opts {
     #[clap(short, long, default_value = "yaml", env = "RGB_FUNGIBLED_FORMAT")]
     pub format: DataFormat,
 
+    /// File with hex-encoded wallet seed used to derive outpoint blinding
+    /// factors; created with a random seed if absent. Used derivation
+    /// counters are kept next to it in a file with `counters` extension
+    #[clap(long, default_value = FUNGIBLED_SEED, env = "RGB_FUNGIBLED_SEED")]
+    pub seed: String,
+
     /// ZMQ socket address string for REQ/REP API
     #[clap(
         long = "rpc",
@@ -81,6 +87,11 @@ pub struct Opts {
     /// Bitcoin network to use
     #[clap(short, long, default_value = RGB_NETWORK, env = "RGB_NETWORK")]
     pub network: bp::Chain,
+
+    /// Rebuilds the cache on start by replaying RGB-20 contract data kept by
+    /// the stash daemon
+    #[clap(long)]
+    pub reindex: bool,
 }
 
 // We need config structure since not all of the parameters can be specified
@@ -93,11 +104,13 @@ pub struct Config {
     pub data_dir: PathBuf,
     pub cache: String,
     pub format: DataFormat,
+    pub seed: PathBuf,
     pub rpc_endpoint: ZmqSocketAddr,
     pub pub_endpoint: ZmqSocketAddr,
     pub stash_rpc: ZmqSocketAddr,
     pub stash_sub: ZmqSocketAddr,
     pub network: bp::Chain,
+    pub reindex: bool,
 }
 
 impl From<Opts> for Config {
@@ -105,10 +118,12 @@ impl From<Opts> for Config {
         let mut me = Self {
             verbose: opts.verbose,
             network: opts.network,
+            reindex: opts.reindex,
             ..Config::default()
         };
         me.data_dir = me.parse_param(opts.data_dir);
         me.cache = me.parse_param(opts.cache);
+        me.seed = me.parse_param(opts.seed);
         me.rpc_endpoint = me.parse_param(opts.rpc_endpoint);
         me.pub_endpoint = me.parse_param(opts.pub_endpoint);
         me.stash_rpc = me.parse_param(opts.stash_rpc);
@@ -129,6 +144,9 @@ impl Default for Config {
             format: DataFormat::Yaml,
             #[cfg(not(feature = "serde"))]
             format: DataFormat::StrictEncode,
+            seed: FUNGIBLED_SEED
+                .parse()
+                .expect("Error in FUNGIBLED_SEED constant value"),
             rpc_endpoint: FUNGIBLED_RPC_ENDPOINT
                 .parse()
                 .expect("Error in FUNGIBLED_RPC_ENDPOINT constant value"),
@@ -144,6 +162,7 @@ impl Default for Config {
             network: RGB_NETWORK
                 .parse()
                 .expect("Error in RGB_NETWORK constant value"),
+            reindex: false,
         }
     }
 }
@@ -28,10 +28,10 @@ use lnpbp::rgb::prelude::*;
 use lnpbp::rgb::seal::WitnessVoutError;
 use lnpbp::secp256k1zkp::{key::SecretKey, Secp256k1};
 
-use super::schema::{self, FieldType, OwnedRightsType};
+use super::schema::{self, FieldType, OwnedRightsType, TransitionType};
 use crate::contracts::fungible::cache::models::{
     read_allocations, read_inflation, SqlAllocation, SqlAllocationUtxo,
-    SqlAsset, SqlIssue,
+    SqlAsset, SqlIssue, SqlNomination,
 };
 use crate::contracts::fungible::cache::SqlCacheError;
 use crate::error::ServiceErrorDomain;
@@ -127,6 +127,14 @@ impl AccountingAmount {
     pub fn fractional_bits(&self) -> u8 {
         self.1
     }
+
+    /// Subtracts amounts, returning `None` if the result would be negative
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        if self.fractional_bits() != rhs.fractional_bits() {
+            panic!("Subtraction of amounts with different fractional bits")
+        }
+        self.0.checked_sub(rhs.0).map(|value| Self(value, self.1))
+    }
 }
 
 impl Add for AccountingAmount {
@@ -172,6 +180,11 @@ pub struct Asset {
     fractional_bits: u8,
     date: NaiveDateTime,
     known_issues: Vec<Issue>,
+    /// History of the asset nominations, starting with the one defined by
+    /// genesis; the last nomination always matches the current asset ticker,
+    /// name and description
+    #[cfg_attr(feature = "serde", serde(default))]
+    known_nominations: Vec<Nomination>,
     /// Specifies outpoints which when spent may indicate inflation happening
     /// up to specific amount.
     known_inflation: BTreeMap<bitcoin::OutPoint, AccountingAmount>,
@@ -206,8 +219,15 @@ impl Asset {
             )?)
         }
 
-        Ok(Self {
-            id: ContractId::from_str(&table_value.contract_id[..])?,
+        let contract_id = ContractId::from_str(&table_value.contract_id[..])?;
+        let known_nominations = SqlNomination::belonging_to(table_value)
+            .load::<SqlNomination>(connection)?
+            .into_iter()
+            .map(Nomination::from_sql_nomination)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut asset = Self {
+            id: contract_id,
             ticker: table_value.ticker.clone(),
             name: table_value.asset_name.clone(),
             description: table_value.asset_description.clone(),
@@ -216,10 +236,70 @@ impl Asset {
             fractional_bits: table_value.fractional_bits[0],
             date: table_value.asset_date,
             known_issues: known_issues,
+            known_nominations,
             known_inflation: known_inflation,
             unknown_inflation: unknown_inflation,
             known_allocations: read_allocations(&table_value, connection)?,
-        })
+        };
+        // Caches created before nomination history was introduced keep only
+        // the genesis nomination in the asset table
+        asset.restore_nominations();
+        Ok(asset)
+    }
+
+    /// Restores nomination history for the assets read from the caches
+    /// created before it was introduced. At that time assets could not be
+    /// renominated, so the current asset nomination is the genesis one.
+    pub(crate) fn restore_nominations(&mut self) {
+        if self.known_nominations.is_empty() {
+            self.known_nominations.push(Nomination {
+                id: NodeId::from_inner(self.id.into_inner()),
+                ticker: self.ticker.clone(),
+                name: self.name.clone(),
+                description: self.description.clone(),
+            });
+        }
+    }
+}
+
+/// Strict-encoded asset data layout used by the file caches created before
+/// nomination history was introduced
+#[derive(Clone, StrictDecode)]
+#[cfg_attr(test, derive(StrictEncode))]
+pub(crate) struct LegacyAsset {
+    id: ContractId,
+    ticker: String,
+    name: String,
+    description: Option<String>,
+    supply: Supply,
+    chain: bp::Chain,
+    fractional_bits: u8,
+    date: NaiveDateTime,
+    known_issues: Vec<Issue>,
+    known_inflation: BTreeMap<bitcoin::OutPoint, AccountingAmount>,
+    unknown_inflation: AccountingAmount,
+    known_allocations: BTreeMap<bitcoin::OutPoint, Vec<Allocation>>,
+}
+
+impl From<LegacyAsset> for Asset {
+    fn from(legacy: LegacyAsset) -> Self {
+        let mut asset = Self {
+            id: legacy.id,
+            ticker: legacy.ticker,
+            name: legacy.name,
+            description: legacy.description,
+            supply: legacy.supply,
+            chain: legacy.chain,
+            fractional_bits: legacy.fractional_bits,
+            date: legacy.date,
+            known_issues: legacy.known_issues,
+            known_nominations: vec![],
+            known_inflation: legacy.known_inflation,
+            unknown_inflation: legacy.unknown_inflation,
+            known_allocations: legacy.known_allocations,
+        };
+        asset.restore_nominations();
+        asset
     }
 }
 
@@ -399,9 +479,282 @@ impl Issue {
     }
 }
 
+#[derive(
+    Clone, Getters, PartialEq, Debug, Display, StrictEncode, StrictDecode,
+)]
+#[display(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize,),
+    serde(crate = "serde_crate")
+)]
+pub struct Nomination {
+    /// Id of the node defining the nomination: either genesis or
+    /// renomination state transition
+    id: NodeId,
+
+    ticker: String,
+
+    name: String,
+
+    description: Option<String>,
+}
+
+impl Nomination {
+    /// Create a Nomination structure from reading the corresponding
+    /// Nomination table entry in the database.
+    pub fn from_sql_nomination(
+        table_value: SqlNomination,
+    ) -> Result<Nomination, SqlCacheError> {
+        Ok(Nomination {
+            id: NodeId::from_hex(&table_value.node_id[..])?,
+            ticker: table_value.ticker,
+            name: table_value.asset_name,
+            description: table_value.asset_description,
+        })
+    }
+}
+
 impl Asset {
-    pub fn add_issue(&self, _issue: Transition) -> Supply {
-        unimplemented!()
+    /// Registers secondary issue produced by spending inflation right
+    /// assigned to the `origin` outpoint. Updates known supply, inflation
+    /// rights and allocations of the asset, resolving witness transaction
+    /// output-based seals with the provided `witness_txid`.
+    pub fn add_issue(
+        &mut self,
+        origin: bitcoin::OutPoint,
+        issue: &Transition,
+        witness_txid: Txid,
+    ) -> Result<Supply, Error> {
+        let node_id = issue.node_id();
+        if self.known_issues.iter().any(|known| known.id == node_id) {
+            return Ok(self.supply);
+        }
+
+        let amount = AccountingAmount::from_fractioned_atomic_value(
+            self.fractional_bits,
+            *issue
+                .metadata()
+                .u64(*FieldType::IssuedSupply)
+                .first()
+                .ok_or(schema::Error::NotAllFieldsPresent)?,
+        );
+        self.known_inflation.remove(&origin);
+        self.add_revealed_inflation(issue, witness_txid)?;
+        self.add_revealed_allocations(issue, witness_txid);
+
+        self.known_issues.push(Issue {
+            id: node_id,
+            asset_id: self.id,
+            amount,
+            origin: Some(origin),
+        });
+        self.supply.known_circulating += amount;
+
+        Ok(self.supply)
+    }
+
+    /// Registers renomination state transition, updating current asset
+    /// nomination and appending it to the nomination history. Returns
+    /// `false` if the renomination was already known.
+    pub fn add_nomination(&mut self, renomination: &Transition) -> bool {
+        let node_id = renomination.node_id();
+        if self
+            .known_nominations
+            .iter()
+            .any(|known| known.id == node_id)
+        {
+            return false;
+        }
+
+        let metadata = renomination.metadata();
+        if let Some(ticker) = metadata.string(*FieldType::Ticker).first() {
+            self.ticker = ticker.clone();
+        }
+        if let Some(name) = metadata.string(*FieldType::Name).first() {
+            self.name = name.clone();
+        }
+        if let Some(description) =
+            metadata.string(*FieldType::ContractText).first()
+        {
+            self.description = Some(description.clone());
+        }
+        self.known_nominations.push(Nomination {
+            id: node_id,
+            ticker: self.ticker.clone(),
+            name: self.name.clone(),
+            description: self.description.clone(),
+        });
+        true
+    }
+
+    /// Registers burn or burn-and-replace operation. Allocations of the
+    /// burned UTXOs are removed and the known circulating supply is reduced by the burned
+    /// amount; replacement allocations with revealed seals are added back,
+    /// resolving witness transaction output-based seals with the provided
+    /// `witness_txid`.
+    pub fn add_burn(
+        &mut self,
+        burn_utxos: &[bitcoin::OutPoint],
+        burn: &Transition,
+        witness_txid: Txid,
+    ) -> Result<Supply, Error> {
+        let burned = AccountingAmount::from_fractioned_atomic_value(
+            self.fractional_bits,
+            *burn
+                .metadata()
+                .u64(*FieldType::BurnedSupply)
+                .first()
+                .ok_or(schema::Error::NotAllFieldsPresent)?,
+        );
+        let is_replace =
+            burn.transition_type() == *TransitionType::BurnAndReplace;
+        // Replacement keeps the supply intact
+        let known_circulating = if is_replace {
+            self.supply.known_circulating
+        } else {
+            self.supply
+                .known_circulating
+                .checked_sub(burned)
+                .ok_or(Error::BurnExceedsSupply)?
+        };
+
+        for utxo in burn_utxos {
+            self.known_allocations.remove(utxo);
+        }
+        if is_replace {
+            self.add_revealed_allocations(burn, witness_txid);
+        }
+        self.supply.known_circulating = known_circulating;
+
+        Ok(self.supply)
+    }
+
+    /// Registers asset transfer. Allocations assigned to the spent `inputs`
+    /// are replaced with the allocations revealed by the transfer, including
+    /// the change, resolving witness transaction output-based seals with the
+    /// provided `witness_txid`. Revealed allocations to `theirs` seals belong
+    /// to the receiver and are not registered.
+    pub fn add_transfer(
+        &mut self,
+        inputs: &[bitcoin::OutPoint],
+        transfer: &Transition,
+        witness_txid: Txid,
+        theirs: &[SealDefinition],
+    ) {
+        for outpoint in inputs {
+            self.known_allocations.remove(outpoint);
+        }
+        self.add_revealed_allocations(transfer, witness_txid);
+        for seal_definition in theirs {
+            self.known_allocations
+                .remove(&seal_outpoint(seal_definition.clone(), witness_txid));
+        }
+    }
+
+    /// Registers rights split operation. Inflation rights and allocations
+    /// assigned to the split `inputs` are replaced with the ones assigned by
+    /// the transition, resolving witness transaction output-based seals with
+    /// the provided `witness_txid`.
+    pub fn add_rights_split(
+        &mut self,
+        inputs: &[bitcoin::OutPoint],
+        split: &Transition,
+        witness_txid: Txid,
+    ) -> Result<(), Error> {
+        for outpoint in inputs {
+            self.known_inflation.remove(outpoint);
+            self.known_allocations.remove(outpoint);
+        }
+        self.add_revealed_inflation(split, witness_txid)?;
+        self.add_revealed_allocations(split, witness_txid);
+        Ok(())
+    }
+
+    fn add_revealed_inflation(
+        &mut self,
+        transition: &Transition,
+        witness_txid: Txid,
+    ) -> Result<(), Error> {
+        for assignment in
+            transition.owned_rights_by_type(*OwnedRightsType::Inflation)
+        {
+            for state in assignment.to_custom_state() {
+                if let OwnedState::Revealed {
+                    seal_definition,
+                    assigned_state,
+                } = state
+                {
+                    self.known_inflation.insert(
+                        seal_outpoint(seal_definition, witness_txid),
+                        AccountingAmount::from_fractioned_atomic_value(
+                            self.fractional_bits,
+                            assigned_state
+                                .u64()
+                                .ok_or(schema::Error::NotAllFieldsPresent)?,
+                        ),
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn add_revealed_allocations(
+        &mut self,
+        transition: &Transition,
+        witness_txid: Txid,
+    ) {
+        let node_id = transition.node_id();
+        for assignment in
+            transition.owned_rights_by_type(*OwnedRightsType::Assets)
+        {
+            for (index, state) in
+                assignment.to_discrete_state().into_iter().enumerate()
+            {
+                // We assume that each allocation with revealed amount and
+                // known seal belongs to us
+                if let OwnedState::Revealed {
+                    seal_definition,
+                    assigned_state,
+                } = state
+                {
+                    self.add_allocation(
+                        seal_outpoint(seal_definition, witness_txid),
+                        node_id,
+                        index as u16,
+                        assigned_state,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Registers allocations revealed by the state extension. Extensions have
+    /// no witness transaction, so only the allocations with seals pointing to
+    /// explicit transaction outputs are registered.
+    pub fn add_extension(&mut self, extension: &Extension) {
+        let node_id = extension.node_id();
+        for assignment in
+            extension.owned_rights_by_type(*OwnedRightsType::Assets)
+        {
+            for (index, state) in
+                assignment.to_discrete_state().into_iter().enumerate()
+            {
+                if let OwnedState::Revealed {
+                    seal_definition: seal::Revealed::TxOutpoint(outpoint_reveal),
+                    assigned_state,
+                } = state
+                {
+                    self.add_allocation(
+                        bitcoin::OutPoint::from(outpoint_reveal),
+                        node_id,
+                        index as u16,
+                        assigned_state,
+                    );
+                }
+            }
+        }
     }
 
     #[inline]
@@ -461,6 +814,21 @@ impl Asset {
     }
 }
 
+fn seal_outpoint(
+    seal_definition: SealDefinition,
+    witness_txid: Txid,
+) -> bitcoin::OutPoint {
+    match seal_definition {
+        seal::Revealed::TxOutpoint(outpoint_reveal) => {
+            bitcoin::OutPoint::from(outpoint_reveal)
+        }
+        seal::Revealed::WitnessVout { vout, .. } => bitcoin::OutPoint {
+            txid: witness_txid,
+            vout,
+        },
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display, From, Error)]
 #[display(doc_comments)]
 pub enum Error {
@@ -473,6 +841,9 @@ pub enum Error {
     /// can't be a witness transaction for genesis
     #[from(WitnessVoutError)]
     GenesisSeal,
+
+    /// Burned amount exceeds the known circulating supply of the asset
+    BurnExceedsSupply,
 }
 
 impl From<Error> for ServiceErrorDomain {
@@ -578,9 +949,8 @@ impl TryFrom<Genesis> for Asset {
                     }
                 });
         }
-        Ok(Self {
-            id: genesis.contract_id(),
-            chain: genesis.chain().clone(),
+        let nomination = Nomination {
+            id: genesis.node_id(),
             ticker: genesis_meta
                 .string(*FieldType::Ticker)
                 .first()
@@ -595,6 +965,13 @@ impl TryFrom<Genesis> for Asset {
                 .string(*FieldType::ContractText)
                 .first()
                 .cloned(),
+        };
+        Ok(Self {
+            id: genesis.contract_id(),
+            chain: genesis.chain().clone(),
+            ticker: nomination.ticker.clone(),
+            name: nomination.name.clone(),
+            description: nomination.description.clone(),
             supply: Supply {
                 known_circulating: supply,
                 is_issued_known: None,
@@ -626,9 +1003,65 @@ impl TryFrom<Genesis> for Asset {
             known_inflation,
             unknown_inflation,
             known_issues: vec![issue],
+            known_nominations: vec![nomination],
             // we assume that each genesis allocation with revealed amount
             // and known seal (they are always revealed together) belongs to us
             known_allocations,
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::contracts::fungible::processor;
+    use lnpbp::hashes::Hash;
+    use lnpbp::strict_encoding::{strict_deserialize, strict_serialize};
+
+    #[test]
+    fn test_legacy_asset_decoding() {
+        let (asset, _) = processor::issue(
+            bp::Chain::Testnet3,
+            s!("TST"),
+            s!("Test asset"),
+            Some(s!("Description")),
+            2,
+            vec![(
+                OutPoint {
+                    txid: Txid::from_inner([0x01; 32]),
+                    vout: 0,
+                },
+                10000,
+            )],
+            bmap! {},
+            None,
+            None,
+        )
+        .unwrap();
+        let legacy = LegacyAsset {
+            id: asset.id,
+            ticker: asset.ticker.clone(),
+            name: asset.name.clone(),
+            description: asset.description.clone(),
+            supply: asset.supply.clone(),
+            chain: asset.chain.clone(),
+            fractional_bits: asset.fractional_bits,
+            date: asset.date,
+            known_issues: asset.known_issues.clone(),
+            known_inflation: asset.known_inflation.clone(),
+            unknown_inflation: asset.unknown_inflation.clone(),
+            known_allocations: asset.known_allocations.clone(),
+        };
+        let data = strict_serialize(&bmap! { asset.id => legacy }).unwrap();
+
+        let decoded: BTreeMap<ContractId, LegacyAsset> =
+            strict_deserialize(&data).unwrap();
+        let restored = Asset::from(decoded[&asset.id].clone());
+        assert_eq!(restored, asset);
+        assert_eq!(restored.known_nominations().len(), 1);
+        assert_eq!(
+            restored.known_nominations()[0].id,
+            NodeId::from_inner(asset.id.into_inner())
+        );
+    }
+}
@@ -11,15 +11,27 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
+use chrono::{DateTime, NaiveDateTime, Utc};
 use core::fmt::{Display, Formatter};
 use core::str::FromStr;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use url::Url;
 
 use lnpbp::bitcoin;
+use lnpbp::bitcoin::secp256k1::{
+    self, Message, PublicKey, Secp256k1, SecretKey, Signature,
+};
 use lnpbp::bitcoin::Address;
-use lnpbp::bp::blind::OutpointHash;
+use lnpbp::bp::blind::{OutpointHash, OutpointReveal};
+use lnpbp::client_side_validation::Conceal;
+use lnpbp::hashes::{sha256, Hash};
+use lnpbp::hex::{FromHex, ToHex};
 use lnpbp::rgb::{Bech32, ContractId, FromBech32, ToBech32};
 
+use crate::contracts::fungible::cache::models::SqlInvoice;
+use crate::contracts::fungible::cache::SqlCacheError;
+
 use super::AccountingValue;
 
 #[derive(Clone, PartialEq, Eq, Debug, Display, Error, From)]
@@ -41,6 +53,14 @@ pub enum Error {
     WrongAmountEncoding,
 
     WrongOutpoint,
+
+    WrongExpiryEncoding,
+
+    Expired,
+
+    WrongSignatureEncoding,
+
+    InvalidSignature,
 }
 
 // TODO: Move RGB outpoints into LNP/BP Core library
@@ -67,6 +87,135 @@ pub struct Invoice {
     pub contract_id: ContractId,
     pub outpoint: Outpoint,
     pub amount: AccountingValue,
+
+    /// Alternative outpoints, which may be used by the payer instead of the
+    /// main one
+    pub alternatives: Vec<Outpoint>,
+
+    /// Time after which the invoice must not be paid
+    pub expiry: Option<DateTime<Utc>>,
+
+    /// Human-readable payment description
+    pub memo: Option<String>,
+
+    /// Merchant-specific payment reference id
+    pub merchant_ref: Option<String>,
+
+    /// Endpoint of the payee node accepting consignments for the invoice
+    pub endpoint: Option<String>,
+
+    /// Payee node key with the signature over the rest of the invoice
+    pub signature: Option<(PublicKey, Signature)>,
+}
+
+/// Status of the invoice registered by the node
+#[derive(
+    Copy, Clone, PartialEq, Eq, Hash, Debug, Display, FromPrimitive, ToPrimitive,
+)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize,),
+    serde(crate = "serde_crate")
+)]
+#[non_exhaustive]
+pub enum InvoiceStatus {
+    /// Invoice is waiting for the payment
+    #[display("pending")]
+    Pending,
+
+    /// Payment for the invoice was accepted
+    #[display("paid")]
+    Paid,
+
+    /// Invoice expired without being paid
+    #[display("expired")]
+    Expired,
+}
+impl_enum_strict_encoding!(InvoiceStatus);
+
+/// Invoice registered by the node, keeping the data required to accept the
+/// payment, including the blinding factor of the invoice outpoint
+#[derive(
+    Clone, Getters, PartialEq, Debug, Display, StrictEncode, StrictDecode,
+)]
+#[display(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize,),
+    serde(crate = "serde_crate")
+)]
+pub struct InvoiceRecord {
+    contract_id: ContractId,
+    outpoint: bitcoin::OutPoint,
+    blinding: u64,
+    amount: AccountingValue,
+    expiry: Option<NaiveDateTime>,
+    paid: bool,
+}
+
+impl InvoiceRecord {
+    pub fn with(
+        contract_id: ContractId,
+        outpoint_reveal: OutpointReveal,
+        amount: AccountingValue,
+        expiry: Option<NaiveDateTime>,
+    ) -> Self {
+        let blinding = outpoint_reveal.blinding;
+        Self {
+            contract_id,
+            outpoint: bitcoin::OutPoint::from(outpoint_reveal),
+            blinding,
+            amount,
+            expiry,
+            paid: false,
+        }
+    }
+
+    /// Create an invoice record from an sqlite invoice table entry
+    pub fn from_sql_invoice(
+        table_value: SqlInvoice,
+    ) -> Result<Self, SqlCacheError> {
+        Ok(Self {
+            contract_id: ContractId::from_str(&table_value.contract_id[..])?,
+            outpoint: bitcoin::OutPoint {
+                txid: bitcoin::Txid::from_hex(&table_value.txid[..])?,
+                vout: table_value.vout as u32,
+            },
+            blinding: table_value.blinding as u64,
+            amount: table_value.amount,
+            expiry: table_value.expiry,
+            paid: table_value.paid,
+        })
+    }
+
+    #[inline]
+    pub fn outpoint_reveal(&self) -> OutpointReveal {
+        OutpointReveal {
+            blinding: self.blinding,
+            txid: self.outpoint.txid,
+            vout: self.outpoint.vout,
+        }
+    }
+
+    #[inline]
+    pub fn outpoint_hash(&self) -> OutpointHash {
+        self.outpoint_reveal().conceal()
+    }
+
+    pub fn status(&self) -> InvoiceStatus {
+        match self.expiry {
+            _ if self.paid => InvoiceStatus::Paid,
+            Some(expiry) if expiry < Utc::now().naive_utc() => {
+                InvoiceStatus::Expired
+            }
+            _ => InvoiceStatus::Pending,
+        }
+    }
+
+    #[inline]
+    pub fn mark_paid(&mut self) {
+        self.paid = true;
+    }
 }
 
 impl From<OutpointDescriptor> for Outpoint {
@@ -107,6 +256,91 @@ impl FromStr for Outpoint {
     }
 }
 
+impl Invoice {
+    /// Creates invoice with only required fields present
+    pub fn with(
+        contract_id: ContractId,
+        outpoint: Outpoint,
+        amount: AccountingValue,
+    ) -> Self {
+        Self {
+            contract_id,
+            outpoint,
+            amount,
+            alternatives: vec![],
+            expiry: None,
+            memo: None,
+            merchant_ref: None,
+            endpoint: None,
+            signature: None,
+        }
+    }
+
+    /// Signs invoice with the payee node private key. The signature commits
+    /// to all other invoice fields.
+    pub fn sign(&mut self, node_key: &SecretKey) {
+        self.signature = None;
+        let secp = Secp256k1::signing_only();
+        let message = signature_message(&self.url().to_string());
+        self.signature = Some((
+            PublicKey::from_secret_key(&secp, node_key),
+            secp.sign(&message, node_key),
+        ));
+    }
+
+    /// Checks whether invoice expiry time has passed
+    pub fn is_expired(&self) -> bool {
+        self.expiry
+            .map(|expiry| expiry < Utc::now())
+            .unwrap_or(false)
+    }
+
+    fn url(&self) -> Url {
+        let mut url = Url::parse(&format!(
+            "rgb20:{}?asset={}&amount={}",
+            self.outpoint,
+            self.contract_id.to_bech32(),
+            self.amount
+        ))
+        .expect("Internal Url generation error");
+        {
+            let mut query = url.query_pairs_mut();
+            for alternative in &self.alternatives {
+                query.append_pair("alt", &alternative.to_string());
+            }
+            if let Some(expiry) = self.expiry {
+                query.append_pair("expiry", &expiry.timestamp().to_string());
+            }
+            if let Some(ref memo) = self.memo {
+                query.append_pair("memo", memo);
+            }
+            if let Some(ref merchant_ref) = self.merchant_ref {
+                query.append_pair("ref", merchant_ref);
+            }
+            if let Some(ref endpoint) = self.endpoint {
+                query.append_pair("endpoint", endpoint);
+            }
+            // Signature must always go last, since it commits to the
+            // preceding part of the invoice
+            if let Some((signer, signature)) = &self.signature {
+                query.append_pair("signer", &signer.to_string());
+                query.append_pair(
+                    "sig",
+                    &signature.serialize_compact()[..].to_hex(),
+                );
+            }
+        }
+        url
+    }
+}
+
+/// Message signed by the invoice signature: hash of the invoice string
+/// preceding the signature
+fn signature_message(unsigned: &str) -> Message {
+    Message::from_slice(&sha256::Hash::hash(unsigned.as_bytes())[..])
+        .expect("SHA256 hash is always a valid signature message")
+}
+
 impl FromStr for Invoice {
     type Err = Error;
 
@@ -130,11 +364,68 @@ impl FromStr for Invoice {
             .ok_or(Error::NoAsset)?;
         let contract_id = ContractId::from_bech32_str(&contract_id)
             .map_err(|_| Error::WrongAssetEncoding)?;
-        Ok(Invoice {
-            contract_id,
-            outpoint,
-            amount,
-        })
+
+        // All other fields are optional; unknown fields are ignored for the
+        // forward compatibility
+        let mut invoice = Invoice::with(contract_id, outpoint, amount);
+        let mut signer = None;
+        let mut signature = None;
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "alt" => invoice.alternatives.push(value.parse()?),
+                "expiry" => {
+                    let timestamp = value
+                        .parse()
+                        .map_err(|_| Error::WrongExpiryEncoding)?;
+                    invoice.expiry = Some(DateTime::from_utc(
+                        NaiveDateTime::from_timestamp_opt(timestamp, 0)
+                            .ok_or(Error::WrongExpiryEncoding)?,
+                        Utc,
+                    ));
+                }
+                "memo" => invoice.memo = Some(value.to_string()),
+                "ref" => invoice.merchant_ref = Some(value.to_string()),
+                "endpoint" => invoice.endpoint = Some(value.to_string()),
+                "signer" => {
+                    signer = Some(
+                        PublicKey::from_str(&value)
+                            .map_err(|_| Error::WrongSignatureEncoding)?,
+                    )
+                }
+                "sig" => {
+                    signature = Some(
+                        Vec::<u8>::from_hex(&value)
+                            .ok()
+                            .and_then(|data| {
+                                Signature::from_compact(&data).ok()
+                            })
+                            .ok_or(Error::WrongSignatureEncoding)?,
+                    )
+                }
+                _ => {}
+            }
+        }
+
+        match (signer, signature) {
+            (Some(signer), Some(signature)) => {
+                // Signed message is reconstructed from the parsed fields
+                // rather than taken from the original string, so no field can
+                // be modified or appended after the signature
+                let unsigned = invoice.url().to_string();
+                Secp256k1::verification_only()
+                    .verify(&signature_message(&unsigned), &signature, &signer)
+                    .map_err(|_: secp256k1::Error| Error::InvalidSignature)?;
+                invoice.signature = Some((signer, signature));
+            }
+            (None, None) => {}
+            _ => Err(Error::WrongSignatureEncoding)?,
+        }
+
+        if invoice.is_expired() {
+            Err(Error::Expired)?
+        }
+
+        Ok(invoice)
     }
 }
 
@@ -160,13 +451,172 @@ impl Display for Outpoint {
 
 impl Display for Invoice {
     fn fmt(&self, f: &mut Formatter<'_>) -> ::core::fmt::Result {
-        let url = Url::parse(&format!(
-            "rgb20:{}?asset={}&amount={}",
-            self.outpoint,
-            self.contract_id.to_bech32(),
-            self.amount
-        ))
-        .expect("Internal Url generation error");
-        write!(f, "{}", url)
+        write!(f, "{}", self.url())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn outpoint_reveal(vout: u32) -> OutpointReveal {
+        OutpointReveal {
+            blinding: 0xdeadbeef,
+            txid: bitcoin::Txid::from_inner([0x01; 32]),
+            vout,
+        }
+    }
+
+    fn outpoint(vout: u32) -> Outpoint {
+        Outpoint::BlindedUtxo(outpoint_reveal(vout).conceal())
+    }
+
+    fn invoice() -> Invoice {
+        let mut invoice = Invoice::with(
+            ContractId::from_inner([0x02; 32]),
+            outpoint(0),
+            10.5,
+        );
+        invoice.alternatives = vec![outpoint(1), outpoint(2)];
+        invoice.expiry = Some(DateTime::from_utc(
+            NaiveDateTime::from_timestamp(Utc::now().timestamp() + 3600, 0),
+            Utc,
+        ));
+        invoice.memo = Some(s!("Coffee & cake"));
+        invoice.merchant_ref = Some(s!("order#42"));
+        invoice.endpoint = Some(s!("lnp://127.0.0.1:9735"));
+        invoice
+    }
+
+    #[test]
+    fn test_parse_minimal() {
+        let invoice = Invoice::with(
+            ContractId::from_inner([0x02; 32]),
+            outpoint(0),
+            10.0,
+        );
+        assert_eq!(Invoice::from_str(&invoice.to_string()), Ok(invoice));
+    }
+
+    #[test]
+    fn test_parse_full() {
+        let invoice = invoice();
+        assert_eq!(Invoice::from_str(&invoice.to_string()), Ok(invoice));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let invoice = invoice().to_string();
+        assert_eq!(
+            Invoice::from_str(&invoice.replace("rgb20:", "bitcoin:")),
+            Err(Error::WrongUrlScheme)
+        );
+        assert_eq!(
+            Invoice::from_str(&invoice.replace("amount=", "value=")),
+            Err(Error::NoAmount)
+        );
+        assert_eq!(
+            Invoice::from_str(&invoice.replace("amount=10.5", "amount=ten")),
+            Err(Error::WrongAmountEncoding)
+        );
+        assert_eq!(
+            Invoice::from_str(&invoice.replace("asset=", "contract=")),
+            Err(Error::NoAsset)
+        );
+        assert_eq!(
+            Invoice::from_str(&invoice.replace("expiry=", "expiry=x")),
+            Err(Error::WrongExpiryEncoding)
+        );
+    }
+
+    #[test]
+    fn test_sign() {
+        let node_key = SecretKey::from_slice(&[0x11; 32]).unwrap();
+        let mut invoice = invoice();
+        invoice.sign(&node_key);
+        let (signer, _) = invoice.signature.unwrap();
+        assert_eq!(
+            signer,
+            PublicKey::from_secret_key(&Secp256k1::new(), &node_key)
+        );
+
+        let signed = invoice.to_string();
+        assert_eq!(Invoice::from_str(&signed), Ok(invoice));
+        assert_eq!(
+            Invoice::from_str(&signed.replace("Coffee", "Tea")),
+            Err(Error::InvalidSignature)
+        );
+        assert_eq!(
+            Invoice::from_str(&signed.replace("&signer=", "&key=")),
+            Err(Error::WrongSignatureEncoding)
+        );
+    }
+
+    #[test]
+    fn test_sign_appended_fields() {
+        let node_key = SecretKey::from_slice(&[0x11; 32]).unwrap();
+        let mut invoice = invoice();
+        invoice.sign(&node_key);
+        let signed = invoice.to_string();
+
+        assert_eq!(
+            Invoice::from_str(&format!("{}&alt={}", signed, outpoint(3))),
+            Err(Error::InvalidSignature)
+        );
+        assert_eq!(
+            Invoice::from_str(&format!("{}&memo=Refund", signed)),
+            Err(Error::InvalidSignature)
+        );
+        assert_eq!(
+            Invoice::from_str(&format!("{}&endpoint=lnp://evil", signed)),
+            Err(Error::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn test_expiry() {
+        let mut invoice = invoice();
+        assert!(!invoice.is_expired());
+        invoice.expiry = Some(DateTime::from_utc(
+            NaiveDateTime::from_timestamp(Utc::now().timestamp() - 1, 0),
+            Utc,
+        ));
+        assert!(invoice.is_expired());
+        assert_eq!(
+            Invoice::from_str(&invoice.to_string()),
+            Err(Error::Expired)
+        );
+    }
+
+    #[test]
+    fn test_record_status() {
+        let contract_id = ContractId::from_inner([0x02; 32]);
+        let now = Utc::now().naive_utc();
+
+        let mut record =
+            InvoiceRecord::with(contract_id, outpoint_reveal(0), 10.0, None);
+        assert_eq!(record.outpoint_reveal(), outpoint_reveal(0));
+        assert_eq!(record.outpoint_hash(), outpoint_reveal(0).conceal());
+        assert_eq!(record.status(), InvoiceStatus::Pending);
+        record.mark_paid();
+        assert_eq!(record.status(), InvoiceStatus::Paid);
+
+        let mut record = InvoiceRecord::with(
+            contract_id,
+            outpoint_reveal(0),
+            10.0,
+            Some(now - chrono::Duration::seconds(1)),
+        );
+        assert_eq!(record.status(), InvoiceStatus::Expired);
+        record.mark_paid();
+        assert_eq!(record.status(), InvoiceStatus::Paid);
+
+        let record = InvoiceRecord::with(
+            contract_id,
+            outpoint_reveal(0),
+            10.0,
+            Some(now + chrono::Duration::hours(1)),
+        );
+        assert_eq!(record.status(), InvoiceStatus::Pending);
     }
 }
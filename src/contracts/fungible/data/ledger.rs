@@ -0,0 +1,236 @@
+// RGB standard library
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use chrono::{NaiveDateTime, Utc};
+use core::str::FromStr;
+use num_traits::FromPrimitive;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use lnpbp::bitcoin::Txid;
+use lnpbp::bp::blind::OutpointHash;
+use lnpbp::hex::FromHex;
+use lnpbp::rgb::{ContractId, NodeId};
+
+use crate::contracts::fungible::cache::models::SqlLedgerEntry;
+use crate::contracts::fungible::cache::SqlCacheError;
+
+use super::AccountingValue;
+
+/// Operation changing the amount of the asset controlled by the node
+#[derive(
+    Copy, Clone, PartialEq, Eq, Hash, Debug, Display, FromPrimitive, ToPrimitive,
+)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize,),
+    serde(crate = "serde_crate")
+)]
+#[non_exhaustive]
+pub enum LedgerOperation {
+    /// Primary or secondary issue of the asset
+    #[display("issue")]
+    Issue,
+
+    /// Outgoing transfer created by the node
+    #[display("sent")]
+    Sent,
+
+    /// Incoming transfer accepted by the node
+    #[display("received")]
+    Received,
+
+    /// Allocations removed from the node with `forget` command
+    #[display("forgotten")]
+    Forgotten,
+}
+impl_enum_strict_encoding!(LedgerOperation);
+
+/// Entry of the per-asset ledger, recording a single change of the asset
+/// amount controlled by the node
+#[derive(
+    Clone, Getters, PartialEq, Debug, Display, StrictEncode, StrictDecode,
+)]
+#[display(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize,),
+    serde(crate = "serde_crate")
+)]
+pub struct LedgerEntry {
+    contract_id: ContractId,
+
+    /// Id of the genesis or state transition performing the operation
+    node_id: NodeId,
+
+    operation: LedgerOperation,
+
+    /// Time when the operation was registered by the node
+    timestamp: NaiveDateTime,
+
+    /// Witness transaction of the state transition; `None` for genesis and
+    /// forgotten allocations
+    witness_txid: Option<Txid>,
+
+    /// Change of the asset amount controlled by the node; negative for the
+    /// outgoing operations
+    delta: AccountingValue,
+
+    /// Concealed seal of the counterparty, if known
+    counterparty: Option<OutpointHash>,
+}
+
+impl LedgerEntry {
+    /// Creates ledger entry timestamped with the current time
+    pub fn with(
+        contract_id: ContractId,
+        node_id: NodeId,
+        operation: LedgerOperation,
+        witness_txid: Option<Txid>,
+        delta: AccountingValue,
+        counterparty: Option<OutpointHash>,
+    ) -> Self {
+        Self {
+            contract_id,
+            node_id,
+            operation,
+            timestamp: Utc::now().naive_utc(),
+            witness_txid,
+            delta,
+            counterparty,
+        }
+    }
+
+    /// Create a ledger entry from an sqlite ledger table entry
+    pub fn from_sql_ledger_entry(
+        table_value: SqlLedgerEntry,
+    ) -> Result<Self, SqlCacheError> {
+        Ok(Self {
+            contract_id: ContractId::from_str(&table_value.contract_id[..])?,
+            node_id: NodeId::from_hex(&table_value.node_id[..])?,
+            operation: LedgerOperation::from_i32(table_value.operation).ok_or(
+                SqlCacheError::Generic(format!(
+                    "Unknown ledger operation {}",
+                    table_value.operation
+                )),
+            )?,
+            timestamp: table_value.timestamp,
+            witness_txid: table_value
+                .witness_txid
+                .map(|txid| Txid::from_hex(&txid[..]))
+                .transpose()?,
+            delta: table_value.delta,
+            counterparty: table_value
+                .counterparty
+                .map(|hash| OutpointHash::from_hex(&hash[..]))
+                .transpose()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use lnpbp::hashes::Hash;
+    use lnpbp::hex::ToHex;
+    use num_traits::ToPrimitive;
+
+    fn sql_entry(entry: &LedgerEntry) -> SqlLedgerEntry {
+        SqlLedgerEntry {
+            id: 0,
+            contract_id: entry.contract_id().to_hex(),
+            node_id: entry.node_id().to_hex(),
+            operation: entry.operation().to_i32().unwrap(),
+            timestamp: *entry.timestamp(),
+            witness_txid: entry.witness_txid().map(|txid| txid.to_hex()),
+            delta: *entry.delta(),
+            counterparty: entry.counterparty().map(|hash| hash.to_hex()),
+        }
+    }
+
+    #[test]
+    fn test_entry_with() {
+        let before = Utc::now().naive_utc();
+        let entry = LedgerEntry::with(
+            ContractId::from_inner([0x01; 32]),
+            NodeId::from_inner([0x02; 32]),
+            LedgerOperation::Sent,
+            Some(Txid::from_inner([0x03; 32])),
+            -10.5,
+            Some(OutpointHash::from_inner([0x04; 32])),
+        );
+        assert_eq!(*entry.operation(), LedgerOperation::Sent);
+        assert_eq!(*entry.delta(), -10.5);
+        assert!(*entry.timestamp() >= before);
+        assert!(*entry.timestamp() <= Utc::now().naive_utc());
+    }
+
+    #[test]
+    fn test_sql_entry() {
+        let entries = vec![
+            LedgerEntry::with(
+                ContractId::from_inner([0x01; 32]),
+                NodeId::from_inner([0x01; 32]),
+                LedgerOperation::Issue,
+                None,
+                100.0,
+                None,
+            ),
+            LedgerEntry::with(
+                ContractId::from_inner([0x01; 32]),
+                NodeId::from_inner([0x02; 32]),
+                LedgerOperation::Sent,
+                Some(Txid::from_inner([0x03; 32])),
+                -10.5,
+                Some(OutpointHash::from_inner([0x04; 32])),
+            ),
+            LedgerEntry::with(
+                ContractId::from_inner([0x01; 32]),
+                NodeId::from_inner([0x05; 32]),
+                LedgerOperation::Received,
+                Some(Txid::from_inner([0x06; 32])),
+                20.0,
+                None,
+            ),
+            LedgerEntry::with(
+                ContractId::from_inner([0x01; 32]),
+                NodeId::from_inner([0x05; 32]),
+                LedgerOperation::Forgotten,
+                None,
+                -20.0,
+                None,
+            ),
+        ];
+        for entry in entries {
+            assert_eq!(
+                LedgerEntry::from_sql_ledger_entry(sql_entry(&entry)).unwrap(),
+                entry
+            );
+        }
+    }
+
+    #[test]
+    fn test_sql_entry_unknown_operation() {
+        let entry = LedgerEntry::with(
+            ContractId::from_inner([0x01; 32]),
+            NodeId::from_inner([0x02; 32]),
+            LedgerOperation::Issue,
+            None,
+            100.0,
+            None,
+        );
+        let mut sql = sql_entry(&entry);
+        sql.operation = 42;
+        assert!(LedgerEntry::from_sql_ledger_entry(sql).is_err());
+    }
+}
@@ -13,14 +13,21 @@
 
 mod asset;
 mod invoice;
+mod ledger;
 mod outcoins;
 pub mod schema;
+mod selection;
 
+pub(crate) use asset::LegacyAsset;
 pub use asset::{
-    AccountingAmount, AccountingValue, Allocation, Asset, Issue, Supply,
+    AccountingAmount, AccountingValue, Allocation, Asset, Issue, Nomination,
+    Supply,
 };
 pub use invoice::{
-    Error as InvoiceError, Invoice, Outpoint, OutpointDescriptor,
+    Error as InvoiceError, Invoice, InvoiceRecord, InvoiceStatus, Outpoint,
+    OutpointDescriptor,
 };
+pub use ledger::{LedgerEntry, LedgerOperation};
 pub use outcoins::{ConsealCoins, OutpointCoins, SealCoins};
 pub use schema::Error;
+pub use selection::SelectionStrategy;
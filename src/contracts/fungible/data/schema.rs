@@ -24,6 +24,7 @@ use lnpbp::rgb::schema::{
 
 use crate::error::ServiceErrorDomain;
 use crate::type_map;
+use crate::util::SchemaTypeNames;
 
 #[derive(
     Clone,
@@ -357,6 +358,81 @@ pub fn schema() -> Schema {
     }
 }
 
+impl FieldType {
+    pub fn all() -> Vec<Self> {
+        vec![
+            FieldType::Ticker,
+            FieldType::Name,
+            FieldType::ContractText,
+            FieldType::Precision,
+            FieldType::IssuedSupply,
+            FieldType::BurnedSupply,
+            FieldType::Timestamp,
+            FieldType::BurnUtxo,
+            FieldType::HistoryProof,
+            FieldType::HistoryProofFormat,
+        ]
+    }
+}
+
+impl OwnedRightsType {
+    pub fn all() -> Vec<Self> {
+        vec![
+            OwnedRightsType::Inflation,
+            OwnedRightsType::Assets,
+            OwnedRightsType::Epoch,
+            OwnedRightsType::BurnReplace,
+            OwnedRightsType::Renomination,
+        ]
+    }
+
+    /// Resolves owned rights type id used by the schema into the type name
+    pub fn from_type(ty: usize) -> Option<Self> {
+        Self::all().into_iter().find(|t| **t == ty)
+    }
+}
+
+impl TransitionType {
+    pub fn all() -> Vec<Self> {
+        vec![
+            TransitionType::Issue,
+            TransitionType::Transfer,
+            TransitionType::Epoch,
+            TransitionType::Burn,
+            TransitionType::BurnAndReplace,
+            TransitionType::Renomination,
+            TransitionType::RightsSplit,
+        ]
+    }
+
+    /// Resolves transition type id used by the schema into the type name
+    pub fn from_type(ty: usize) -> Option<Self> {
+        Self::all().into_iter().find(|t| **t == ty)
+    }
+}
+
+/// Human-readable names of the RGB-20 schema types, which are registered in
+/// the stash together with the schema
+pub fn type_names() -> SchemaTypeNames {
+    SchemaTypeNames {
+        schema_name: s!("RGB-20"),
+        field_types: FieldType::all()
+            .into_iter()
+            .map(|t| (*t, t.to_string()))
+            .collect(),
+        owned_right_types: OwnedRightsType::all()
+            .into_iter()
+            .map(|t| (*t, t.to_string()))
+            .collect(),
+        public_right_types: bmap! {},
+        extension_types: bmap! {},
+        transition_types: TransitionType::all()
+            .into_iter()
+            .map(|t| (*t, t.to_string()))
+            .collect(),
+    }
+}
+
 impl Deref for FieldType {
     type Target = usize;
 
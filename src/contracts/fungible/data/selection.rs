@@ -0,0 +1,347 @@
+// RGB standard library
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use core::str::FromStr;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use lnpbp::bitcoin::OutPoint;
+use lnpbp::rgb::AtomicValue;
+
+use super::Allocation;
+use crate::error::ParseError;
+
+/// Strategy for selecting asset inputs for a transfer from the known asset
+/// allocations
+#[derive(
+    Copy, Clone, PartialEq, Eq, Hash, Debug, Display, FromPrimitive, ToPrimitive,
+)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize,),
+    serde(crate = "serde_crate")
+)]
+#[non_exhaustive]
+pub enum SelectionStrategy {
+    /// Spends outputs with the largest allocations first
+    #[display("largest-first")]
+    LargestFirst,
+
+    /// Spends the smallest possible number of outputs, minimizing the change
+    /// among the sets of outputs of the same size
+    #[display("fewest-inputs")]
+    FewestInputs,
+
+    /// Prefers a single output holding exactly the required amount, so no
+    /// change is produced; otherwise spends outputs in random order
+    #[display("privacy")]
+    Privacy,
+}
+impl_enum_strict_encoding!(SelectionStrategy);
+
+impl Default for SelectionStrategy {
+    fn default() -> Self {
+        SelectionStrategy::FewestInputs
+    }
+}
+
+impl FromStr for SelectionStrategy {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match &s.to_lowercase() {
+            s if s.starts_with("largest") => Self::LargestFirst,
+            s if s.starts_with("fewest") => Self::FewestInputs,
+            s if s.starts_with("priv") => Self::Privacy,
+            _ => Err(ParseError)?,
+        })
+    }
+}
+
+impl SelectionStrategy {
+    /// Selects outputs from `allocations` holding in total at least `amount`
+    /// of the asset. Since spending an output closes all asset allocations
+    /// assigned to it, outputs are selected as a whole. Returns `None` if
+    /// the allocations are not sufficient for the requested amount. Amounts
+    /// are summed with saturation, which is sufficient for comparing them
+    /// with the requested amount.
+    pub fn select(
+        self,
+        allocations: &BTreeMap<OutPoint, Vec<Allocation>>,
+        amount: AtomicValue,
+    ) -> Option<Vec<OutPoint>> {
+        let coins = allocations
+            .iter()
+            .map(|(outpoint, allocations)| {
+                (
+                    *outpoint,
+                    allocations.iter().fold(0u64, |acc, alloc| {
+                        acc.saturating_add(alloc.value().value)
+                    }),
+                )
+            })
+            .filter(|(_, value)| *value > 0)
+            .collect::<Vec<_>>();
+        self.select_coins(coins, amount)
+    }
+
+    /// Selects outputs holding the given total amounts of the asset
+    fn select_coins(
+        self,
+        coins: Vec<(OutPoint, AtomicValue)>,
+        amount: AtomicValue,
+    ) -> Option<Vec<OutPoint>> {
+        if coins
+            .iter()
+            .fold(0u64, |acc, (_, value)| acc.saturating_add(*value))
+            < amount
+        {
+            return None;
+        }
+
+        match self {
+            SelectionStrategy::LargestFirst => largest_first(coins, amount),
+            SelectionStrategy::FewestInputs => fewest_inputs(coins, amount),
+            SelectionStrategy::Privacy => privacy(coins, amount),
+        }
+    }
+}
+
+fn accumulate(
+    coins: impl IntoIterator<Item = (OutPoint, AtomicValue)>,
+    amount: AtomicValue,
+) -> Option<Vec<OutPoint>> {
+    let mut selected = vec![];
+    let mut total = 0u64;
+    for (outpoint, value) in coins {
+        if total >= amount {
+            break;
+        }
+        total = total.saturating_add(value);
+        selected.push(outpoint);
+    }
+    if total >= amount {
+        Some(selected)
+    } else {
+        None
+    }
+}
+
+fn largest_first(
+    mut coins: Vec<(OutPoint, AtomicValue)>,
+    amount: AtomicValue,
+) -> Option<Vec<OutPoint>> {
+    coins.sort_by(|(_, a), (_, b)| b.cmp(a));
+    accumulate(coins, amount)
+}
+
+fn fewest_inputs(
+    mut coins: Vec<(OutPoint, AtomicValue)>,
+    amount: AtomicValue,
+) -> Option<Vec<OutPoint>> {
+    // Largest-first selection gives the minimal number of inputs
+    let count = largest_first(coins.clone(), amount)?.len();
+
+    // Picking each next input as the smallest one which, together with the
+    // largest remaining ones, still covers the rest of the amount
+    coins.sort_by(|(_, a), (_, b)| a.cmp(b));
+    let mut selected = vec![];
+    let mut rest = amount;
+    for left in (1..=count).rev() {
+        if rest == 0 {
+            break;
+        }
+        let pos = (0..coins.len()).find(|pos| {
+            let others = coins
+                .iter()
+                .enumerate()
+                .rev()
+                .filter(|(no, _)| no != pos)
+                .take(left - 1)
+                .fold(0u64, |acc, (_, (_, value))| acc.saturating_add(*value));
+            coins[*pos].1.saturating_add(others) >= rest
+        })?;
+        let (outpoint, value) = coins.remove(pos);
+        rest = rest.saturating_sub(value);
+        selected.push(outpoint);
+    }
+    Some(selected)
+}
+
+fn privacy(
+    mut coins: Vec<(OutPoint, AtomicValue)>,
+    amount: AtomicValue,
+) -> Option<Vec<OutPoint>> {
+    use lnpbp::bitcoin::secp256k1::rand::{self, seq::SliceRandom};
+
+    if let Some((outpoint, _)) =
+        coins.iter().find(|(_, value)| *value == amount)
+    {
+        return Some(vec![*outpoint]);
+    }
+    coins.shuffle(&mut rand::thread_rng());
+    accumulate(coins, amount)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use lnpbp::bitcoin::Txid;
+    use lnpbp::hashes::Hash;
+
+    fn coins(values: &[AtomicValue]) -> Vec<(OutPoint, AtomicValue)> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(vout, value)| {
+                (
+                    OutPoint {
+                        txid: Txid::from_inner([0x01; 32]),
+                        vout: vout as u32,
+                    },
+                    *value,
+                )
+            })
+            .collect()
+    }
+
+    fn selected_values(
+        strategy: SelectionStrategy,
+        values: &[AtomicValue],
+        amount: AtomicValue,
+    ) -> Option<Vec<AtomicValue>> {
+        let coins = coins(values);
+        strategy
+            .select_coins(coins.clone(), amount)
+            .map(|selected| {
+                let mut selected = selected
+                    .into_iter()
+                    .map(|outpoint| {
+                        coins
+                            .iter()
+                            .find(|(o, _)| *o == outpoint)
+                            .map(|(_, value)| *value)
+                            .unwrap()
+                    })
+                    .collect::<Vec<_>>();
+                selected.sort();
+                selected
+            })
+    }
+
+    #[test]
+    fn test_strategy_from_str() {
+        assert_eq!(
+            SelectionStrategy::from_str("largest-first").unwrap(),
+            SelectionStrategy::LargestFirst
+        );
+        assert_eq!(
+            SelectionStrategy::from_str("Fewest-Inputs").unwrap(),
+            SelectionStrategy::FewestInputs
+        );
+        assert_eq!(
+            SelectionStrategy::from_str("privacy").unwrap(),
+            SelectionStrategy::Privacy
+        );
+        assert!(SelectionStrategy::from_str("random").is_err());
+    }
+
+    #[test]
+    fn test_insufficient() {
+        for strategy in &[
+            SelectionStrategy::LargestFirst,
+            SelectionStrategy::FewestInputs,
+            SelectionStrategy::Privacy,
+        ] {
+            assert_eq!(selected_values(*strategy, &[10, 20, 30], 61), None);
+            assert_eq!(selected_values(*strategy, &[], 1), None);
+            assert_eq!(
+                selected_values(*strategy, &[10, 20, 30], 60),
+                Some(vec![10, 20, 30])
+            );
+        }
+    }
+
+    #[test]
+    fn test_largest_first() {
+        assert_eq!(
+            selected_values(SelectionStrategy::LargestFirst, &[10, 50, 30], 60),
+            Some(vec![30, 50])
+        );
+        assert_eq!(
+            selected_values(SelectionStrategy::LargestFirst, &[10, 50, 30], 40),
+            Some(vec![50])
+        );
+    }
+
+    #[test]
+    fn test_fewest_inputs() {
+        // Single input is enough, so the smallest sufficient one is picked
+        assert_eq!(
+            selected_values(SelectionStrategy::FewestInputs, &[10, 50, 30], 25),
+            Some(vec![30])
+        );
+        // Two inputs are required; change is minimized among the pairs
+        assert_eq!(
+            selected_values(
+                SelectionStrategy::FewestInputs,
+                &[10, 50, 30, 45],
+                60
+            ),
+            Some(vec![10, 50])
+        );
+        // Pair without change is preferred over the two largest inputs
+        assert_eq!(
+            selected_values(
+                SelectionStrategy::FewestInputs,
+                &[5, 20, 40, 100],
+                120
+            ),
+            Some(vec![20, 100])
+        );
+        // Number of inputs never exceeds the one of largest-first selection
+        assert_eq!(
+            selected_values(
+                SelectionStrategy::FewestInputs,
+                &[1, 2, 3, 4, 100],
+                10
+            ),
+            Some(vec![100])
+        );
+    }
+
+    #[test]
+    fn test_privacy_exact_match() {
+        assert_eq!(
+            selected_values(SelectionStrategy::Privacy, &[10, 50, 30], 30),
+            Some(vec![30])
+        );
+        let selected =
+            selected_values(SelectionStrategy::Privacy, &[10, 50, 30], 45)
+                .unwrap();
+        assert!(selected.iter().sum::<AtomicValue>() >= 45);
+    }
+
+    #[test]
+    fn test_saturation() {
+        assert_eq!(
+            selected_values(
+                SelectionStrategy::LargestFirst,
+                &[core::u64::MAX, core::u64::MAX],
+                core::u64::MAX
+            ),
+            Some(vec![core::u64::MAX])
+        );
+    }
+}
@@ -20,8 +20,9 @@ pub(self) mod cache;
 
 pub use data::{
     schema, AccountingAmount, AccountingValue, Allocation, Asset, ConsealCoins,
-    Error, Invoice, InvoiceError, Issue, Outpoint, OutpointCoins,
-    OutpointDescriptor, SealCoins, Supply,
+    Error, Invoice, InvoiceError, InvoiceRecord, InvoiceStatus, Issue,
+    LedgerEntry, LedgerOperation, Nomination, Outpoint, OutpointCoins,
+    OutpointDescriptor, SealCoins, SelectionStrategy, Supply,
 };
 
 pub use config::{Config, Opts};
@@ -13,15 +13,20 @@
 
 use chrono::Utc;
 use core::convert::TryFrom;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 use lnpbp::bitcoin::OutPoint;
 use lnpbp::bp;
 use lnpbp::rgb::prelude::*;
 use lnpbp::secp256k1zkp;
 
-use super::schema::{self, FieldType, OwnedRightsType, TransitionType};
-use super::{AccountingAmount, Allocation, Asset, ConsealCoins, SealCoins};
+use super::schema::{
+    self, FieldType, HistoryProofFormat, OwnedRightsType, TransitionType,
+};
+use super::{
+    AccountingAmount, AccountingValue, Allocation, Asset, ConsealCoins,
+    OutpointCoins, SealCoins,
+};
 
 use crate::error::ServiceErrorDomain;
 use crate::{field, type_map};
@@ -48,11 +53,11 @@ pub fn issue(
         metadata.insert(*FieldType::ContractText, field!(String, description));
     }
 
-    let mut issued_supply = 0u64;
+    let issued_supply =
+        checked_sum(allocation.iter().map(|(_, value)| *value))?;
     let allocations = allocation
         .into_iter()
         .map(|(outpoint, value)| {
-            issued_supply += value;
             (SealDefinition::TxOutpoint(outpoint.into()), value)
         })
         .collect();
@@ -99,7 +104,7 @@ pub fn issue(
 
     if let Some(outpoint) = epoch {
         owned_rights.insert(
-            *OwnedRightsType::BurnReplace,
+            *OwnedRightsType::Epoch,
             Assignments::Declarative(vec![OwnedState::Revealed {
                 seal_definition: SealDefinition::TxOutpoint(outpoint.into()),
                 assigned_state: data::Void,
@@ -122,12 +127,17 @@ pub fn issue(
 }
 
 /// Function creates a fungible asset-specific state transition (i.e. RGB-20
-/// schema-based) given an asset information, inputs and desired outputs
+/// schema-based) given an asset information, inputs and desired outputs.
+/// Allocations to `theirs_addressed` seals are kept revealed for the
+/// receiver. If the inputs exceed the outputs, the difference is allocated to
+/// the `change` seal.
 pub fn transfer(
     asset: &mut Asset,
     inputs: Vec<OutPoint>,
     ours: Vec<SealCoins>,
     theirs: Vec<ConsealCoins>,
+    theirs_addressed: Vec<(SealDefinition, AccountingValue)>,
+    change: Option<SealDefinition>,
 ) -> Result<Transition, ServiceErrorDomain> {
     // Collecting all input allocations
     let mut input_allocations = Vec::<Allocation>::new();
@@ -142,23 +152,28 @@ pub fn transfer(
         input_allocations.extend(found);
     }
     // Computing sum of inputs
-    let total_inputs = input_allocations
-        .iter()
-        .fold(0u64, |acc, alloc| acc + alloc.value().value);
+    let total_inputs =
+        checked_sum(input_allocations.iter().map(|alloc| alloc.value().value))?;
 
     let metadata = type_map! {};
-    let mut total_outputs = 0;
-    let allocations_ours = ours
+    let mut allocations_ours = ours
         .into_iter()
         .map(|outcoins| {
             let amount = AccountingAmount::transmutate(
                 *asset.fractional_bits(),
                 outcoins.coins,
             );
-            total_outputs += amount;
             (outcoins.seal_definition(), amount)
         })
-        .collect();
+        .collect::<Vec<_>>();
+    // Allocations to the receiver's addresses are kept revealed, like ours
+    allocations_ours.extend(theirs_addressed.into_iter().map(
+        |(seal_definition, coins)| {
+            let amount =
+                AccountingAmount::transmutate(*asset.fractional_bits(), coins);
+            (seal_definition, amount)
+        },
+    ));
     let allocations_theirs = theirs
         .into_iter()
         .map(|outcoincealed| {
@@ -166,13 +181,26 @@ pub fn transfer(
                 *asset.fractional_bits(),
                 outcoincealed.coins,
             );
-            total_outputs += amount;
             (outcoincealed.seal_confidential, amount)
         })
-        .collect();
+        .collect::<Vec<_>>();
+    let total_outputs = checked_sum(
+        allocations_ours
+            .iter()
+            .map(|(_, amount)| *amount)
+            .chain(allocations_theirs.iter().map(|(_, amount)| *amount)),
+    )?;
 
-    if total_inputs != total_outputs {
-        Err("Input amount is not equal to output amount".to_string())?
+    if total_inputs < total_outputs {
+        Err("Input amount is less than output amount".to_string())?
+    }
+    if total_inputs > total_outputs {
+        let seal = change.ok_or(
+            "Input amount exceeds output amount while no change output is \
+            given"
+                .to_string(),
+        )?;
+        allocations_ours.push((seal, total_inputs - total_outputs));
     }
 
     let input_amounts = input_allocations
@@ -205,3 +233,554 @@ pub fn transfer(
 
     Ok(transition)
 }
+
+/// Function creates a blank state transition for the RGB-20 asset, moving
+/// all asset allocations from the `inputs` to the `change` seal. Blank
+/// transitions are required when outputs holding the asset are spent by an
+/// operation on some other contract.
+pub fn blank(
+    asset: &mut Asset,
+    inputs: Vec<OutPoint>,
+    change: SealDefinition,
+) -> Result<Transition, ServiceErrorDomain> {
+    transfer(asset, inputs, vec![], vec![], vec![], Some(change))
+}
+
+/// Function creates a secondary issuance state transition for the RGB-20
+/// asset, spending inflation right assigned to the `inflation` outpoint by
+/// the `parent` node. The remaining part of the inflation allowance is
+/// re-assigned to the `remainder` outpoint, if one is given; otherwise it is
+/// irreversibly destroyed.
+pub fn inflate(
+    asset: &Asset,
+    inflation: OutPoint,
+    parent: (NodeId, u16),
+    ours: Vec<SealCoins>,
+    theirs: Vec<ConsealCoins>,
+    remainder: Option<OutPoint>,
+) -> Result<Transition, ServiceErrorDomain> {
+    let allowance = asset
+        .known_inflation()
+        .get(&inflation)
+        .ok_or(format!("Unknown inflation right {}", inflation))?
+        .atomic_value();
+
+    let allocations_ours = ours
+        .into_iter()
+        .map(|outcoins| {
+            let amount = AccountingAmount::transmutate(
+                *asset.fractional_bits(),
+                outcoins.coins,
+            );
+            (outcoins.seal_definition(), amount)
+        })
+        .collect::<Vec<_>>();
+    let allocations_theirs = theirs
+        .into_iter()
+        .map(|outcoincealed| {
+            let amount = AccountingAmount::transmutate(
+                *asset.fractional_bits(),
+                outcoincealed.coins,
+            );
+            (outcoincealed.seal_confidential, amount)
+        })
+        .collect::<Vec<_>>();
+    let issued_supply = checked_sum(
+        allocations_ours
+            .iter()
+            .map(|(_, amount)| *amount)
+            .chain(allocations_theirs.iter().map(|(_, amount)| *amount)),
+    )?;
+
+    if issued_supply > allowance {
+        Err(format!(
+            "Issued amount {} exceeds inflation allowance {}",
+            issued_supply, allowance
+        ))?
+    }
+
+    let metadata = type_map! {
+        FieldType::IssuedSupply => field!(U64, issued_supply)
+    };
+
+    let mut assignments = type_map! {
+        OwnedRightsType::Assets =>
+        Assignments::zero_balanced(
+            vec![value::Revealed {
+                value: issued_supply,
+                blinding: secp256k1zkp::key::ONE_KEY,
+            }],
+            allocations_ours,
+            allocations_theirs,
+        )
+    };
+    if let Some(outpoint) = remainder {
+        if allowance > issued_supply {
+            assignments.insert(
+                *OwnedRightsType::Inflation,
+                Assignments::CustomData(vec![OwnedState::Revealed {
+                    seal_definition: SealDefinition::TxOutpoint(
+                        outpoint.into(),
+                    ),
+                    assigned_state: data::Revealed::U64(
+                        allowance - issued_supply,
+                    ),
+                }]),
+            );
+        }
+    }
+
+    let (node_id, index) = parent;
+    let mut parent = ParentOwnedRights::new();
+    parent.insert(
+        node_id,
+        type_map! { OwnedRightsType::Inflation => vec![index] },
+    );
+
+    let transition = Transition::with(
+        *TransitionType::Issue,
+        metadata.into(),
+        parent,
+        assignments,
+        bset![],
+        vec![],
+    );
+
+    Ok(transition)
+}
+
+/// Function creates a state transition opening new epoch for the burn and
+/// burn-and-replace procedures. It spends epoch right assigned by the
+/// `parent` node and may assign the right to open the next epoch and the
+/// right to burn (and replace) assets within the opened epoch.
+pub fn epoch(
+    parent: (NodeId, u16),
+    next_epoch: Option<OutPoint>,
+    burn_replace: Option<OutPoint>,
+) -> Result<Transition, ServiceErrorDomain> {
+    let mut owned_rights = BTreeMap::new();
+    if let Some(outpoint) = next_epoch {
+        owned_rights.insert(*OwnedRightsType::Epoch, declarative(outpoint));
+    }
+    if let Some(outpoint) = burn_replace {
+        owned_rights
+            .insert(*OwnedRightsType::BurnReplace, declarative(outpoint));
+    }
+
+    let (node_id, index) = parent;
+    let mut parent = ParentOwnedRights::new();
+    parent.insert(node_id, type_map! { OwnedRightsType::Epoch => vec![index] });
+
+    let transition = Transition::with(
+        *TransitionType::Epoch,
+        type_map! {}.into(),
+        parent,
+        owned_rights,
+        bset![],
+        vec![],
+    );
+
+    Ok(transition)
+}
+
+/// Function creates a state transition burning all assets allocated to the
+/// `burn_utxos`. It spends burn right assigned by the `parent` node and may
+/// assign the right for subsequent burns within the same epoch to the
+/// `next_burn` outpoint. The history of the burned assets may be
+/// accompanied with a proof given in one of [`HistoryProofFormat`] formats.
+pub fn burn(
+    asset: &Asset,
+    parent: (NodeId, u16),
+    burn_utxos: Vec<OutPoint>,
+    next_burn: Option<OutPoint>,
+    history_proof: Option<(u8, Vec<u8>)>,
+) -> Result<Transition, ServiceErrorDomain> {
+    let (metadata, _) = burn_metadata(asset, &burn_utxos, history_proof)?;
+    let mut owned_rights = BTreeMap::new();
+    if let Some(outpoint) = next_burn {
+        owned_rights
+            .insert(*OwnedRightsType::BurnReplace, declarative(outpoint));
+    }
+
+    Ok(burn_transition(
+        TransitionType::Burn,
+        metadata,
+        parent,
+        owned_rights,
+    ))
+}
+
+/// Function creates a state transition burning all assets allocated to the
+/// `burn_utxos` and replacing them with new allocations of exactly the same
+/// total amount. Otherwise works like [`burn`].
+pub fn burn_replace(
+    asset: &Asset,
+    parent: (NodeId, u16),
+    burn_utxos: Vec<OutPoint>,
+    next_burn: Option<OutPoint>,
+    history_proof: Option<(u8, Vec<u8>)>,
+    ours: Vec<SealCoins>,
+    theirs: Vec<ConsealCoins>,
+) -> Result<Transition, ServiceErrorDomain> {
+    let (metadata, burned_supply) =
+        burn_metadata(asset, &burn_utxos, history_proof)?;
+
+    let allocations_ours = ours
+        .into_iter()
+        .map(|outcoins| {
+            let amount = AccountingAmount::transmutate(
+                *asset.fractional_bits(),
+                outcoins.coins,
+            );
+            (outcoins.seal_definition(), amount)
+        })
+        .collect::<Vec<_>>();
+    let allocations_theirs = theirs
+        .into_iter()
+        .map(|outcoincealed| {
+            let amount = AccountingAmount::transmutate(
+                *asset.fractional_bits(),
+                outcoincealed.coins,
+            );
+            (outcoincealed.seal_confidential, amount)
+        })
+        .collect::<Vec<_>>();
+    let total_outputs = checked_sum(
+        allocations_ours
+            .iter()
+            .map(|(_, amount)| *amount)
+            .chain(allocations_theirs.iter().map(|(_, amount)| *amount)),
+    )?;
+
+    if total_outputs != burned_supply {
+        Err("Replaced amount is not equal to the burned amount".to_string())?
+    }
+
+    let mut owned_rights = type_map! {
+        OwnedRightsType::Assets =>
+        Assignments::zero_balanced(
+            vec![value::Revealed {
+                value: burned_supply,
+                blinding: secp256k1zkp::key::ONE_KEY,
+            }],
+            allocations_ours,
+            allocations_theirs,
+        )
+    };
+    if let Some(outpoint) = next_burn {
+        owned_rights
+            .insert(*OwnedRightsType::BurnReplace, declarative(outpoint));
+    }
+
+    Ok(burn_transition(
+        TransitionType::BurnAndReplace,
+        metadata,
+        parent,
+        owned_rights,
+    ))
+}
+
+fn declarative(outpoint: OutPoint) -> Assignments {
+    Assignments::Declarative(vec![OwnedState::Revealed {
+        seal_definition: SealDefinition::TxOutpoint(outpoint.into()),
+        assigned_state: data::Void,
+    }])
+}
+
+/// Constructs metadata for the burn and burn-and-replace state transitions,
+/// returning it together with the total burned amount
+fn burn_metadata(
+    asset: &Asset,
+    burn_utxos: &Vec<OutPoint>,
+    history_proof: Option<(u8, Vec<u8>)>,
+) -> Result<(Metadata, AtomicValue), ServiceErrorDomain> {
+    if burn_utxos.is_empty() {
+        Err("At least one UTXO must be burned".to_string())?
+    }
+
+    let mut burned = vec![];
+    for utxo in burn_utxos {
+        let allocations = asset
+            .allocations(utxo)
+            .filter(|allocations| !allocations.is_empty())
+            .ok_or(format!("No known assets are allocated to {}", utxo))?;
+        burned.extend(allocations.iter().map(|alloc| alloc.value().value));
+    }
+    let burned_supply = checked_sum(burned)?;
+
+    let mut metadata = type_map! {
+        FieldType::BurnedSupply => field!(U64, burned_supply),
+        FieldType::BurnUtxo => burn_utxos
+            .iter()
+            .map(|utxo| data::Revealed::TxOutPoint(*utxo))
+            .collect::<BTreeSet<_>>()
+    };
+    match history_proof {
+        Some((format, proof)) => {
+            if format == *HistoryProofFormat::ProofAbsent
+                || !HistoryProofFormat::all().contains(&format)
+            {
+                Err(format!("Unsupported history proof format {}", format))?
+            }
+            metadata.insert(*FieldType::HistoryProofFormat, field!(U8, format));
+            metadata.insert(*FieldType::HistoryProof, field!(Bytes, proof));
+        }
+        None => {
+            metadata.insert(
+                *FieldType::HistoryProofFormat,
+                field!(U8, *HistoryProofFormat::ProofAbsent),
+            );
+        }
+    }
+
+    Ok((metadata.into(), burned_supply))
+}
+
+fn burn_transition(
+    transition_type: TransitionType,
+    metadata: Metadata,
+    parent: (NodeId, u16),
+    owned_rights: BTreeMap<usize, Assignments>,
+) -> Transition {
+    let (node_id, index) = parent;
+    let mut parent = ParentOwnedRights::new();
+    parent.insert(
+        node_id,
+        type_map! { OwnedRightsType::BurnReplace => vec![index] },
+    );
+
+    Transition::with(
+        *transition_type,
+        metadata,
+        parent,
+        owned_rights,
+        bset![],
+        vec![],
+    )
+}
+
+/// Function creates a renomination state transition changing asset ticker,
+/// name and/or description. It spends renomination right assigned by the
+/// `parent` node and may re-assign the right to the `next_renomination`
+/// outpoint; otherwise the asset can't be renominated anymore.
+pub fn renominate(
+    parent: (NodeId, u16),
+    ticker: Option<String>,
+    name: Option<String>,
+    description: Option<String>,
+    next_renomination: Option<OutPoint>,
+) -> Result<Transition, ServiceErrorDomain> {
+    let mut metadata = type_map! {};
+    if let Some(ticker) = ticker {
+        metadata
+            .insert(*FieldType::Ticker, field!(String, ticker.to_uppercase()));
+    }
+    if let Some(name) = name {
+        metadata.insert(*FieldType::Name, field!(String, name));
+    }
+    if let Some(description) = description {
+        metadata.insert(*FieldType::ContractText, field!(String, description));
+    }
+    if metadata.is_empty() {
+        Err(
+            "Renomination must change at least one of the asset ticker, name \
+            or description"
+                .to_string(),
+        )?
+    }
+
+    let mut owned_rights = BTreeMap::new();
+    if let Some(outpoint) = next_renomination {
+        owned_rights
+            .insert(*OwnedRightsType::Renomination, declarative(outpoint));
+    }
+
+    let (node_id, index) = parent;
+    let mut parent = ParentOwnedRights::new();
+    parent.insert(
+        node_id,
+        type_map! { OwnedRightsType::Renomination => vec![index] },
+    );
+
+    let transition = Transition::with(
+        *TransitionType::Renomination,
+        metadata.into(),
+        parent,
+        owned_rights,
+        bset![],
+        vec![],
+    );
+
+    Ok(transition)
+}
+
+/// Function creates a state transition splitting rights assigned to the same
+/// `inputs` into separate outputs. Non-asset rights which are closed by the
+/// transition are provided in `closed` as the nodes and indexes of their
+/// assignments; assets allocated to the inputs are always closed and must be
+/// fully re-allocated with `assets`. Each of the closed renomination and
+/// epoch rights must be re-assigned to a single outpoint, unless
+/// `destroy_rights` is set; inflation allowance must be fully distributed
+/// among new `inflation` outpoints.
+pub fn split_rights(
+    asset: &Asset,
+    inputs: &[OutPoint],
+    closed: BTreeMap<OwnedRightsType, Vec<(NodeId, u16)>>,
+    inflation: Vec<OutpointCoins>,
+    renomination: Option<OutPoint>,
+    epoch: Option<OutPoint>,
+    destroy_rights: bool,
+    assets: Vec<SealCoins>,
+) -> Result<Transition, ServiceErrorDomain> {
+    let mut parent = ParentOwnedRights::new();
+    let mut owned_rights = BTreeMap::new();
+
+    for (right, assignments) in &closed {
+        for (node_id, index) in assignments {
+            parent
+                .entry(*node_id)
+                .or_insert(bmap! {})
+                .entry(**right)
+                .or_insert(vec![])
+                .push(*index);
+        }
+    }
+
+    // Inflation allowance is moved without changes
+    let closes_inflation = closed.contains_key(&OwnedRightsType::Inflation);
+    let input_allowance = checked_sum(
+        inputs
+            .iter()
+            .filter_map(|outpoint| asset.known_inflation().get(outpoint))
+            .map(|amount| amount.atomic_value()),
+    )?;
+    let inflation = inflation
+        .into_iter()
+        .map(|OutpointCoins { coins, outpoint }| {
+            let amount =
+                AccountingAmount::transmutate(*asset.fractional_bits(), coins);
+            (outpoint, amount)
+        })
+        .collect::<Vec<_>>();
+    let output_allowance =
+        checked_sum(inflation.iter().map(|(_, amount)| *amount))?;
+    let inflation = inflation
+        .into_iter()
+        .map(|(outpoint, amount)| OwnedState::Revealed {
+            seal_definition: SealDefinition::TxOutpoint(outpoint.into()),
+            assigned_state: data::Revealed::U64(amount),
+        })
+        .collect::<Vec<_>>();
+    if (closes_inflation || !inflation.is_empty())
+        && input_allowance != output_allowance
+    {
+        Err(
+            "Inflation allowance of the inputs is not equal to the allowance \
+            assigned to the outputs"
+                .to_string(),
+        )?
+    }
+    if !inflation.is_empty() {
+        owned_rights.insert(
+            *OwnedRightsType::Inflation,
+            Assignments::CustomData(inflation),
+        );
+    }
+
+    for (right, outpoint) in &[
+        (OwnedRightsType::Renomination, renomination),
+        (OwnedRightsType::Epoch, epoch),
+    ] {
+        match outpoint {
+            Some(outpoint) => {
+                if !closed.contains_key(right) {
+                    Err(format!("Inputs do not have {} right assigned", right))?
+                }
+                owned_rights.insert(**right, declarative(*outpoint));
+            }
+            // Closing the right without a new seal destroys it forever
+            None if closed.contains_key(right) && !destroy_rights => {
+                Err(format!(
+                    "No new seal is given for {} right of the inputs; it must \
+                    be destroyed explicitly",
+                    right
+                ))?
+            }
+            None => {}
+        }
+    }
+
+    // Assets allocated to the inputs must be kept
+    let mut input_allocations = Vec::<Allocation>::new();
+    for outpoint in inputs {
+        if let Some(found) = asset.allocations(outpoint) {
+            input_allocations.extend(found.clone());
+        }
+    }
+    let total_inputs =
+        checked_sum(input_allocations.iter().map(|alloc| alloc.value().value))?;
+    let allocations_ours = assets
+        .into_iter()
+        .map(|outcoins| {
+            let amount = AccountingAmount::transmutate(
+                *asset.fractional_bits(),
+                outcoins.coins,
+            );
+            (outcoins.seal_definition(), amount)
+        })
+        .collect::<Vec<_>>();
+    let total_outputs =
+        checked_sum(allocations_ours.iter().map(|(_, amount)| *amount))?;
+    if total_inputs != total_outputs {
+        Err("Input amount is not equal to output amount".to_string())?
+    }
+    if !input_allocations.is_empty() {
+        owned_rights.insert(
+            *OwnedRightsType::Assets,
+            Assignments::zero_balanced(
+                input_allocations
+                    .iter()
+                    .map(|alloc| alloc.value().clone())
+                    .collect(),
+                allocations_ours,
+                vec![],
+            ),
+        );
+        for alloc in input_allocations {
+            parent
+                .entry(*alloc.node_id())
+                .or_insert(bmap! {})
+                .entry(*OwnedRightsType::Assets)
+                .or_insert(vec![])
+                .push(*alloc.index());
+        }
+    }
+
+    if parent.is_empty() {
+        Err("Inputs do not have any known rights assigned".to_string())?
+    }
+
+    let transition = Transition::with(
+        *TransitionType::RightsSplit,
+        type_map! {}.into(),
+        parent,
+        owned_rights,
+        bset![],
+        vec![],
+    );
+
+    Ok(transition)
+}
+
+/// Computes total of the asset amounts, failing if it does not fit into the
+/// 64-bit atomic value
+pub(super) fn checked_sum(
+    amounts: impl IntoIterator<Item = AtomicValue>,
+) -> Result<AtomicValue, ServiceErrorDomain> {
+    amounts
+        .into_iter()
+        .try_fold(0u64, |acc, amount| acc.checked_add(amount))
+        .ok_or(ServiceErrorDomain::Internal(s!(
+            "Total asset amount exceeds the maximum possible supply"
+        )))
+}
@@ -13,10 +13,12 @@
 
 use core::borrow::Borrow;
 use core::convert::TryFrom;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
 use std::path::PathBuf;
 
-use lnpbp::bitcoin::OutPoint;
+use lnpbp::bitcoin::{OutPoint, Txid};
+use lnpbp::bp::blind::OutpointHash;
 use lnpbp::client_side_validation::Conceal;
 use lnpbp::lnp::zmqsocket::ZmqType;
 use lnpbp::lnp::TypedEnum;
@@ -24,15 +26,25 @@ use lnpbp::lnp::{
     session, transport, CreateUnmarshaller, PlainTranscoder, Session,
     Unmarshall, Unmarshaller,
 };
-use lnpbp::rgb::{Assignments, Consignment, ContractId, Genesis, Node};
+use lnpbp::rgb::{
+    Assignments, AtomicValue, Consignment, ContractId, Extension, Genesis,
+    Node, NodeId, OwnedState, SealDefinition, Transition,
+};
 
 use super::cache::{Cache, FileCache, FileCacheConfig};
-use super::schema::OwnedRightsType;
-use super::{processor, schema, Asset, Config, OutpointCoins};
-use crate::api::stash::MergeRequest;
+use super::schema::{OwnedRightsType, TransitionType};
+use super::{
+    processor, schema, AccountingValue, Asset, Config, ConsealCoins,
+    InvoiceRecord, LedgerEntry, LedgerOperation, OutpointCoins,
+};
+use crate::api::stash::{MergeRequest, SchemaRegistration};
 use crate::api::{
     self,
-    fungible::{AcceptApi, Issue, Request, TransferApi},
+    fungible::{
+        AcceptApi, BatchPayApi, BurnApi, BurnReplaceApi, EpochApi, HistoryApi,
+        InflateApi, InvoiceApi, Issue, PayApi, RenominateApi, Request,
+        SplitRightsApi, TransferApi,
+    },
     reply,
     stash::ConsignRequest,
     Reply,
@@ -43,8 +55,13 @@ use crate::error::{
     ServiceErrorDomain, ServiceErrorSource,
 };
 use crate::service::TryService;
+use crate::util::{BlindingCounters, BlindingSeed};
 use crate::DataFormat;
 
+/// Number of confirmations after which assets spent by the node are no longer
+/// reported as recently spent
+const RECENT_CONFIRMATIONS: u32 = 6;
+
 pub struct Runtime {
     /// Original configuration object
     config: Config,
@@ -67,6 +84,13 @@ pub struct Runtime {
     /// friendly asset information with clients
     cacher: FileCache,
 
+    /// Wallet seed used for deterministic derivation of outpoint blinding
+    /// factors
+    seed: BlindingSeed,
+
+    /// Derivation counters already used with the wallet seed
+    counters: BlindingCounters,
+
     /// Unmarshaller instance used for parsing RPC request
     unmarshaller: Unmarshaller<Request>,
 
@@ -84,15 +108,29 @@ impl Runtime {
     }
 
     pub fn init(config: Config) -> Result<Self, BootstrapError> {
-        let cacher = FileCache::new(FileCacheConfig {
+        let cache_config = FileCacheConfig {
             data_dir: PathBuf::from(&config.cache),
             data_format: config.format,
-        })
-        .map_err(|err| {
+        };
+        if config.reindex {
+            // Assets are rebuilt from the stash once the daemon is started, so
+            // the existing (possibly corrupted) data must not prevent it
+            let filename = cache_config.assets_filename();
+            if filename.exists() {
+                debug!("Removing assets file {:?} for reindexing", filename);
+                fs::remove_file(filename)?;
+            }
+        }
+        let cacher = FileCache::new(cache_config).map_err(|err| {
             error!("{}", err);
             err
         })?;
 
+        let seed = BlindingSeed::load_or_create(&config.seed)?;
+        let counters = BlindingCounters::load(
+            BlindingCounters::path_for_seed(&config.seed),
+        )?;
+
         let session_rpc = session::Raw::with_zmq_unencrypted(
             ZmqType::Rep,
             &config.rpc_endpoint,
@@ -128,6 +166,8 @@ impl Runtime {
             stash_rpc,
             stash_sub,
             cacher,
+            seed,
+            counters,
             unmarshaller: Request::create_unmarshaller(),
             reply_unmarshaller: Reply::create_unmarshaller(),
         })
@@ -147,6 +187,18 @@ impl TryService for Runtime {
             )
         })?;
 
+        if self.config.reindex {
+            info!("Rebuilding cache from the stash data");
+            let count = self.reindex().await.map_err(|err| {
+                error!("Unable to rebuild cache: {}", err);
+                RuntimeError::Internal(format!(
+                    "Unable to rebuild cache: {}",
+                    err
+                ))
+            })?;
+            info!("Cache is rebuilt for {} assets", count);
+        }
+
         loop {
             match self.run().await {
                 Ok(_) => debug!("API request processing complete"),
@@ -187,17 +239,38 @@ impl Runtime {
         Ok(match message {
             Request::Issue(issue) => self.rpc_issue(issue).await,
             Request::Transfer(transfer) => self.rpc_transfer(transfer).await,
+            Request::Pay(pay) => self.rpc_pay(pay).await,
+            Request::BatchPay(batch) => self.rpc_batch_pay(batch).await,
+            Request::Invoice(invoice) => self.rpc_invoice(invoice).await,
+            Request::ListInvoices(contract_id) => {
+                self.rpc_list_invoices(*contract_id).await
+            }
+            Request::History(history) => self.rpc_history(history).await,
+            Request::Balance(contract_id) => {
+                self.rpc_balance(*contract_id).await
+            }
             Request::Validate(consignment) => {
                 self.rpc_validate(consignment).await
             }
             Request::Accept(accept) => self.rpc_accept(accept).await,
             Request::Forget(outpoint) => self.rpc_forget(outpoint).await,
+            Request::Inflate(inflate) => self.rpc_inflate(inflate).await,
+            Request::Epoch(epoch) => self.rpc_epoch(epoch).await,
+            Request::Burn(burn) => self.rpc_burn(burn).await,
+            Request::BurnReplace(burn_replace) => {
+                self.rpc_burn_replace(burn_replace).await
+            }
+            Request::Renominate(renominate) => {
+                self.rpc_renominate(renominate).await
+            }
+            Request::SplitRights(split) => self.rpc_split_rights(split).await,
             Request::ImportAsset(genesis) => {
                 self.rpc_import_asset(genesis).await
             }
             Request::ExportAsset(asset_id) => {
                 self.rpc_export_asset(asset_id).await
             }
+            Request::Reindex() => self.rpc_reindex().await,
             Request::Sync(data_format) => self.rpc_sync(*data_format).await,
             Request::Assets(outpoint) => self.rpc_assets(*outpoint).await,
             Request::Allocations(contract_id) => {
@@ -244,7 +317,16 @@ impl Runtime {
             issue.epoch,
         )?;
 
+        let entry = LedgerEntry::with(
+            *asset.id(),
+            genesis.node_id(),
+            LedgerOperation::Issue,
+            None,
+            accounting_value(&asset, balance(&asset)),
+            None,
+        );
         self.import_asset(asset, genesis).await?;
+        self.cacher.add_ledger_entry(entry)?;
 
         // TODO: Send push request to client informing about cache update
 
@@ -257,42 +339,760 @@ impl Runtime {
     ) -> Result<Reply, ServiceErrorDomain> {
         debug!("Got TRANSFER {}", transfer);
 
-        // TODO: Check inputs that they really exist and have sufficient amount
-        //       of asset for the transfer operation
-
         trace!("Looking for asset information");
-        let mut asset = self.cacher.asset(transfer.contract_id)?.clone();
+        let mut asset = self.cacher.asset(transfer.contract_id)?;
         debug!("Transferring asset {}", asset);
 
+        let addressed = transfer
+            .theirs_addressed
+            .iter()
+            .map(|theirs| (theirs.seal_definition(), theirs.coins))
+            .collect::<Vec<_>>();
+
         trace!("Preparing state transition");
         let transition = processor::transfer(
             &mut asset,
             transfer.inputs.clone(),
             transfer.ours.clone(),
             transfer.theirs.clone(),
+            addressed.clone(),
+            transfer.change.map(|change| change.seal_definition()),
         )?;
         debug!("State transition: {}", transition);
 
+        let blanks = self.blank_transitions(
+            transfer.contract_id,
+            &transfer.inputs,
+            transfer.change.map(|change| change.seal_definition()),
+        )?;
+
         trace!("Requesting consignment from stash daemon");
         let reply = self
             .consign(ConsignRequest {
                 contract_id: transfer.contract_id,
                 inputs: transfer.inputs.clone(),
-                transition,
-                // TODO: Collect blank state transitions and pass it here
+                transition: transition.clone(),
+                other_transition_ids: blank_transition_ids(&blanks),
+                other_transitions: blank_transition_list(&blanks),
+                outpoints: exposed_seals(&transfer.theirs, &addressed),
+                psbt: transfer.psbt.clone(),
+                known_history: transfer.known_history.clone(),
+            })
+            .await?;
+
+        if let Reply::Transfer(ref transfer_reply) = reply {
+            let witness_txid = transfer_reply.psbt.global.unsigned_tx.txid();
+            asset.add_transfer(
+                &transfer.inputs,
+                &transition,
+                witness_txid,
+                &theirs_seals(&addressed),
+            );
+            debug!(
+                "Updated asset allocations: {:?}",
+                asset.known_allocations()
+            );
+            self.add_sent_entries(
+                &asset,
+                &transition,
+                witness_txid,
+                &transfer.theirs,
+                &addressed,
+            )?;
+            self.cacher.add_asset(asset)?;
+            self.add_blank_transitions(blanks, witness_txid)?;
+        }
+
+        Ok(reply)
+    }
+
+    async fn rpc_pay(
+        &mut self,
+        pay: &PayApi,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got PAY {}", pay);
+
+        trace!("Looking for asset information");
+        let mut asset = self.cacher.asset(pay.contract_id)?;
+        debug!("Paying with asset {}", asset);
+
+        let addressed = pay
+            .theirs_addressed
+            .iter()
+            .map(|theirs| (theirs.seal_definition(), theirs.coins))
+            .collect::<Vec<_>>();
+        let amount = payment_amount(&asset, &pay.theirs, &addressed)?;
+
+        trace!("Selecting inputs using {} strategy", pay.strategy);
+        let inputs = pay
+            .strategy
+            .select(asset.known_allocations(), amount)
+            .ok_or(format!(
+                "Known allocations of asset {} are not sufficient to pay {}",
+                pay.contract_id, amount
+            ))?;
+        let change = inputs_amount(&asset, &inputs)? - amount;
+        debug!("Selected inputs {:?} with change {}", inputs, change);
+
+        trace!("Preparing state transition");
+        let transition = processor::transfer(
+            &mut asset,
+            inputs.clone(),
+            vec![],
+            pay.theirs.clone(),
+            addressed.clone(),
+            pay.change.map(|change| change.seal_definition()),
+        )?;
+        debug!("State transition: {}", transition);
+
+        let blanks = self.blank_transitions(
+            pay.contract_id,
+            &inputs,
+            pay.change.map(|change| change.seal_definition()),
+        )?;
+
+        trace!("Requesting consignment from stash daemon");
+        let reply = self
+            .consign(ConsignRequest {
+                contract_id: pay.contract_id,
+                inputs: inputs.clone(),
+                transition: transition.clone(),
+                other_transition_ids: blank_transition_ids(&blanks),
+                other_transitions: blank_transition_list(&blanks),
+                outpoints: exposed_seals(&pay.theirs, &addressed),
+                psbt: pay.psbt.clone(),
+                known_history: pay.known_history.clone(),
+            })
+            .await?;
+
+        Ok(match reply {
+            Reply::Transfer(transfer) => {
+                let witness_txid = transfer.psbt.global.unsigned_tx.txid();
+                asset.add_transfer(
+                    &inputs,
+                    &transition,
+                    witness_txid,
+                    &theirs_seals(&addressed),
+                );
+                self.add_sent_entries(
+                    &asset,
+                    &transition,
+                    witness_txid,
+                    &pay.theirs,
+                    &addressed,
+                )?;
+                self.cacher.add_asset(asset)?;
+                self.add_blank_transitions(blanks, witness_txid)?;
+                Reply::Payment(reply::Payment {
+                    inputs,
+                    change,
+                    transfer,
+                })
+            }
+            reply => reply,
+        })
+    }
+
+    async fn rpc_batch_pay(
+        &mut self,
+        batch: &BatchPayApi,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got BATCH_PAY {}", batch);
+
+        trace!("Looking for asset information");
+        let mut asset = self.cacher.asset(batch.contract_id)?;
+        debug!("Paying with asset {}", asset);
+
+        let addressed = batch
+            .theirs_addressed
+            .iter()
+            .map(|theirs| (theirs.seal_definition(), theirs.coins))
+            .collect::<Vec<_>>();
+        let amount = payment_amount(&asset, &batch.theirs, &addressed)?;
+
+        let inputs = if batch.inputs.is_empty() {
+            trace!("Selecting inputs using {} strategy", batch.strategy);
+            batch
+                .strategy
+                .select(asset.known_allocations(), amount)
+                .ok_or(format!(
+                "Known allocations of asset {} are not sufficient to pay {}",
+                batch.contract_id, amount
+            ))?
+        } else {
+            batch.inputs.clone()
+        };
+        // Explicitly given inputs may not cover the payment
+        let available = inputs_amount(&asset, &inputs)?;
+        if available < amount {
+            Err(format!(
+                "Inputs holding {} of asset {} are not sufficient to pay {}",
+                available, batch.contract_id, amount
+            ))?
+        }
+        let change = available - amount;
+        debug!("Using inputs {:?} with change {}", inputs, change);
+
+        trace!("Preparing state transition");
+        let transition = processor::transfer(
+            &mut asset,
+            inputs.clone(),
+            vec![],
+            batch.theirs.clone(),
+            addressed.clone(),
+            batch.change.map(|change| change.seal_definition()),
+        )?;
+        debug!("State transition: {}", transition);
+
+        let blanks = self.blank_transitions(
+            batch.contract_id,
+            &inputs,
+            batch.change.map(|change| change.seal_definition()),
+        )?;
+
+        trace!("Requesting consignment from stash daemon");
+        let endpoints = exposed_seals(&batch.theirs, &addressed);
+        let reply = self
+            .consign(ConsignRequest {
+                contract_id: batch.contract_id,
+                inputs: inputs.clone(),
+                transition: transition.clone(),
+                other_transition_ids: blank_transition_ids(&blanks),
+                other_transitions: blank_transition_list(&blanks),
+                outpoints: endpoints.clone(),
+                psbt: batch.psbt.clone(),
+                known_history: batch.known_history.clone(),
+            })
+            .await?;
+
+        Ok(match reply {
+            Reply::Transfer(transfer) => {
+                let witness_txid = transfer.psbt.global.unsigned_tx.txid();
+                asset.add_transfer(
+                    &inputs,
+                    &transition,
+                    witness_txid,
+                    &theirs_seals(&addressed),
+                );
+                self.add_sent_entries(
+                    &asset,
+                    &transition,
+                    witness_txid,
+                    &batch.theirs,
+                    &addressed,
+                )?;
+                self.cacher.add_asset(asset)?;
+                self.add_blank_transitions(blanks, witness_txid)?;
+
+                // Each of the receivers gets own consignment, which extends
+                // and reveals only the receiver's own endpoint
+                let consignments = endpoints
+                    .into_iter()
+                    .map(|endpoint| {
+                        (
+                            endpoint,
+                            receiver_consignment(
+                                &transfer.consignment,
+                                &transition,
+                                endpoint,
+                            ),
+                        )
+                    })
+                    .collect();
+                Reply::BatchPayment(reply::BatchPayment {
+                    inputs,
+                    change,
+                    consignments,
+                    psbt: transfer.psbt,
+                })
+            }
+            reply => reply,
+        })
+    }
+
+    async fn rpc_inflate(
+        &mut self,
+        inflate: &InflateApi,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got INFLATE {}", inflate);
+
+        trace!("Looking for asset information");
+        let mut asset = self.cacher.asset(inflate.contract_id)?;
+        debug!("Inflating asset {}", asset);
+
+        trace!("Looking for the node assigning inflation right");
+        let parent = self
+            .inflation_parent(&asset, inflate.inflation, inflate.parent)
+            .await?;
+
+        trace!("Preparing state transition");
+        let transition = processor::inflate(
+            &asset,
+            inflate.inflation,
+            parent,
+            inflate.ours.clone(),
+            inflate.theirs.clone(),
+            inflate.remainder,
+        )?;
+        debug!("State transition: {}", transition);
+
+        trace!("Requesting consignment from stash daemon");
+        let reply = self
+            .consign(ConsignRequest {
+                contract_id: inflate.contract_id,
+                inputs: vec![inflate.inflation],
+                transition: transition.clone(),
                 other_transition_ids: bmap![],
-                outpoints: transfer
+                other_transitions: vec![],
+                outpoints: inflate
                     .theirs
                     .iter()
                     .map(|o| (o.seal_confidential))
                     .collect(),
-                psbt: transfer.psbt.clone(),
+                psbt: inflate.psbt.clone(),
+                known_history: None,
             })
             .await?;
 
+        if let Reply::Transfer(ref transfer) = reply {
+            let witness_txid = transfer.psbt.global.unsigned_tx.txid();
+            let before = balance(&asset);
+            let supply = asset.add_issue(
+                inflate.inflation,
+                &transition,
+                witness_txid,
+            )?;
+            debug!("Updated asset supply: {}", supply);
+            let entry = LedgerEntry::with(
+                *asset.id(),
+                transition.node_id(),
+                LedgerOperation::Issue,
+                Some(witness_txid),
+                accounting_value(
+                    &asset,
+                    balance(&asset).saturating_sub(before),
+                ),
+                None,
+            );
+            self.cacher.add_asset(asset)?;
+            self.cacher.add_ledger_entry(entry)?;
+        }
+
         Ok(reply)
     }
 
+    async fn rpc_epoch(
+        &mut self,
+        epoch: &EpochApi,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got EPOCH {}", epoch);
+
+        let asset = self.cacher.asset(epoch.contract_id)?;
+        let parent = self
+            .right_parent(
+                &asset,
+                OwnedRightsType::Epoch,
+                epoch.epoch,
+                epoch.parent,
+            )
+            .await?;
+
+        trace!("Preparing state transition");
+        let transition =
+            processor::epoch(parent, epoch.next_epoch, epoch.burn_replace)?;
+        debug!("State transition: {}", transition);
+
+        trace!("Requesting consignment from stash daemon");
+        self.consign(ConsignRequest {
+            contract_id: epoch.contract_id,
+            inputs: vec![epoch.epoch],
+            transition,
+            other_transition_ids: bmap![],
+            other_transitions: vec![],
+            outpoints: vec![],
+            psbt: epoch.psbt.clone(),
+            known_history: None,
+        })
+        .await
+    }
+
+    async fn rpc_burn(
+        &mut self,
+        burn: &BurnApi,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got BURN {}", burn);
+
+        let asset = self.cacher.asset(burn.contract_id)?;
+        let parent = self
+            .right_parent(
+                &asset,
+                OwnedRightsType::BurnReplace,
+                burn.burn_right,
+                Some(burn.parent),
+            )
+            .await?;
+
+        trace!("Preparing state transition");
+        let transition = processor::burn(
+            &asset,
+            parent,
+            burn.burn_utxos.clone(),
+            burn.next_burn,
+            burn.history_proof.clone(),
+        )?;
+        debug!("State transition: {}", transition);
+
+        self.consign_burn(asset, burn, transition, vec![]).await
+    }
+
+    async fn rpc_burn_replace(
+        &mut self,
+        burn_replace: &BurnReplaceApi,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got BURN_REPLACE {}", burn_replace);
+
+        let burn = &burn_replace.burn;
+        let asset = self.cacher.asset(burn.contract_id)?;
+        let parent = self
+            .right_parent(
+                &asset,
+                OwnedRightsType::BurnReplace,
+                burn.burn_right,
+                Some(burn.parent),
+            )
+            .await?;
+
+        trace!("Preparing state transition");
+        let transition = processor::burn_replace(
+            &asset,
+            parent,
+            burn.burn_utxos.clone(),
+            burn.next_burn,
+            burn.history_proof.clone(),
+            burn_replace.ours.clone(),
+            burn_replace.theirs.clone(),
+        )?;
+        debug!("State transition: {}", transition);
+
+        self.consign_burn(
+            asset,
+            burn,
+            transition,
+            burn_replace
+                .theirs
+                .iter()
+                .map(|o| (o.seal_confidential))
+                .collect(),
+        )
+        .await
+    }
+
+    async fn rpc_renominate(
+        &mut self,
+        renominate: &RenominateApi,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got RENOMINATE {}", renominate);
+
+        let mut asset = self.cacher.asset(renominate.contract_id)?;
+
+        trace!("Looking for the node assigning renomination right");
+        let parent = self
+            .renomination_parent(
+                &asset,
+                renominate.renomination,
+                renominate.parent,
+            )
+            .await?;
+
+        trace!("Preparing state transition");
+        let transition = processor::renominate(
+            parent,
+            renominate.ticker.clone(),
+            renominate.name.clone(),
+            renominate.description.clone(),
+            renominate.next_renomination,
+        )?;
+        debug!("State transition: {}", transition);
+
+        trace!("Requesting consignment from stash daemon");
+        let reply = self
+            .consign(ConsignRequest {
+                contract_id: renominate.contract_id,
+                inputs: vec![renominate.renomination],
+                transition: transition.clone(),
+                other_transition_ids: bmap![],
+                other_transitions: vec![],
+                outpoints: vec![],
+                psbt: renominate.psbt.clone(),
+                known_history: None,
+            })
+            .await?;
+
+        if let Reply::Transfer(_) = reply {
+            asset.add_nomination(&transition);
+            debug!("Asset is renominated to {}", asset.ticker());
+            self.cacher.add_asset(asset)?;
+        }
+
+        Ok(reply)
+    }
+
+    async fn rpc_split_rights(
+        &mut self,
+        split: &SplitRightsApi,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got SPLIT_RIGHTS {}", split);
+
+        let mut asset = self.cacher.asset(split.contract_id)?;
+
+        trace!("Looking for the nodes assigning rights to the inputs");
+        let mut closed = BTreeMap::<OwnedRightsType, Vec<(NodeId, u16)>>::new();
+        for input in &split.inputs {
+            if asset.known_inflation().contains_key(input) {
+                let parent =
+                    self.inflation_parent(&asset, *input, None).await?;
+                closed
+                    .entry(OwnedRightsType::Inflation)
+                    .or_insert(vec![])
+                    .push(parent);
+            }
+            if let Ok(parent) =
+                self.renomination_parent(&asset, *input, None).await
+            {
+                closed
+                    .entry(OwnedRightsType::Renomination)
+                    .or_insert(vec![])
+                    .push(parent);
+            }
+            if let Ok(parent) = self
+                .right_parent(&asset, OwnedRightsType::Epoch, *input, None)
+                .await
+            {
+                closed
+                    .entry(OwnedRightsType::Epoch)
+                    .or_insert(vec![])
+                    .push(parent);
+            }
+        }
+
+        trace!("Preparing state transition");
+        let transition = processor::split_rights(
+            &asset,
+            &split.inputs,
+            closed,
+            split.inflation.clone(),
+            split.renomination,
+            split.epoch,
+            split.destroy_rights,
+            split.assets.clone(),
+        )?;
+        debug!("State transition: {}", transition);
+
+        trace!("Requesting consignment from stash daemon");
+        let reply = self
+            .consign(ConsignRequest {
+                contract_id: split.contract_id,
+                inputs: split.inputs.clone(),
+                transition: transition.clone(),
+                other_transition_ids: bmap![],
+                other_transitions: vec![],
+                outpoints: vec![],
+                psbt: split.psbt.clone(),
+                known_history: None,
+            })
+            .await?;
+
+        if let Reply::Transfer(ref transfer) = reply {
+            let witness_txid = transfer.psbt.global.unsigned_tx.txid();
+            asset.add_rights_split(&split.inputs, &transition, witness_txid)?;
+            debug!("Asset rights are split");
+            self.cacher.add_asset(asset)?;
+        }
+
+        Ok(reply)
+    }
+
+    async fn rpc_invoice(
+        &mut self,
+        invoice: &InvoiceApi,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got INVOICE {}", invoice);
+
+        if invoice.outpoints.is_empty() {
+            Err(ServiceErrorDomain::Internal(s!(
+                "Invoice must have at least one receiving outpoint"
+            )))?
+        }
+
+        let mut records = vec![];
+        for outpoint in &invoice.outpoints {
+            // Each new invoice for the same outpoint gets the next derivation
+            // counter, so the blinded outpoints are not linkable. Counters are
+            // persisted next to the seed; cached invoices cover the ones
+            // created before the counters file was introduced.
+            let cached = self
+                .cacher
+                .invoices()?
+                .into_iter()
+                .filter(|known| known.outpoint() == outpoint)
+                .count() as u32;
+            let counter = self.counters.next(*outpoint).max(cached);
+            self.counters.mark_used(*outpoint, counter);
+            self.counters.write_file(BlindingCounters::path_for_seed(
+                &self.config.seed,
+            ))?;
+            let record = InvoiceRecord::with(
+                invoice.contract_id,
+                self.seed.outpoint_reveal(*outpoint, counter),
+                invoice.amount,
+                invoice.expiry,
+            );
+            self.cacher.add_invoice(record.clone())?;
+            records.push(record);
+        }
+
+        Ok(Reply::Invoices(records))
+    }
+
+    async fn rpc_list_invoices(
+        &mut self,
+        contract_id: Option<ContractId>,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got LIST_INVOICES");
+        let records = self
+            .cacher
+            .invoices()?
+            .into_iter()
+            .filter(|invoice| {
+                contract_id
+                    .map(|id| *invoice.contract_id() == id)
+                    .unwrap_or(true)
+            })
+            .collect();
+        Ok(Reply::Invoices(records))
+    }
+
+    async fn rpc_history(
+        &mut self,
+        history: &HistoryApi,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got HISTORY {}", history);
+
+        let entries = self
+            .cacher
+            .history(history.contract_id)?
+            .into_iter()
+            .rev()
+            .skip(history.offset as usize)
+            .take(history.limit as usize)
+            .collect();
+
+        Ok(Reply::History(entries))
+    }
+
+    async fn rpc_balance(
+        &mut self,
+        contract_id: Option<ContractId>,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got BALANCE");
+
+        let assets = self
+            .cacher
+            .assets()?
+            .into_iter()
+            .filter(|asset| {
+                contract_id.map(|id| *asset.id() == id).unwrap_or(true)
+            })
+            .collect::<Vec<_>>();
+        let mut sent =
+            BTreeMap::<ContractId, Vec<(Txid, NodeId, AtomicValue)>>::new();
+        for asset in &assets {
+            let entries = self
+                .cacher
+                .history(*asset.id())?
+                .into_iter()
+                .filter(|entry| *entry.operation() == LedgerOperation::Sent)
+                .filter_map(|entry| {
+                    entry.witness_txid().map(|txid| {
+                        (
+                            txid,
+                            *entry.node_id(),
+                            AccountingAmount::transmutate(
+                                *asset.fractional_bits(),
+                                -*entry.delta(),
+                            ),
+                        )
+                    })
+                })
+                .collect();
+            sent.insert(*asset.id(), entries);
+        }
+
+        let txids = assets
+            .iter()
+            .flat_map(|asset| asset.known_allocations().keys())
+            .map(|outpoint| outpoint.txid)
+            .chain(sent.values().flatten().map(|(txid, ..)| *txid))
+            .collect::<BTreeSet<_>>();
+        trace!("Requesting confirmations for {} transactions", txids.len());
+        let confirmations =
+            self.confirmations(txids.into_iter().collect()).await?;
+        let confirmations_of =
+            |txid: &Txid| confirmations.get(txid).copied().unwrap_or_default();
+
+        let mut balances = BTreeMap::new();
+        for asset in &assets {
+            // Allocations assigned by the witness transactions created by the
+            // node are the change of the outgoing transfers, not the incoming
+            // ones; until confirmation they are accounted as a part of the
+            // reserved inputs
+            let originated = sent[asset.id()]
+                .iter()
+                .map(|(txid, ..)| *txid)
+                .collect::<BTreeSet<_>>();
+            let (mut confirmed, mut unconfirmed) = (0u64, 0u64);
+            for (outpoint, allocations) in asset.known_allocations() {
+                let value = allocations.iter().fold(0u64, |acc, alloc| {
+                    acc.saturating_add(alloc.value().value)
+                });
+                if confirmations_of(&outpoint.txid) > 0 {
+                    confirmed = confirmed.saturating_add(value);
+                } else if !originated.contains(&outpoint.txid) {
+                    unconfirmed = unconfirmed.saturating_add(value);
+                }
+            }
+            let mut pending = BTreeSet::new();
+            let mut spent = 0u64;
+            for (txid, node_id, value) in &sent[asset.id()] {
+                match confirmations_of(txid) {
+                    0 => {
+                        pending.insert(*node_id);
+                    }
+                    n if n < RECENT_CONFIRMATIONS => {
+                        spent = spent.saturating_add(*value)
+                    }
+                    _ => {}
+                }
+            }
+            let reserved = self
+                .closed_amount(asset, pending.into_iter().collect())
+                .await?;
+            let amount =
+                |value| AccountingAmount::from_asset_atomic_value(asset, value);
+            balances.insert(
+                *asset.id(),
+                reply::Balance {
+                    confirmed: amount(confirmed),
+                    unconfirmed_incoming: amount(unconfirmed),
+                    reserved_outgoing: amount(reserved),
+                    recently_spent: amount(spent),
+                },
+            );
+        }
+
+        Ok(Reply::Balances(balances))
+    }
+
     async fn rpc_validate(
         &mut self,
         consignment: &Consignment,
@@ -318,6 +1118,13 @@ impl Runtime {
         Ok(self.forget(outpoint.clone()).await?)
     }
 
+    async fn rpc_reindex(&mut self) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got REINDEX");
+        let count = self.reindex().await?;
+        info!("Cache is rebuilt for {} assets", count);
+        Ok(Reply::Success)
+    }
+
     async fn rpc_sync(
         &mut self,
         data_format: DataFormat,
@@ -364,9 +1171,151 @@ impl Runtime {
         Ok(Reply::Genesis(genesis))
     }
 
+    /// Prepares blank state transitions for all other known assets allocated
+    /// to the spent `inputs`, moving them to the `change` seal, so they are
+    /// not lost when the inputs are spent by a transfer of `contract_id`
+    fn blank_transitions(
+        &self,
+        contract_id: ContractId,
+        inputs: &[OutPoint],
+        change: Option<SealDefinition>,
+    ) -> Result<Vec<(Asset, Vec<OutPoint>, Transition)>, ServiceErrorDomain>
+    {
+        let mut blanks = vec![];
+        for mut asset in self.cacher.assets()? {
+            if *asset.id() == contract_id {
+                continue;
+            }
+            let spent = inputs
+                .iter()
+                .filter(|outpoint| {
+                    asset
+                        .allocations(outpoint)
+                        .map(|allocations| !allocations.is_empty())
+                        .unwrap_or(false)
+                })
+                .copied()
+                .collect::<Vec<_>>();
+            if spent.is_empty() {
+                continue;
+            }
+            let change = change.clone().ok_or(format!(
+                "Inputs also hold allocations of asset {}; change output is \
+                required to keep them",
+                asset.id()
+            ))?;
+            let transition =
+                processor::blank(&mut asset, spent.clone(), change)?;
+            debug!(
+                "Blank state transition for asset {}: {}",
+                asset.id(),
+                transition
+            );
+            blanks.push((asset, spent, transition));
+        }
+        Ok(blanks)
+    }
+
+    /// Updates cached allocations of the assets moved by blank state
+    /// transitions
+    fn add_blank_transitions(
+        &mut self,
+        blanks: Vec<(Asset, Vec<OutPoint>, Transition)>,
+        witness_txid: Txid,
+    ) -> Result<(), ServiceErrorDomain> {
+        for (mut asset, inputs, transition) in blanks {
+            asset.add_transfer(&inputs, &transition, witness_txid, &[]);
+            self.cacher.add_asset(asset)?;
+        }
+        Ok(())
+    }
+
+    /// Records ledger entries for each of the receivers of an outgoing
+    /// transfer
+    fn add_sent_entries(
+        &mut self,
+        asset: &Asset,
+        transition: &Transition,
+        witness_txid: Txid,
+        theirs: &[ConsealCoins],
+        theirs_addressed: &[(SealDefinition, AccountingValue)],
+    ) -> Result<(), ServiceErrorDomain> {
+        let receivers = theirs
+            .iter()
+            .map(|theirs| (theirs.seal_confidential, theirs.coins))
+            .chain(theirs_addressed.iter().map(|(seal_definition, coins)| {
+                (seal_definition.conceal(), *coins)
+            }));
+        for (seal, coins) in receivers {
+            self.cacher.add_ledger_entry(LedgerEntry::with(
+                *asset.id(),
+                transition.node_id(),
+                LedgerOperation::Sent,
+                Some(witness_txid),
+                -coins,
+                Some(seal),
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// Rebuilds cached data of all RGB-20 assets known to the stash by
+    /// replaying their genesis and state transitions. Invoices and the ledger
+    /// are not affected. Returns number of the rebuilt assets.
+    async fn reindex(&mut self) -> Result<usize, ServiceErrorDomain> {
+        let contract_ids = match self
+            .stash_req_rep(api::stash::Request::ListGeneses())
+            .await?
+        {
+            Reply::ContractIds(contract_ids) => contract_ids,
+            _ => Err(ServiceErrorDomain::Api(ApiErrorType::UnexpectedReply))?,
+        };
+
+        let schema_id = schema::schema().schema_id();
+        let mut rebuilt = BTreeSet::new();
+        for contract_id in contract_ids {
+            let consignment = match self
+                .stash_req_rep(api::stash::Request::ReadContract(contract_id))
+                .await?
+            {
+                Reply::Consignment(consignment) => consignment,
+                _ => {
+                    Err(ServiceErrorDomain::Api(ApiErrorType::UnexpectedReply))?
+                }
+            };
+            if consignment.genesis.schema_id() != schema_id {
+                continue;
+            }
+            let asset = replay(&consignment)?;
+            debug!("Rebuilt asset {}", asset);
+            self.cacher.add_asset(asset)?;
+            rebuilt.insert(contract_id);
+        }
+
+        // Assets which are not known to the stash can't be rebuilt
+        let stale = self
+            .cacher
+            .assets()?
+            .into_iter()
+            .map(|asset| *asset.id())
+            .filter(|id| !rebuilt.contains(id))
+            .collect::<Vec<_>>();
+        for contract_id in stale {
+            warn!("Asset {} is not known to the stash; removing", contract_id);
+            self.cacher.remove_asset(contract_id)?;
+        }
+
+        Ok(rebuilt.len())
+    }
+
     async fn register_schema(&mut self) -> Result<(), ServiceErrorDomain> {
         match self
-            .stash_req_rep(api::stash::Request::AddSchema(schema::schema()))
+            .stash_req_rep(api::stash::Request::RegisterSchema(
+                SchemaRegistration {
+                    schema: schema::schema(),
+                    type_names: schema::type_names(),
+                },
+            ))
             .await?
         {
             Reply::Success => Ok(()),
@@ -374,6 +1323,204 @@ impl Runtime {
         }
     }
 
+    /// Finds node and index of the inflation right assignment for the given
+    /// outpoint. If the node is not provided, it is looked up among the known
+    /// asset issues
+    async fn inflation_parent(
+        &mut self,
+        asset: &Asset,
+        outpoint: OutPoint,
+        parent: Option<NodeId>,
+    ) -> Result<(NodeId, u16), ServiceErrorDomain> {
+        if parent.is_some() {
+            return self
+                .right_parent(
+                    asset,
+                    OwnedRightsType::Inflation,
+                    outpoint,
+                    parent,
+                )
+                .await;
+        }
+        for issue in asset.known_issues() {
+            let parent = if issue.is_primary() {
+                None
+            } else {
+                Some(*issue.id())
+            };
+            if let Ok(found) = self
+                .right_parent(
+                    asset,
+                    OwnedRightsType::Inflation,
+                    outpoint,
+                    parent,
+                )
+                .await
+            {
+                return Ok(found);
+            }
+        }
+        Err(format!(
+            "No known inflation right is assigned to {}",
+            outpoint
+        ))?
+    }
+
+    /// Finds node and index of the renomination right assignment for the
+    /// given outpoint. If the node is not provided, it is looked up among the
+    /// nodes defining known asset nominations
+    async fn renomination_parent(
+        &mut self,
+        asset: &Asset,
+        outpoint: OutPoint,
+        parent: Option<NodeId>,
+    ) -> Result<(NodeId, u16), ServiceErrorDomain> {
+        if parent.is_some() {
+            return self
+                .right_parent(
+                    asset,
+                    OwnedRightsType::Renomination,
+                    outpoint,
+                    parent,
+                )
+                .await;
+        }
+        for (no, nomination) in asset.known_nominations().iter().enumerate() {
+            // The first nomination is always defined by genesis
+            let parent = if no == 0 {
+                None
+            } else {
+                Some(*nomination.id())
+            };
+            if let Ok(found) = self
+                .right_parent(
+                    asset,
+                    OwnedRightsType::Renomination,
+                    outpoint,
+                    parent,
+                )
+                .await
+            {
+                return Ok(found);
+            }
+        }
+        Err(format!(
+            "No known renomination right is assigned to {}",
+            outpoint
+        ))?
+    }
+
+    /// Finds index of the right assignment for the given outpoint within the
+    /// `parent` state transition or, if no parent is given, asset genesis
+    async fn right_parent(
+        &mut self,
+        asset: &Asset,
+        right: OwnedRightsType,
+        outpoint: OutPoint,
+        parent: Option<NodeId>,
+    ) -> Result<(NodeId, u16), ServiceErrorDomain> {
+        let found = match parent {
+            None => {
+                let genesis = self.export_asset(*asset.id()).await?;
+                right_index(&genesis, right, outpoint)
+                    .map(|index| (genesis.node_id(), index))
+            }
+            Some(node_id) => match self
+                .stash_req_rep(api::stash::Request::ReadTransitions(vec![
+                    node_id,
+                ]))
+                .await?
+            {
+                Reply::Transitions(transitions) => transitions
+                    .first()
+                    .and_then(|transition| {
+                        right_index(transition, right, outpoint)
+                    })
+                    .map(|index| (node_id, index)),
+                _ => {
+                    Err(ServiceErrorDomain::Api(ApiErrorType::UnexpectedReply))?
+                }
+            },
+        };
+        found.ok_or(
+            format!("No known {} right is assigned to {}", right, outpoint)
+                .into(),
+        )
+    }
+
+    /// Sums amounts of the asset allocations closed by the given state
+    /// transitions, which are read from the stash together with their parents
+    async fn closed_amount(
+        &mut self,
+        asset: &Asset,
+        node_ids: Vec<NodeId>,
+    ) -> Result<AtomicValue, ServiceErrorDomain> {
+        if node_ids.is_empty() {
+            return Ok(0);
+        }
+        let transitions = match self
+            .stash_req_rep(api::stash::Request::ReadTransitions(node_ids))
+            .await?
+        {
+            Reply::Transitions(transitions) => transitions,
+            _ => Err(ServiceErrorDomain::Api(ApiErrorType::UnexpectedReply))?,
+        };
+
+        let mut closed = BTreeMap::<NodeId, BTreeSet<u16>>::new();
+        for transition in &transitions {
+            for (parent_id, rights) in transition.parent_owned_rights() {
+                if let Some(indexes) = rights.get(&*OwnedRightsType::Assets) {
+                    closed.entry(*parent_id).or_default().extend(indexes);
+                }
+            }
+        }
+
+        let genesis = self.export_asset(*asset.id()).await?;
+        let mut amount = 0u64;
+        for (parent_id, indexes) in closed {
+            let amounts = if parent_id == genesis.node_id() {
+                allocated_amounts(&genesis)
+            } else {
+                self.node_allocated_amounts(parent_id).await?
+            };
+            for index in indexes {
+                let value = amounts
+                    .get(index as usize)
+                    .copied()
+                    .flatten()
+                    .unwrap_or_default();
+                amount = amount.saturating_add(value);
+            }
+        }
+        Ok(amount)
+    }
+
+    /// Reads state transition or extension from the stash and returns amounts
+    /// of the asset allocations assigned by it
+    async fn node_allocated_amounts(
+        &mut self,
+        node_id: NodeId,
+    ) -> Result<Vec<Option<AtomicValue>>, ServiceErrorDomain> {
+        if let Ok(Reply::Transitions(transitions)) = self
+            .stash_req_rep(api::stash::Request::ReadTransitions(vec![node_id]))
+            .await
+        {
+            if let Some(transition) = transitions.first() {
+                return Ok(allocated_amounts(transition));
+            }
+        }
+        match self
+            .stash_req_rep(api::stash::Request::ReadExtensions(vec![node_id]))
+            .await?
+        {
+            Reply::Extensions(extensions) => Ok(extensions
+                .first()
+                .map(|extension| allocated_amounts(extension))
+                .unwrap_or_default()),
+            _ => Err(ServiceErrorDomain::Api(ApiErrorType::UnexpectedReply)),
+        }
+    }
+
     async fn import_asset(
         &mut self,
         asset: Asset,
@@ -401,6 +1548,19 @@ impl Runtime {
         }
     }
 
+    async fn confirmations(
+        &mut self,
+        txids: Vec<Txid>,
+    ) -> Result<BTreeMap<Txid, u32>, ServiceErrorDomain> {
+        match self
+            .stash_req_rep(api::stash::Request::Confirmations(txids))
+            .await?
+        {
+            Reply::Confirmations(confirmations) => Ok(confirmations),
+            _ => Err(ServiceErrorDomain::Api(ApiErrorType::UnexpectedReply)),
+        }
+    }
+
     async fn consign(
         &mut self,
         consign_req: ConsignRequest,
@@ -415,6 +1575,42 @@ impl Runtime {
         }
     }
 
+    /// Consigns burn or burn-and-replace state transition and updates asset
+    /// supply in the cache
+    async fn consign_burn(
+        &mut self,
+        mut asset: Asset,
+        burn: &BurnApi,
+        transition: Transition,
+        outpoints: Vec<OutpointHash>,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        trace!("Requesting consignment from stash daemon");
+        let mut inputs = burn.burn_utxos.clone();
+        inputs.push(burn.burn_right);
+        let reply = self
+            .consign(ConsignRequest {
+                contract_id: burn.contract_id,
+                inputs,
+                transition: transition.clone(),
+                other_transition_ids: bmap![],
+                other_transitions: vec![],
+                outpoints,
+                psbt: burn.psbt.clone(),
+                known_history: None,
+            })
+            .await?;
+
+        if let Reply::Transfer(ref transfer) = reply {
+            let witness_txid = transfer.psbt.global.unsigned_tx.txid();
+            let supply =
+                asset.add_burn(&burn.burn_utxos, &transition, witness_txid)?;
+            debug!("Updated asset supply: {}", supply);
+            self.cacher.add_asset(asset)?;
+        }
+
+        Ok(reply)
+    }
+
     async fn validate(
         &mut self,
         consignment: Consignment,
@@ -431,8 +1627,48 @@ impl Runtime {
 
     async fn accept(
         &mut self,
-        accept: AcceptApi,
+        mut accept: AcceptApi,
     ) -> Result<Reply, ServiceErrorDomain> {
+        // Consignment endpoints matching invoices registered by the node are
+        // revealed with the stored invoice data
+        let contract_id = accept.consignment.genesis.contract_id();
+        let endpoints = accept
+            .consignment
+            .endpoints
+            .iter()
+            .map(|(_, outpoint_hash)| *outpoint_hash)
+            .collect::<Vec<_>>();
+        let invoices = self
+            .cacher
+            .invoices()?
+            .into_iter()
+            .filter(|invoice| {
+                *invoice.contract_id() == contract_id
+                    && endpoints.contains(&invoice.outpoint_hash())
+            })
+            .collect::<Vec<_>>();
+        // Blinding factors derived from the wallet seed are recovered for the
+        // locally-controlled outputs, so no per-invoice backup is required
+        let recovered = self.seed.recover(
+            &accept.witness_outpoints,
+            &endpoints,
+            &self.counters,
+        );
+        for reveal in invoices
+            .iter()
+            .map(InvoiceRecord::outpoint_reveal)
+            .chain(recovered)
+        {
+            let outpoint_hash = reveal.conceal();
+            if !accept
+                .reveal_outpoints
+                .iter()
+                .any(|known| known.conceal() == outpoint_hash)
+            {
+                accept.reveal_outpoints.push(reveal);
+            }
+        }
+
         let reply = self
             .stash_req_rep(api::stash::Request::Merge(MergeRequest {
                 consignment: accept.consignment.clone(),
@@ -442,12 +1678,14 @@ impl Runtime {
         if let Reply::Success = reply {
             let asset_id = accept.consignment.genesis.contract_id();
             let mut asset = if self.cacher.has_asset(asset_id)? {
-                self.cacher.asset(asset_id)?.clone()
+                self.cacher.asset(asset_id)?
             } else {
                 Asset::try_from(accept.consignment.genesis)?
             };
 
-            for (_, transition) in &accept.consignment.state_transitions {
+            let mut entries = vec![];
+            for (anchor, transition) in &accept.consignment.state_transitions {
+                let mut received = 0u64;
                 let set =
                     transition.owned_rights_by_type(*OwnedRightsType::Assets);
                 for variant in set {
@@ -463,25 +1701,78 @@ impl Runtime {
                                 if let Some(assigned_state) =
                                     assignment.assigned_state()
                                 {
-                                    asset.add_allocation(
+                                    if asset.add_allocation(
                                         seal.clone().into(),
                                         transition.node_id(),
                                         index as u16,
                                         assigned_state.clone(),
-                                    );
+                                    ) {
+                                        received = received.saturating_add(
+                                            assigned_state.value,
+                                        );
+                                    }
                                 } else {
                                     Err(ServiceErrorDomain::Internal(
                                         "Consignment structure is broken"
                                             .to_string(),
                                     ))?
                                 }
+                            } else if let OwnedState::Revealed {
+                                seal_definition,
+                                assigned_state,
+                            } = assignment
+                            {
+                                // Seals disclosed by pay-to-address transfers
+                                let outpoint = match seal_definition {
+                                    SealDefinition::TxOutpoint(reveal) => {
+                                        OutPoint::from(reveal.clone())
+                                    }
+                                    SealDefinition::WitnessVout {
+                                        vout,
+                                        ..
+                                    } => OutPoint {
+                                        txid: anchor.txid,
+                                        vout: *vout,
+                                    },
+                                };
+                                if accept.witness_outpoints.contains(&outpoint)
+                                    && asset.add_allocation(
+                                        outpoint,
+                                        transition.node_id(),
+                                        index as u16,
+                                        assigned_state.clone(),
+                                    )
+                                {
+                                    received = received
+                                        .saturating_add(assigned_state.value);
+                                }
                             }
                         }
                     }
                 }
+                if received > 0 {
+                    entries.push(LedgerEntry::with(
+                        asset_id,
+                        transition.node_id(),
+                        LedgerOperation::Received,
+                        Some(anchor.txid),
+                        accounting_value(&asset, received),
+                        None,
+                    ));
+                }
             }
 
             self.cacher.add_asset(asset)?;
+            for entry in entries {
+                self.cacher.add_ledger_entry(entry)?;
+            }
+
+            for mut invoice in invoices {
+                debug!("Invoice for {} is paid", invoice.outpoint());
+                invoice.mark_paid();
+                self.cacher.add_invoice(invoice)?;
+            }
+
             Ok(reply)
         } else if let Reply::Failure(_) = &reply {
             Ok(reply)
@@ -495,14 +1786,8 @@ impl Runtime {
         outpoint: OutPoint,
     ) -> Result<Reply, ServiceErrorDomain> {
         let mut removal_list = Vec::<_>::new();
-        let assets = self
-            .cacher
-            .assets()?
-            .into_iter()
-            .map(Clone::clone)
-            .collect::<Vec<_>>();
-        for asset in assets {
-            let mut asset = asset.clone();
+        let mut entries = Vec::<LedgerEntry>::new();
+        for mut asset in self.cacher.assets()? {
             for allocation in asset
                 .clone()
                 .allocations(&outpoint)
@@ -515,8 +1800,19 @@ impl Runtime {
                     allocation.value().clone(),
                 );
                 removal_list.push((*allocation.node_id(), *allocation.index()));
+                entries.push(LedgerEntry::with(
+                    *asset.id(),
+                    *allocation.node_id(),
+                    LedgerOperation::Forgotten,
+                    None,
+                    -accounting_value(&asset, allocation.value().value),
+                    None,
+                ));
             }
             self.cacher.add_asset(asset)?;
+            for entry in entries.drain(..) {
+                self.cacher.add_ledger_entry(entry)?;
+            }
         }
         if removal_list.is_empty() {
             return Ok(Reply::Nothing);
@@ -548,9 +1844,409 @@ impl Runtime {
     }
 }
 
+/// Returns revealed seals of the right assignments in the order of their
+/// indexes; concealed seals are represented with `None`
+fn right_seals(
+    node: &impl Node,
+    right: OwnedRightsType,
+) -> Vec<Option<SealDefinition>> {
+    let assignments = match node.owned_rights_by_type(*right) {
+        Some(assignments) => assignments,
+        None => return vec![],
+    };
+    match assignments {
+        Assignments::Declarative(_) => assignments
+            .to_declarative_state()
+            .into_iter()
+            .map(|state| match state {
+                OwnedState::Revealed {
+                    seal_definition, ..
+                } => Some(seal_definition),
+                _ => None,
+            })
+            .collect(),
+        Assignments::DiscreteFiniteField(_) => assignments
+            .to_discrete_state()
+            .into_iter()
+            .map(|state| match state {
+                OwnedState::Revealed {
+                    seal_definition, ..
+                } => Some(seal_definition),
+                _ => None,
+            })
+            .collect(),
+        Assignments::CustomData(_) => assignments
+            .to_custom_state()
+            .into_iter()
+            .map(|state| match state {
+                OwnedState::Revealed {
+                    seal_definition, ..
+                } => Some(seal_definition),
+                _ => None,
+            })
+            .collect(),
+    }
+}
+
+/// Returns index of the right assignment with a revealed seal matching the
+/// given outpoint
+fn right_index(
+    node: &impl Node,
+    right: OwnedRightsType,
+    outpoint: OutPoint,
+) -> Option<u16> {
+    right_seals(node, right)
+        .into_iter()
+        .position(|seal| {
+            seal.and_then(|seal| OutPoint::try_from(seal).ok())
+                == Some(outpoint)
+        })
+        .map(|index| index as u16)
+}
+
+/// Returns amounts of the asset allocations assigned by the node in the order
+/// of their indexes; concealed amounts are represented with `None`
+fn allocated_amounts(node: &impl Node) -> Vec<Option<AtomicValue>> {
+    node.owned_rights_by_type(*OwnedRightsType::Assets)
+        .map(|assignments| {
+            assignments
+                .to_discrete_state()
+                .into_iter()
+                .map(|state| match state {
+                    OwnedState::Revealed { assigned_state, .. } => {
+                        Some(assigned_state.value)
+                    }
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Returns outpoints of the right assignments closed by the transition, for
+/// which the seals are revealed in the parent nodes. Parent transitions are
+/// looked up among the `replayed` ones, together with their witness
+/// transaction ids, and parent extensions among the `extensions`.
+fn closed_outpoints(
+    transition: &Transition,
+    right: OwnedRightsType,
+    genesis: &Genesis,
+    extensions: &BTreeMap<NodeId, Extension>,
+    replayed: &BTreeMap<NodeId, (Txid, Transition)>,
+) -> Vec<OutPoint> {
+    let mut outpoints = vec![];
+    for (parent_id, rights) in transition.parent_owned_rights() {
+        let indexes = match rights.get(&*right) {
+            Some(indexes) => indexes,
+            None => continue,
+        };
+        let (seals, witness_txid) = if *parent_id == genesis.node_id() {
+            (right_seals(genesis, right), None)
+        } else if let Some((txid, parent)) = replayed.get(parent_id) {
+            (right_seals(parent, right), Some(*txid))
+        } else if let Some(parent) = extensions.get(parent_id) {
+            (right_seals(parent, right), None)
+        } else {
+            continue;
+        };
+        for index in indexes {
+            match (seals.get(*index as usize).cloned().flatten(), witness_txid)
+            {
+                (Some(SealDefinition::TxOutpoint(reveal)), _) => {
+                    outpoints.push(OutPoint::from(reveal))
+                }
+                (
+                    Some(SealDefinition::WitnessVout { vout, .. }),
+                    Some(txid),
+                ) => outpoints.push(OutPoint { txid, vout }),
+                _ => {}
+            }
+        }
+    }
+    outpoints
+}
+
+/// Reconstructs asset data from the full contract history, applying genesis
+/// and state transitions in the same way as the RPC handlers do. Operations
+/// are attributed to the node only when the rights they close have revealed
+/// seals, i.e. were controlled by the node; the rest of the transitions only
+/// contribute the allocations revealed to the node. State extensions do not
+/// close any seals, so their allocations are registered before the
+/// transitions, which may spend them.
+fn replay(consignment: &Consignment) -> Result<Asset, ServiceErrorDomain> {
+    replay_history(
+        &consignment.genesis,
+        &consignment.state_extensions,
+        consignment
+            .state_transitions
+            .iter()
+            .map(|(anchor, transition)| (anchor.txid, transition)),
+    )
+}
+
+/// Replays contract history given as state transitions in topological order
+/// together with their witness transaction ids
+fn replay_history<'a>(
+    genesis: &Genesis,
+    state_extensions: &[Extension],
+    state_transitions: impl IntoIterator<Item = (Txid, &'a Transition)>,
+) -> Result<Asset, ServiceErrorDomain> {
+    let mut asset = Asset::try_from(genesis.clone())?;
+    let mut extensions = BTreeMap::<NodeId, Extension>::new();
+    for extension in state_extensions {
+        asset.add_extension(extension);
+        extensions.insert(extension.node_id(), extension.clone());
+    }
+    let mut replayed = BTreeMap::<NodeId, (Txid, Transition)>::new();
+    for (witness_txid, transition) in state_transitions {
+        let closed = |right| {
+            closed_outpoints(transition, right, genesis, &extensions, &replayed)
+        };
+        let inputs = closed(OwnedRightsType::Assets);
+        let transition_type = transition.transition_type();
+        if transition_type == *TransitionType::Issue {
+            match closed(OwnedRightsType::Inflation).first() {
+                Some(origin) => {
+                    asset.add_issue(*origin, transition, witness_txid)?;
+                }
+                None => {
+                    asset.add_transfer(&inputs, transition, witness_txid, &[])
+                }
+            }
+        } else if transition_type == *TransitionType::Burn
+            || transition_type == *TransitionType::BurnAndReplace
+        {
+            if closed(OwnedRightsType::BurnReplace).is_empty() {
+                asset.add_transfer(&inputs, transition, witness_txid, &[]);
+            } else {
+                asset.add_burn(&inputs, transition, witness_txid)?;
+            }
+        } else if transition_type == *TransitionType::Renomination {
+            if !closed(OwnedRightsType::Renomination).is_empty() {
+                asset.add_nomination(transition);
+            }
+        } else if transition_type == *TransitionType::RightsSplit {
+            let mut inputs = inputs;
+            inputs.extend(closed(OwnedRightsType::Inflation));
+            asset.add_rights_split(&inputs, transition, witness_txid)?;
+        } else if transition_type == *TransitionType::Transfer {
+            asset.add_transfer(&inputs, transition, witness_txid, &[]);
+        }
+        // Epoch transitions do not affect cached asset data
+        replayed
+            .insert(transition.node_id(), (witness_txid, transition.clone()));
+    }
+    Ok(asset)
+}
+
 pub async fn main_with_config(config: Config) -> Result<(), BootstrapError> {
     let runtime = Runtime::init(config)?;
     runtime.run_or_panic("Fungible contract runtime").await;
 
     unreachable!()
 }
+
+/// Returns ids of the blank state transitions per contract
+fn blank_transition_ids(
+    blanks: &[(Asset, Vec<OutPoint>, Transition)],
+) -> BTreeMap<ContractId, NodeId> {
+    blanks
+        .iter()
+        .map(|(asset, _, transition)| (*asset.id(), transition.node_id()))
+        .collect()
+}
+
+/// Returns blank state transitions, which have to be kept in the stash
+fn blank_transition_list(
+    blanks: &[(Asset, Vec<OutPoint>, Transition)],
+) -> Vec<Transition> {
+    blanks
+        .iter()
+        .map(|(_, _, transition)| transition.clone())
+        .collect()
+}
+
+/// Returns total amount of the asset allocated to the receivers
+fn payment_amount(
+    asset: &Asset,
+    theirs: &[ConsealCoins],
+    theirs_addressed: &[(SealDefinition, AccountingValue)],
+) -> Result<AtomicValue, ServiceErrorDomain> {
+    processor::checked_sum(
+        theirs
+            .iter()
+            .map(|theirs| theirs.coins)
+            .chain(theirs_addressed.iter().map(|(_, coins)| *coins))
+            .map(|coins| {
+                AccountingAmount::transmutate(*asset.fractional_bits(), coins)
+            }),
+    )
+}
+
+/// Returns total amount of the asset allocated to the given outputs
+fn inputs_amount(
+    asset: &Asset,
+    inputs: &[OutPoint],
+) -> Result<AtomicValue, ServiceErrorDomain> {
+    processor::checked_sum(
+        inputs
+            .iter()
+            .filter_map(|outpoint| asset.allocations(outpoint))
+            .flatten()
+            .map(|alloc| alloc.value().value),
+    )
+}
+
+/// Returns total amount of the asset allocated to the node. The amount is
+/// only reported to the user, so it saturates instead of failing.
+fn balance(asset: &Asset) -> AtomicValue {
+    asset
+        .known_allocations()
+        .values()
+        .flatten()
+        .fold(0u64, |acc, alloc| acc.saturating_add(alloc.value().value))
+}
+
+/// Converts atomic value of the asset into its accounting value
+fn accounting_value(asset: &Asset, value: AtomicValue) -> AccountingValue {
+    AccountingAmount::from_fractioned_atomic_value(
+        *asset.fractional_bits(),
+        value,
+    )
+    .accounting_value()
+}
+
+/// Returns concealed seals of the receiver's allocations, which are exposed
+/// to the receiver within the consignment
+fn exposed_seals(
+    theirs: &[ConsealCoins],
+    theirs_addressed: &[(SealDefinition, AccountingValue)],
+) -> Vec<OutpointHash> {
+    theirs
+        .iter()
+        .map(|theirs| theirs.seal_confidential)
+        .chain(
+            theirs_addressed
+                .iter()
+                .map(|(seal_definition, _)| seal_definition.conceal()),
+        )
+        .collect()
+}
+
+/// Prepares consignment for a single receiver of a batch payment. The tip
+/// state transition is concealed once again, so the receiver does not learn
+/// seals, amounts and blinding factors of the other receivers' allocations.
+fn receiver_consignment(
+    consignment: &Consignment,
+    transition: &Transition,
+    endpoint: OutpointHash,
+) -> Consignment {
+    let mut consignment = consignment.clone();
+    consignment.endpoints.retain(|(_, seal)| *seal == endpoint);
+    let node_id = transition.node_id();
+    consignment
+        .state_transitions
+        .iter_mut()
+        .filter(|(_, tip)| tip.node_id() == node_id)
+        .for_each(|(_, tip)| {
+            *tip = transition.clone();
+            tip.conceal_except(&vec![endpoint]);
+        });
+    consignment
+}
+
+/// Returns seals of the receiver's allocations kept revealed by the transfer
+fn theirs_seals(
+    theirs_addressed: &[(SealDefinition, AccountingValue)],
+) -> Vec<SealDefinition> {
+    theirs_addressed
+        .iter()
+        .map(|(seal_definition, _)| seal_definition.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::contracts::fungible::SealCoins;
+    use lnpbp::bp::blind::OutpointReveal;
+    use lnpbp::hashes::Hash;
+
+    fn outpoint(vout: u32) -> OutPoint {
+        OutPoint {
+            txid: Txid::from_inner([0x01; 32]),
+            vout,
+        }
+    }
+
+    #[test]
+    fn test_replay_matches_incremental_updates() {
+        // Asset is updated in the same way as by ISSUE, TRANSFER and INFLATE
+        // request handlers
+        let (mut asset, genesis) = processor::issue(
+            lnpbp::bp::Chain::Testnet3,
+            s!("TST"),
+            s!("Test asset"),
+            None,
+            0,
+            vec![(outpoint(0), 10000), (outpoint(1), 5000)],
+            bmap! { outpoint(2) => 100000 },
+            None,
+            None,
+        )
+        .unwrap();
+
+        let transfer_txid = Txid::from_inner([0x02; 32]);
+        let receiver = OutpointReveal {
+            blinding: 1,
+            txid: Txid::from_inner([0x03; 32]),
+            vout: 0,
+        };
+        let transfer = processor::transfer(
+            &mut asset,
+            vec![outpoint(0)],
+            vec![],
+            vec![ConsealCoins {
+                coins: 30.0,
+                seal_confidential: receiver.conceal(),
+            }],
+            vec![],
+            Some(SealDefinition::WitnessVout {
+                vout: 1,
+                blinding: 2,
+            }),
+        )
+        .unwrap();
+        asset.add_transfer(&[outpoint(0)], &transfer, transfer_txid, &[]);
+
+        let inflate_txid = Txid::from_inner([0x04; 32]);
+        let inflate = processor::inflate(
+            &asset,
+            outpoint(2),
+            (genesis.node_id(), 0),
+            vec![SealCoins {
+                coins: 200.0,
+                vout: 0,
+                txid: None,
+            }],
+            vec![],
+            Some(outpoint(3)),
+        )
+        .unwrap();
+        asset
+            .add_issue(outpoint(2), &inflate, inflate_txid)
+            .unwrap();
+
+        let replayed = replay_history(
+            &genesis,
+            &[],
+            vec![(transfer_txid, &transfer), (inflate_txid, &inflate)],
+        )
+        .unwrap();
+
+        assert_eq!(replayed.known_allocations(), asset.known_allocations());
+        assert_eq!(replayed.known_issues(), asset.known_issues());
+        assert_eq!(replayed.known_inflation(), asset.known_inflation());
+        assert_eq!(replayed.supply(), asset.supply());
+    }
+}
@@ -17,6 +17,7 @@ use lnpbp::bp;
 
 use crate::constants::*;
 use crate::rgbd::ContractName;
+use crate::util::BlindingSeed;
 
 #[derive(Clone, PartialEq, Eq, Debug, Display)]
 #[display(Debug)]
@@ -30,6 +31,8 @@ pub struct Config {
     pub contract_endpoints: HashMap<ContractName, String>,
     pub network: bp::Chain,
     pub run_embedded: bool,
+    pub seed_file: String,
+    pub seed: Option<BlindingSeed>,
 }
 
 impl Default for Config {
@@ -48,6 +51,8 @@ impl Default for Config {
                 .parse()
                 .expect("Error in RGB_NETWORK constant value"),
             run_embedded: true,
+            seed_file: FUNGIBLED_SEED.to_owned(),
+            seed: None,
         }
     }
 }
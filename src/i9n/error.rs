@@ -46,4 +46,9 @@ pub enum Error {
     /// The provided network id does not match the network used by the RGB node
     #[display(doc_comments)]
     WrongNetwork,
+
+    /// Transaction prototype does not contain an output paying to the invoice
+    /// address
+    #[display(doc_comments)]
+    AddressOutputMissing,
 }
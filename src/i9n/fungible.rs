@@ -11,6 +11,7 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
+use chrono::NaiveDateTime;
 use std::collections::BTreeMap;
 use std::fs::File;
 use std::path::PathBuf;
@@ -28,14 +29,16 @@ use lnpbp::rgb::{
 
 use super::{Error, Runtime};
 use crate::api::{
-    fungible::AcceptApi, fungible::Issue, fungible::Request,
-    fungible::TransferApi, reply, Reply,
+    fungible::AcceptApi, fungible::HistoryApi, fungible::InvoiceApi,
+    fungible::Issue, fungible::Request, fungible::TransferApi, reply, Reply,
 };
 use crate::error::ServiceErrorDomain;
 use crate::fungible::{
-    ConsealCoins, Invoice, Outpoint, OutpointCoins, SealCoins,
+    AccountingValue, ConsealCoins, Invoice, InvoiceRecord, LedgerEntry,
+    Outpoint, OutpointCoins, SealCoins,
 };
 use crate::util::file::ReadWrite;
+use crate::util::{KnownHistory, SealSpec};
 use crate::DataFormat;
 
 impl Runtime {
@@ -86,16 +89,13 @@ impl Runtime {
         &mut self,
         inputs: Vec<OutPoint>,
         allocate: Vec<SealCoins>,
+        change: Option<SealSpec>,
         invoice: Invoice,
         prototype_psbt: String,
         consignment_file: String,
         transaction_file: String,
+        known_history: Option<KnownHistory>,
     ) -> Result<(), Error> {
-        let seal_confidential = match invoice.outpoint {
-            Outpoint::BlindedUtxo(outpoint_hash) => outpoint_hash,
-            Outpoint::Address(_address) => unimplemented!(),
-        };
-
         let psbt_bytes = base64::decode(&prototype_psbt)?;
         let mut psbt: PartiallySignedTransaction = deserialize(&psbt_bytes)?;
 
@@ -121,15 +121,42 @@ impl Runtime {
         }
         trace!("{:?}", psbt);
 
+        let mut theirs = vec![];
+        let mut theirs_addressed = vec![];
+        match invoice.outpoint {
+            Outpoint::BlindedUtxo(outpoint_hash) => theirs.push(ConsealCoins {
+                coins: invoice.amount,
+                seal_confidential: outpoint_hash,
+            }),
+            Outpoint::Address(address) => {
+                // The output paying to the invoice address must be added to
+                // the transaction prototype by the payer wallet
+                let vout = psbt
+                    .global
+                    .unsigned_tx
+                    .output
+                    .iter()
+                    .position(|txout| {
+                        txout.script_pubkey == address.script_pubkey()
+                    })
+                    .ok_or(Error::AddressOutputMissing)?;
+                theirs_addressed.push(SealCoins {
+                    coins: invoice.amount,
+                    vout: vout as u32,
+                    txid: None,
+                })
+            }
+        };
+
         let api = TransferApi {
             psbt,
             contract_id: invoice.contract_id,
             inputs,
             ours: allocate,
-            theirs: vec![ConsealCoins {
-                coins: invoice.amount,
-                seal_confidential,
-            }],
+            theirs,
+            theirs_addressed,
+            change,
+            known_history,
         };
 
         match &*self.command(Request::Transfer(api))? {
@@ -152,14 +179,67 @@ impl Runtime {
         }
     }
 
+    pub fn invoice(
+        &mut self,
+        contract_id: ContractId,
+        amount: AccountingValue,
+        outpoints: Vec<OutPoint>,
+        expiry: Option<NaiveDateTime>,
+    ) -> Result<Vec<InvoiceRecord>, Error> {
+        let api = InvoiceApi {
+            contract_id,
+            amount,
+            outpoints,
+            expiry,
+        };
+
+        match &*self.command(Request::Invoice(api))? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::Invoices(records) => Ok(records.clone()),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    pub fn list_invoices(
+        &mut self,
+        contract_id: Option<ContractId>,
+    ) -> Result<Vec<InvoiceRecord>, Error> {
+        match &*self.command(Request::ListInvoices(contract_id))? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::Invoices(records) => Ok(records.clone()),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    pub fn history(
+        &mut self,
+        contract_id: ContractId,
+        offset: u32,
+        limit: u32,
+    ) -> Result<Vec<LedgerEntry>, Error> {
+        let api = HistoryApi {
+            contract_id,
+            offset,
+            limit,
+        };
+
+        match &*self.command(Request::History(api))? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::History(entries) => Ok(entries.clone()),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
     pub fn accept(
         &mut self,
         consignment: Consignment,
         reveal_outpoints: Vec<bp::blind::OutpointReveal>,
+        witness_outpoints: Vec<OutPoint>,
     ) -> Result<(), Error> {
         let api = AcceptApi {
             consignment,
             reveal_outpoints,
+            witness_outpoints,
         };
 
         match &*self.command(Request::Accept(api))? {
@@ -205,6 +285,28 @@ impl Runtime {
         }
     }
 
+    pub fn balance(
+        &mut self,
+        contract_id: Option<ContractId>,
+    ) -> Result<BTreeMap<ContractId, reply::Balance>, Error> {
+        match &*self.command(Request::Balance(contract_id))? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::Balances(response) => Ok(response.clone()),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    pub fn reindex(&mut self) -> Result<(), Error> {
+        match &*self.command(Request::Reindex())? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::Success => {
+                info!("Reindex command succeeded");
+                Ok(())
+            }
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
     pub fn export_asset(
         &mut self,
         asset_id: ContractId,
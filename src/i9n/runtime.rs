@@ -11,6 +11,7 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
+use std::path::Path;
 use std::str::FromStr;
 use std::thread;
 
@@ -23,6 +24,7 @@ use super::Config;
 use crate::api::Reply;
 use crate::error::BootstrapError;
 use crate::rgbd::{self, ContractName};
+use crate::util::BlindingSeed;
 
 pub struct Runtime {
     pub(super) config: Config,
@@ -33,6 +35,25 @@ pub struct Runtime {
 
 impl Runtime {
     pub fn init(config: Config) -> Result<Self, BootstrapError> {
+        if let Some(ref seed) = config.seed {
+            let seed_file = config
+                .seed_file
+                .replace("{data_dir}", &config.data_dir)
+                .replace("{network}", &config.network.to_string());
+            if Path::new(&seed_file).exists() {
+                // Never overwrite existing seed, since this will make all
+                // previously blinded outpoints unrecoverable
+                if BlindingSeed::read_file(&seed_file)? != *seed {
+                    Err(BootstrapError::ArgParseError(format!(
+                        "Seed file {} contains a different seed",
+                        seed_file
+                    )))?
+                }
+            } else {
+                seed.write_file(&seed_file)?;
+            }
+        }
+
         // Start rgbd on a separate thread
         if config.run_embedded {
             let rgbd_opts = rgbd::Opts {
@@ -58,6 +79,7 @@ impl Runtime {
                 stash_pub_endpoint: config.stash_pub_endpoint.clone(),
                 fungible_pub_endpoint: config.fungible_pub_endpoint.clone(),
                 network: config.network.clone(),
+                seed: config.seed_file.clone(),
                 threaded: true,
                 ..rgbd::Opts::default()
             };
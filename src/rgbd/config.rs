@@ -92,6 +92,11 @@ pub struct Opts {
     #[clap(short, long, default_value = "yaml", env = "RGB_FUNGIBLED_FORMAT")]
     pub format: DataFormat,
 
+    /// File with hex-encoded wallet seed used by fungibled to derive outpoint
+    /// blinding factors
+    #[clap(long, default_value = FUNGIBLED_SEED, env = "RGB_FUNGIBLED_SEED")]
+    pub seed: String,
+
     /// Connection string to stashd stash (exact format depends on used storage
     /// engine)
     #[clap(
@@ -176,6 +181,7 @@ pub struct Config {
     pub stash_pub_endpoint: String,
     pub cache: String,
     pub format: DataFormat,
+    pub seed: String,
     pub stash: String,
     pub index: String,
     pub p2p_endpoint: String,
@@ -196,6 +202,7 @@ impl From<Opts> for Config {
             stash_pub_endpoint: opts.stash_pub_endpoint,
             cache: opts.cache,
             format: opts.format,
+            seed: opts.seed,
             stash: opts.stash,
             index: opts.index,
             p2p_endpoint: opts.p2p_endpoint,
@@ -226,6 +233,7 @@ impl Default for Config {
             format: DataFormat::Yaml,
             #[cfg(not(feature = "serde_yaml"))]
             format: DataFormat::StrictEncode,
+            seed: FUNGIBLED_SEED.to_string(),
             stash: STASHD_STASH.to_string(),
             index: STASHD_INDEX.to_string(),
             p2p_endpoint: STASHD_P2P_ENDPOINT.to_string(),
@@ -261,6 +269,7 @@ impl Default for Opts {
             format: DataFormat::Yaml,
             #[cfg(not(feature = "serde_yaml"))]
             format: DataFormat::StrictEncode,
+            seed: FUNGIBLED_SEED.to_string(),
             stash: STASHD_STASH.to_string(),
             index: STASHD_INDEX.to_string(),
             p2p_endpoint: STASHD_P2P_ENDPOINT.to_string(),
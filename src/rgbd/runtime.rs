@@ -87,6 +87,8 @@ impl Runtime {
                 self.config.cache.to_string(),
                 s!("--format"),
                 self.config.format.to_string(),
+                s!("--seed"),
+                self.config.seed.to_string(),
             ]
             .iter()
             .cloned(),
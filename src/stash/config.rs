@@ -46,6 +46,10 @@ pub struct Opts {
     #[clap(short, long, default_value = STASHD_INDEX, env = "RGB_STASHD_INDEX")]
     pub index: String,
 
+    /// Path to the file storing schema registry metadata
+    #[clap(long, default_value = STASHD_REGISTRY, env = "RGB_STASHD_REGISTRY")]
+    pub registry: String,
+
     /// LNP socket address string for P2P API
     #[clap(long = "bind", default_value = STASHD_P2P_ENDPOINT, env = "RGB_STASHD_BIND")]
     pub p2p_endpoint: String,
@@ -90,6 +94,7 @@ pub struct Config {
     pub data_dir: PathBuf,
     pub stash: String,
     pub index: String,
+    pub registry: String,
     pub p2p_endpoint: String,
     pub rpc_endpoint: ZmqSocketAddr,
     pub pub_endpoint: ZmqSocketAddr,
@@ -107,6 +112,7 @@ impl From<Opts> for Config {
         me.data_dir = me.parse_param(opts.data_dir);
         me.stash = me.parse_param(opts.stash);
         me.index = me.parse_param(opts.index);
+        me.registry = me.parse_param(opts.registry);
         me.rpc_endpoint = me.parse_param(opts.rpc_endpoint);
         me.pub_endpoint = me.parse_param(opts.pub_endpoint);
         me.p2p_endpoint = me.parse_param(opts.p2p_endpoint);
@@ -125,6 +131,7 @@ impl Default for Config {
                 .expect("Error in RGB_DATA_DIR constant value"),
             stash: STASHD_STASH.to_string(),
             index: STASHD_INDEX.to_string(),
+            registry: STASHD_REGISTRY.to_string(),
             p2p_endpoint: STASHD_P2P_ENDPOINT.to_string(),
             rpc_endpoint: STASHD_RPC_ENDPOINT
                 .parse()
@@ -36,6 +36,37 @@ impl ElectrumTxResolver {
     }
 }
 
+impl ElectrumTxResolver {
+    /// Returns number of confirmations of the transaction; zero for the
+    /// transactions which are not mined yet or are not known to the server
+    pub fn confirmations(&self, txid: &Txid) -> Result<u32, Error> {
+        let mut client = self.client.borrow_mut();
+        let tx = match client.transaction_get(txid) {
+            Ok(tx) => tx,
+            // Server reports unknown transactions with a protocol error
+            Err(Error::Protocol(_)) => return Ok(0),
+            Err(err) => return Err(err),
+        };
+        // Electrum servers index transactions by output scripts only
+        let script = match tx.output.first() {
+            Some(txout) => txout.script_pubkey.clone(),
+            None => return Ok(0),
+        };
+        let height = client
+            .script_get_history(&script)?
+            .into_iter()
+            .find(|entry| entry.tx_hash == *txid)
+            .map(|entry| entry.height)
+            .unwrap_or_default();
+        if height <= 0 {
+            // Zero and negative heights are used for mempool transactions
+            return Ok(0);
+        }
+        let tip = client.block_headers_subscribe()?.height as u32;
+        Ok(tip.saturating_sub(height as u32) + 1)
+    }
+}
+
 impl TxResolver for &ElectrumTxResolver {
     fn resolve(
         &self,
@@ -11,19 +11,27 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
 use std::io;
 use std::path::PathBuf;
 
-use lnpbp::rgb::{Anchor, AnchorId, NodeId};
-use lnpbp::strict_encoding::{StrictDecode, StrictEncode};
+use lnpbp::rgb::{Anchor, AnchorId, ContractId, NodeId};
+use lnpbp::strict_encoding::{
+    strict_deserialize, strict_serialize, StrictDecode, StrictEncode,
+};
 
 use super::Index;
 use crate::error::{BootstrapError, ServiceErrorDomain};
 
 type BTreeIndexData = BTreeMap<Vec<u8>, Vec<u8>>;
 
+/// Prefix of the keys attributing nodes to contracts. Each of these keys is
+/// followed by the contract id and the node id and has an empty value, so
+/// nodes of a contract are read with a single range query. The keys are
+/// longer than the transition id keys and never collide with them.
+const CONTRACT_NODE_KEY_PREFIX: &[u8] = b"contract_node";
+
 #[derive(Debug, Display, Error, From)]
 #[display(Debug)]
 pub enum BTreeIndexError {
@@ -32,6 +40,8 @@ pub enum BTreeIndexError {
 
     #[from]
     Encoding(lnpbp::strict_encoding::Error),
+
+    NotFound,
 }
 
 impl From<BTreeIndexError> for ServiceErrorDomain {
@@ -87,6 +97,14 @@ impl BTreeIndex {
         self.index.strict_encode(file)?;
         Ok(())
     }
+
+    fn contract_key(
+        contract_id: ContractId,
+    ) -> Result<Vec<u8>, BTreeIndexError> {
+        let mut key = CONTRACT_NODE_KEY_PREFIX.to_vec();
+        key.extend(strict_serialize(&contract_id)?);
+        Ok(key)
+    }
 }
 
 impl Index for BTreeIndex {
@@ -94,12 +112,85 @@ impl Index for BTreeIndex {
 
     fn anchor_id_by_transition_id(
         &self,
-        _tsid: NodeId,
+        tsid: NodeId,
     ) -> Result<AnchorId, Self::Error> {
-        unimplemented!()
+        let data = self
+            .index
+            .get(&strict_serialize(&tsid)?)
+            .ok_or(BTreeIndexError::NotFound)?;
+        Ok(strict_deserialize(data)?)
+    }
+
+    fn index_transition(
+        &mut self,
+        tsid: NodeId,
+        anchor: &Anchor,
+    ) -> Result<bool, Self::Error> {
+        Ok(self
+            .index
+            .insert(
+                strict_serialize(&tsid)?,
+                strict_serialize(&anchor.anchor_id())?,
+            )
+            .is_some())
     }
 
-    fn index_anchor(&mut self, _anchor: &Anchor) -> Result<bool, Self::Error> {
-        unimplemented!()
+    fn node_ids_by_contract_id(
+        &self,
+        contract_id: ContractId,
+    ) -> Result<BTreeSet<NodeId>, Self::Error> {
+        let prefix = Self::contract_key(contract_id)?;
+        self.index
+            .range(prefix.clone()..)
+            .take_while(|(key, _)| key.starts_with(&prefix))
+            .map(|(key, _)| Ok(strict_deserialize(&key[prefix.len()..])?))
+            .collect()
+    }
+
+    fn index_contract_node(
+        &mut self,
+        contract_id: ContractId,
+        node_id: NodeId,
+    ) -> Result<bool, Self::Error> {
+        let mut key = Self::contract_key(contract_id)?;
+        key.extend(strict_serialize(&node_id)?);
+        Ok(self.index.insert(key, vec![]).is_some())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use lnpbp::hashes::Hash;
+
+    #[test]
+    fn test_contract_nodes() {
+        let mut index = BTreeIndex::new(BTreeIndexConfig {
+            index_file: PathBuf::from("index.dat"),
+        });
+        let contract1 = ContractId::from_inner([0x01; 32]);
+        let contract2 = ContractId::from_inner([0x02; 32]);
+        let node1 = NodeId::from_inner([0x11; 32]);
+        let node2 = NodeId::from_inner([0x12; 32]);
+        let node3 = NodeId::from_inner([0x13; 32]);
+
+        assert!(!index.index_contract_node(contract1, node1).unwrap());
+        assert!(!index.index_contract_node(contract1, node2).unwrap());
+        assert!(!index.index_contract_node(contract2, node3).unwrap());
+        assert!(index.index_contract_node(contract1, node1).unwrap());
+
+        assert_eq!(
+            index.node_ids_by_contract_id(contract1).unwrap(),
+            bset! {node1, node2}
+        );
+        assert_eq!(
+            index.node_ids_by_contract_id(contract2).unwrap(),
+            bset! {node3}
+        );
+        assert!(index
+            .node_ids_by_contract_id(ContractId::from_inner([0x03; 32]))
+            .unwrap()
+            .is_empty());
+        assert!(index.anchor_id_by_transition_id(node1).is_err());
     }
 }
@@ -11,7 +11,9 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
-use lnpbp::rgb::{Anchor, AnchorId, NodeId};
+use std::collections::BTreeSet;
+
+use lnpbp::rgb::{Anchor, AnchorId, ContractId, NodeId};
 
 use crate::error::ServiceErrorDomain;
 
@@ -23,5 +25,26 @@ pub trait Index {
         tsid: NodeId,
     ) -> Result<AnchorId, Self::Error>;
 
-    fn index_anchor(&mut self, anchor: &Anchor) -> Result<bool, Self::Error>;
+    /// Registers state transition as committed by the given anchor. Returns
+    /// whether the transition was already indexed.
+    fn index_transition(
+        &mut self,
+        tsid: NodeId,
+        anchor: &Anchor,
+    ) -> Result<bool, Self::Error>;
+
+    /// Returns ids of all state transitions and extensions registered as a
+    /// part of the contract history
+    fn node_ids_by_contract_id(
+        &self,
+        contract_id: ContractId,
+    ) -> Result<BTreeSet<NodeId>, Self::Error>;
+
+    /// Registers state transition or extension as a part of the contract
+    /// history. Returns whether the node was already indexed.
+    fn index_contract_node(
+        &mut self,
+        contract_id: ContractId,
+        node_id: NodeId,
+    ) -> Result<bool, Self::Error>;
 }
@@ -12,6 +12,7 @@
 // If not, see <https://opensource.org/licenses/MIT>.
 
 mod config;
+mod registry;
 mod runtime;
 mod stash;
 
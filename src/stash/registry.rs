@@ -0,0 +1,387 @@
+// RGB standard library
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use lnpbp::rgb::schema::Occurences;
+use lnpbp::rgb::{Schema, SchemaId};
+use lnpbp::strict_encoding::{StrictDecode, StrictEncode};
+
+use super::storage::Store;
+use crate::error::{BootstrapError, ServiceErrorDomain};
+use crate::util::SchemaTypeNames;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Display)]
+#[display(Debug)]
+pub enum TypeKind {
+    Field,
+    OwnedRight,
+    PublicRight,
+    Genesis,
+    Extension,
+    Transition,
+}
+
+#[derive(Debug, Display, Error, From)]
+#[display(Debug)]
+pub enum SchemaRegistryError {
+    #[from]
+    Io(io::Error),
+
+    #[from]
+    Encoding(lnpbp::strict_encoding::Error),
+
+    /// Root schema referenced by the subschema is not known
+    UnknownRootSchema(SchemaId),
+
+    /// Root schema must not have a root schema itself
+    NestedRootSchema(SchemaId),
+
+    /// Type is used by the schema, but is not declared by it
+    UndeclaredType(TypeKind, usize),
+
+    /// Type is declared by both subschema and its root in a different way
+    ConflictingType(TypeKind, usize),
+
+    /// Subschema declares type which is absent in its root schema
+    AbsentInRoot(TypeKind, usize),
+
+    /// Subschema relaxes the requirements of its root schema for the type
+    NotRestriction(TypeKind, usize),
+
+    /// Type name is provided for the type not declared by the schema
+    UnknownTypeName(TypeKind, usize),
+}
+
+impl From<SchemaRegistryError> for ServiceErrorDomain {
+    fn from(err: SchemaRegistryError) -> Self {
+        ServiceErrorDomain::Schema(err.to_string())
+    }
+}
+
+impl From<SchemaRegistryError> for BootstrapError {
+    fn from(_: SchemaRegistryError) -> Self {
+        BootstrapError::StorageError
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
+#[display(Debug)]
+pub struct SchemaRegistryConfig {
+    pub registry_file: PathBuf,
+}
+
+/// Registry of the schemata known to the stash. Checks schema consistency and
+/// its compatibility with the root schema before the schema gets stored, and
+/// keeps human-readable names of the schema types as the schema metadata.
+#[derive(Display, Debug)]
+#[display(Debug)]
+pub struct SchemaRegistry {
+    config: SchemaRegistryConfig,
+    type_names: BTreeMap<SchemaId, SchemaTypeNames>,
+}
+
+impl SchemaRegistry {
+    pub fn new(config: SchemaRegistryConfig) -> Self {
+        debug!("Instantiating schema registry ...");
+        Self {
+            config,
+            type_names: bmap! {},
+        }
+    }
+
+    pub fn load(
+        config: SchemaRegistryConfig,
+    ) -> Result<Self, SchemaRegistryError> {
+        if let Ok(file) = fs::File::open(&config.registry_file) {
+            debug!(
+                "Loading schema registry from file {:?} ...",
+                &config.registry_file
+            );
+            Ok(Self {
+                config,
+                type_names: BTreeMap::strict_decode(file)?,
+            })
+        } else {
+            Ok(Self::new(config))
+        }
+    }
+
+    pub fn store(&self) -> Result<(), SchemaRegistryError> {
+        debug!(
+            "Saving schema registry to file {:?} ...",
+            &self.config.registry_file
+        );
+        let _ = fs::remove_file(&self.config.registry_file);
+        let file = fs::File::create(&self.config.registry_file)?;
+        self.type_names.strict_encode(file)?;
+        Ok(())
+    }
+
+    pub fn type_names(&self, schema_id: &SchemaId) -> Option<&SchemaTypeNames> {
+        self.type_names.get(schema_id)
+    }
+
+    /// Registers type names for the schema, checking that all named types
+    /// are declared by it
+    pub fn register_type_names(
+        &mut self,
+        schema: &Schema,
+        type_names: SchemaTypeNames,
+    ) -> Result<(), SchemaRegistryError> {
+        check_names(
+            TypeKind::Field,
+            &type_names.field_types,
+            schema.field_types.keys(),
+        )?;
+        check_names(
+            TypeKind::OwnedRight,
+            &type_names.owned_right_types,
+            schema.owned_right_types.keys(),
+        )?;
+        check_names(
+            TypeKind::PublicRight,
+            &type_names.public_right_types,
+            schema.public_right_types.iter(),
+        )?;
+        check_names(
+            TypeKind::Extension,
+            &type_names.extension_types,
+            schema.extensions.keys(),
+        )?;
+        check_names(
+            TypeKind::Transition,
+            &type_names.transition_types,
+            schema.transitions.keys(),
+        )?;
+        self.type_names.insert(schema.schema_id(), type_names);
+        self.store()
+    }
+
+    /// Checks that the schema is internally consistent and, if it has a root
+    /// schema, that it is a valid restriction of the root
+    pub fn check(
+        &self,
+        schema: &Schema,
+        storage: &impl Store,
+    ) -> Result<(), SchemaRegistryError> {
+        check_declarations(schema)?;
+
+        if schema.root_id == SchemaId::default() {
+            return Ok(());
+        }
+        let root = storage.schema(&schema.root_id).map_err(|_| {
+            SchemaRegistryError::UnknownRootSchema(schema.root_id)
+        })?;
+        if root.root_id != SchemaId::default() {
+            Err(SchemaRegistryError::NestedRootSchema(root.root_id))?
+        }
+        check_restriction(schema, &root)
+    }
+}
+
+fn check_names<'a>(
+    kind: TypeKind,
+    names: &BTreeMap<usize, String>,
+    declared: impl Iterator<Item = &'a usize>,
+) -> Result<(), SchemaRegistryError> {
+    let declared = declared.copied().collect::<BTreeSet<_>>();
+    match names.keys().find(|ty| !declared.contains(ty)) {
+        Some(ty) => Err(SchemaRegistryError::UnknownTypeName(kind, *ty)),
+        None => Ok(()),
+    }
+}
+
+fn check_declared<'a>(
+    kind: TypeKind,
+    used: impl Iterator<Item = &'a usize>,
+    declared: &BTreeSet<usize>,
+) -> Result<(), SchemaRegistryError> {
+    for ty in used {
+        if !declared.contains(ty) {
+            Err(SchemaRegistryError::UndeclaredType(kind, *ty))?
+        }
+    }
+    Ok(())
+}
+
+/// Checks that all field and right types used by genesis, extensions and
+/// transitions are declared by the schema
+fn check_declarations(schema: &Schema) -> Result<(), SchemaRegistryError> {
+    let fields = schema.field_types.keys().copied().collect();
+    let owned = schema.owned_right_types.keys().copied().collect();
+    let public = schema.public_right_types.iter().copied().collect();
+
+    let genesis = &schema.genesis;
+    check_declared(TypeKind::Field, genesis.metadata.keys(), &fields)?;
+    check_declared(TypeKind::OwnedRight, genesis.owned_rights.keys(), &owned)?;
+    check_declared(
+        TypeKind::PublicRight,
+        genesis.public_rights.iter(),
+        &public,
+    )?;
+
+    for extension in schema.extensions.values() {
+        check_declared(TypeKind::Field, extension.metadata.keys(), &fields)?;
+        check_declared(
+            TypeKind::OwnedRight,
+            extension.owned_rights.keys(),
+            &owned,
+        )?;
+        check_declared(
+            TypeKind::PublicRight,
+            extension
+                .public_rights
+                .iter()
+                .chain(extension.extends.iter()),
+            &public,
+        )?;
+    }
+
+    for transition in schema.transitions.values() {
+        check_declared(TypeKind::Field, transition.metadata.keys(), &fields)?;
+        check_declared(
+            TypeKind::OwnedRight,
+            transition
+                .owned_rights
+                .keys()
+                .chain(transition.closes.keys()),
+            &owned,
+        )?;
+        check_declared(
+            TypeKind::PublicRight,
+            transition.public_rights.iter(),
+            &public,
+        )?;
+    }
+    Ok(())
+}
+
+/// Checks that the type declarations of the subschema match the root, and
+/// that the subschema does not allow anything which is not allowed by the
+/// root
+fn check_restriction(
+    schema: &Schema,
+    root: &Schema,
+) -> Result<(), SchemaRegistryError> {
+    for (ty, format) in &schema.field_types {
+        match root.field_types.get(ty) {
+            None => {
+                Err(SchemaRegistryError::AbsentInRoot(TypeKind::Field, *ty))?
+            }
+            Some(root_format) if root_format != format => {
+                Err(SchemaRegistryError::ConflictingType(TypeKind::Field, *ty))?
+            }
+            _ => {}
+        }
+    }
+    for (ty, state) in &schema.owned_right_types {
+        match root.owned_right_types.get(ty) {
+            None => Err(SchemaRegistryError::AbsentInRoot(
+                TypeKind::OwnedRight,
+                *ty,
+            ))?,
+            Some(root_state) if root_state != state => Err(
+                SchemaRegistryError::ConflictingType(TypeKind::OwnedRight, *ty),
+            )?,
+            _ => {}
+        }
+    }
+    if let Some(ty) = schema
+        .public_right_types
+        .difference(&root.public_right_types)
+        .next()
+    {
+        Err(SchemaRegistryError::AbsentInRoot(
+            TypeKind::PublicRight,
+            *ty,
+        ))?
+    }
+
+    let genesis = &schema.genesis;
+    let root_genesis = &root.genesis;
+    if !is_restriction(&genesis.metadata, &root_genesis.metadata)
+        || !is_restriction(&genesis.owned_rights, &root_genesis.owned_rights)
+        || !genesis.public_rights.is_subset(&root_genesis.public_rights)
+        || genesis.abi != root_genesis.abi
+    {
+        Err(SchemaRegistryError::NotRestriction(TypeKind::Genesis, 0))?
+    }
+
+    for (ty, extension) in &schema.extensions {
+        let root_extension = root.extensions.get(ty).ok_or(
+            SchemaRegistryError::AbsentInRoot(TypeKind::Extension, *ty),
+        )?;
+        if !is_restriction(&extension.metadata, &root_extension.metadata)
+            || !is_restriction(
+                &extension.owned_rights,
+                &root_extension.owned_rights,
+            )
+            || !extension.extends.is_subset(&root_extension.extends)
+            || !extension
+                .public_rights
+                .is_subset(&root_extension.public_rights)
+            || extension.abi != root_extension.abi
+        {
+            Err(SchemaRegistryError::NotRestriction(
+                TypeKind::Extension,
+                *ty,
+            ))?
+        }
+    }
+
+    for (ty, transition) in &schema.transitions {
+        let root_transition = root.transitions.get(ty).ok_or(
+            SchemaRegistryError::AbsentInRoot(TypeKind::Transition, *ty),
+        )?;
+        if !is_restriction(&transition.metadata, &root_transition.metadata)
+            || !is_restriction(&transition.closes, &root_transition.closes)
+            || !is_restriction(
+                &transition.owned_rights,
+                &root_transition.owned_rights,
+            )
+            || !transition
+                .public_rights
+                .is_subset(&root_transition.public_rights)
+            || transition.abi != root_transition.abi
+        {
+            Err(SchemaRegistryError::NotRestriction(
+                TypeKind::Transition,
+                *ty,
+            ))?
+        }
+    }
+
+    Ok(())
+}
+
+/// Subschema structure is a restriction of the root one if it uses only the
+/// types allowed by the root, requires all the types required by the root and
+/// does not widen the allowed number of occurrences
+fn is_restriction(
+    structure: &BTreeMap<usize, Occurences<u16>>,
+    root: &BTreeMap<usize, Occurences<u16>>,
+) -> bool {
+    structure.iter().all(|(ty, occurences)| match root.get(ty) {
+        None => false,
+        Some(root_occurences) => {
+            occurences.min_value() >= root_occurences.min_value()
+                && occurences.max_value() <= root_occurences.max_value()
+        }
+    }) && root.iter().all(|(ty, occurences)| {
+        occurences.min_value() == 0 || structure.contains_key(ty)
+    })
+}
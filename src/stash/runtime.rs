@@ -20,23 +20,29 @@ use lnpbp::lnp::{
     TypedEnum, Unmarshall, Unmarshaller,
 };
 use lnpbp::rgb::{
-    validation, Anchor, Assignments, Consignment, ContractId, Genesis, Node,
-    NodeId, Schema, SchemaId, Stash, Validity,
+    validation, Anchor, AnchorId, Assignments, Consignment, ContractId,
+    Genesis, Node, NodeId, Schema, SchemaId, Stash, Validity,
 };
 
 use super::electrum::ElectrumTxResolver;
 use super::index::{BTreeIndex, Index};
+use super::registry::{SchemaRegistry, SchemaRegistryConfig};
 #[cfg(not(store_hammersbald))] // Default store
 use super::storage::{DiskStorage, DiskStorageConfig, Store};
 use super::Config;
-use crate::api::stash::{ConsignRequest, MergeRequest, Request};
+use crate::api::stash::{
+    BatchConsignRequest, ConsignRequest, ExtensionConsignRequest,
+    GenesisConsignRequest, HistoryRequest, MergeRequest, Request,
+    SchemaRegistration,
+};
 use crate::api::{reply, Reply};
 use crate::error::{
-    BootstrapError, RuntimeError, ServiceError, ServiceErrorDomain,
-    ServiceErrorSource,
+    ApiErrorType, BootstrapError, RuntimeError, ServiceError,
+    ServiceErrorDomain, ServiceErrorSource,
 };
 use crate::service::TryService;
 use crate::stash::index::BTreeIndexConfig;
+use crate::util::ConsignmentStats;
 
 pub struct Runtime {
     /// Original configuration object
@@ -67,6 +73,9 @@ pub struct Runtime {
     #[cfg(all(store_hammersbald, not(any(store_disk))))]
     pub(super) storage: HammersbaldStore,
 
+    /// Schema registry checking schemata and keeping their metadata
+    registry: SchemaRegistry,
+
     /// Unmarshaller instance used for parsing RPC request
     unmarshaller: Unmarshaller<Request>,
 
@@ -97,6 +106,10 @@ impl Runtime {
             index_file: PathBuf::from(config.index.clone()),
         })?;
 
+        let registry = SchemaRegistry::load(SchemaRegistryConfig {
+            registry_file: PathBuf::from(config.registry.clone()),
+        })?;
+
         let session_rpc = session::Raw::with_zmq_unencrypted(
             ZmqType::Rep,
             &config.rpc_endpoint,
@@ -119,6 +132,7 @@ impl Runtime {
             session_pub,
             indexer,
             storage,
+            registry,
             unmarshaller: Request::create_unmarshaller(),
             electrum,
         })
@@ -174,13 +188,43 @@ impl Runtime {
             Request::ReadSchema(schema_id) => {
                 self.rpc_read_schema(schema_id).await
             }
+            Request::ReadTransitions(node_ids) => {
+                self.rpc_read_transitions(node_ids).await
+            }
+            Request::ReadExtensions(node_ids) => {
+                self.rpc_read_extensions(node_ids).await
+            }
+            Request::RegisterSchema(registration) => {
+                self.rpc_register_schema(registration).await
+            }
+            Request::ReadSchemaTypeNames(schema_id) => {
+                self.rpc_read_schema_type_names(schema_id).await
+            }
+            Request::ReadAnchors(anchor_ids) => {
+                self.rpc_read_anchors(anchor_ids).await
+            }
+            Request::ReadHistory(request) => {
+                self.rpc_read_history(request).await
+            }
+            Request::ReadContract(contract_id) => {
+                self.rpc_read_contract(contract_id).await
+            }
             Request::Consign(consign) => self.rpc_consign(consign).await,
+            Request::ConsignBatch(batch) => self.rpc_consign_batch(batch).await,
+            Request::ConsignExtension(request) => {
+                self.rpc_consign_extension(request).await
+            }
+            Request::ConsignGenesis(request) => {
+                self.rpc_consign_genesis(request).await
+            }
             Request::Validate(consign) => self.rpc_validate(consign).await,
             Request::Merge(merge) => self.rpc_merge(merge).await,
             Request::Forget(removal_list) => {
                 self.rpc_forget(removal_list).await
             }
-            _ => unimplemented!(),
+            Request::Confirmations(txids) => {
+                self.rpc_confirmations(txids).await
+            }
         }
         .map_err(|err| ServiceError {
             domain: err,
@@ -205,15 +249,93 @@ impl Runtime {
         schema: &Schema,
     ) -> Result<Reply, ServiceErrorDomain> {
         debug!("Got ADD_SCHEMA {}", schema);
+        if let Err(err) = self.registry.check(schema, &self.storage) {
+            return Ok(Reply::Failure(reply::Failure {
+                code: 1,
+                info: err.to_string(),
+            }));
+        }
+        self.storage.add_schema(schema)?;
+        Ok(Reply::Success)
+    }
+
+    async fn rpc_register_schema(
+        &mut self,
+        registration: &SchemaRegistration,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got REGISTER_SCHEMA {}", registration.schema);
+        let schema = &registration.schema;
+        if let Err(err) =
+            self.registry.check(schema, &self.storage).and_then(|_| {
+                self.registry.register_type_names(
+                    schema,
+                    registration.type_names.clone(),
+                )
+            })
+        {
+            return Ok(Reply::Failure(reply::Failure {
+                code: 1,
+                info: err.to_string(),
+            }));
+        }
         self.storage.add_schema(schema)?;
         Ok(Reply::Success)
     }
 
+    async fn rpc_read_schema_type_names(
+        &mut self,
+        schema_id: &SchemaId,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got READ_SCHEMA_TYPE_NAMES {}", schema_id);
+        Ok(match self.registry.type_names(schema_id) {
+            Some(type_names) => Reply::SchemaTypeNames(type_names.clone()),
+            None => Reply::Nothing,
+        })
+    }
+
     async fn rpc_add_genesis(
         &mut self,
         genesis: &Genesis,
     ) -> Result<Reply, ServiceErrorDomain> {
         debug!("Got ADD_GENESIS {}", genesis);
+
+        if genesis.chain() != &self.config.network {
+            return Ok(Reply::Failure(reply::Failure {
+                code: 1,
+                info: format!(
+                    "Genesis belongs to {} network, while the node runs on {}",
+                    genesis.chain(),
+                    self.config.network
+                ),
+            }));
+        }
+
+        let schema = match self.storage.schema(&genesis.schema_id()) {
+            Ok(schema) => schema,
+            Err(_) => {
+                return Ok(Reply::Failure(reply::Failure {
+                    code: 2,
+                    info: format!(
+                        "Genesis uses unknown schema {}; import the schema \
+                         first",
+                        genesis.schema_id()
+                    ),
+                }))
+            }
+        };
+
+        // [VALIDATION]: Genesis-only consignment does not have any anchored
+        // nodes, so only the genesis itself is validated against the schema
+        let validation_status =
+            Consignment::with(genesis.clone(), vec![], vec![], vec![])
+                .validate(&schema, &self.electrum);
+        if let Validity::Invalid = validation_status.validity() {
+            return Ok(Reply::Failure(reply::Failure {
+                code: 3,
+                info: format!("{:?}", validation_status.failures),
+            }));
+        }
+
         self.storage.add_genesis(genesis)?;
         Ok(Reply::Success)
     }
@@ -236,6 +358,81 @@ impl Runtime {
         Ok(Reply::Schema(schema))
     }
 
+    async fn rpc_read_transitions(
+        &mut self,
+        node_ids: &Vec<NodeId>,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got READ_TRANSITIONS {:?}", node_ids);
+        let transitions = node_ids
+            .iter()
+            .map(|node_id| self.storage.transition(node_id))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Reply::Transitions(transitions))
+    }
+
+    async fn rpc_read_extensions(
+        &mut self,
+        node_ids: &Vec<NodeId>,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got READ_EXTENSIONS {:?}", node_ids);
+        let extensions = node_ids
+            .iter()
+            .map(|node_id| self.storage.extension(node_id))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Reply::Extensions(extensions))
+    }
+
+    async fn rpc_read_anchors(
+        &mut self,
+        anchor_ids: &Vec<AnchorId>,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got READ_ANCHORS {:?}", anchor_ids);
+        let anchors = anchor_ids
+            .iter()
+            .map(|anchor_id| self.storage.anchor(anchor_id))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Reply::Anchors(anchors))
+    }
+
+    async fn rpc_read_history(
+        &mut self,
+        request: &HistoryRequest,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got READ_HISTORY {}", request);
+        let genesis = self.storage.genesis(&request.contract_id)?;
+        let (state_transitions, state_extensions) = self
+            .history(
+                &genesis,
+                request.node_ids.iter().copied().collect(),
+                |_| false,
+            )
+            .map_err(|_| ServiceErrorDomain::Stash)?;
+        Ok(Reply::Consignment(Consignment::with(
+            genesis,
+            vec![],
+            state_transitions,
+            state_extensions,
+        )))
+    }
+
+    async fn rpc_read_contract(
+        &mut self,
+        contract_id: &ContractId,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got READ_CONTRACT {}", contract_id);
+        let genesis = self.storage.genesis(contract_id)?;
+        let (state_transitions, state_extensions) = self
+            .contract_node_ids(*contract_id)
+            .and_then(|node_ids| self.history(&genesis, node_ids, |_| false))
+            .map_err(|_| ServiceErrorDomain::Stash)?;
+        Ok(Reply::Consignment(Consignment::with(
+            genesis,
+            vec![],
+            state_transitions,
+            state_extensions,
+        )))
+    }
+
     async fn rpc_consign(
         &mut self,
         request: &ConsignRequest,
@@ -257,15 +454,164 @@ impl Runtime {
         // Prepare consignments: extract from stash storage the required data
         // and assemble them into a consignment
         let consignment = self
-            .consign(
+            .consign_incremental(
                 request.contract_id,
                 &request.transition,
                 Some(&anchor),
                 &request.outpoints.clone(),
+                request.known_history.as_ref(),
+            )
+            .map_err(|_| ServiceErrorDomain::Stash)?;
+        let stats = ConsignmentStats::with(&consignment)
+            .map_err(|err| ServiceErrorDomain::Internal(err.to_string()))?;
+        info!(
+            "Consignment for {} prepared: {}",
+            request.contract_id, stats
+        );
+
+        // Transitions created by the node are kept in the stash, so the
+        // allocations remaining under the node control (change and assets
+        // moved by blank transitions) can be consigned further
+        self.store_consigned(
+            request.contract_id,
+            &anchor,
+            &request.transition,
+            &request.outpoints,
+        )
+        .map_err(|_| ServiceErrorDomain::Stash)?;
+        for transition in &request.other_transitions {
+            let contract_id = request
+                .other_transition_ids
+                .iter()
+                .find(|(_, node_id)| **node_id == transition.node_id())
+                .map(|(contract_id, _)| contract_id)
+                .ok_or(ServiceErrorDomain::Internal(s!(
+                    "Other state transition is not listed among the \
+                     committed transition ids"
+                )))?;
+            let anchor = &anchors[*map
+                .get(contract_id)
+                .expect("Core LNP/BP anchor commitment procedure is broken")];
+            self.store_consigned(*contract_id, anchor, transition, &[])
+                .map_err(|_| ServiceErrorDomain::Stash)?;
+        }
+        self.indexer.store()?;
+
+        Ok(Reply::Transfer(reply::Transfer {
+            consignment,
+            psbt,
+            stats,
+        }))
+    }
+
+    async fn rpc_consign_batch(
+        &mut self,
+        request: &BatchConsignRequest,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got CONSIGN_BATCH {}", request);
+
+        let mut transitions = bmap! {};
+        for item in &request.transitions {
+            if transitions
+                .insert(item.contract_id, item.transition.node_id())
+                .is_some()
+            {
+                Err(ServiceErrorDomain::Api(ApiErrorType::MalformedArgument {
+                    request: s!("CONSIGN_BATCH"),
+                    argument: format!(
+                        "more than one state transition for contract {}",
+                        item.contract_id
+                    ),
+                }))?
+            }
+        }
+
+        // Construct anchors: all transitions are committed into the same
+        // witness transaction
+        let mut psbt = request.psbt.clone();
+        let (anchors, map) = Anchor::commit(transitions, &mut psbt)
+            .map_err(|err| ServiceErrorDomain::Anchor(format!("{}", err)))?;
+
+        let mut consignments = bmap! {};
+        let mut disclosure = bmap! {};
+        for item in &request.transitions {
+            let anchor = anchors[*map
+                .get(&item.contract_id)
+                .expect("Core LNP/BP anchor commitment procedure is broken")]
+            .clone();
+
+            let consignment = self
+                .consign(
+                    item.contract_id,
+                    &item.transition,
+                    Some(&anchor),
+                    &item.outpoints.clone(),
+                )
+                .map_err(|_| ServiceErrorDomain::Stash)?;
+
+            // Transitions created by the node are kept in the stash, like
+            // in case of a single-contract consignment
+            self.store_consigned(
+                item.contract_id,
+                &anchor,
+                &item.transition,
+                &item.outpoints,
             )
             .map_err(|_| ServiceErrorDomain::Stash)?;
 
-        Ok(Reply::Transfer(reply::Transfer { consignment, psbt }))
+            consignments.insert(item.contract_id, consignment);
+            disclosure
+                .insert(item.contract_id, (anchor, item.transition.clone()));
+        }
+        self.indexer.store()?;
+
+        Ok(Reply::BatchTransfer(reply::BatchTransfer {
+            consignments,
+            psbt,
+            disclosure,
+        }))
+    }
+
+    async fn rpc_consign_extension(
+        &mut self,
+        request: &ExtensionConsignRequest,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got CONSIGN_EXTENSION {}", request);
+
+        // State extensions are not anchored to bitcoin transactions, so no
+        // PSBT is required here
+        let consignment = self
+            .consign(
+                request.contract_id,
+                &request.extension,
+                None,
+                &request.outpoints,
+            )
+            .map_err(|_| ServiceErrorDomain::Stash)?;
+
+        self.store_consigned_extension(
+            request.contract_id,
+            &request.extension,
+            &request.outpoints,
+        )
+        .map_err(|_| ServiceErrorDomain::Stash)?;
+        self.indexer.store()?;
+
+        Ok(Reply::Consignment(consignment))
+    }
+
+    async fn rpc_consign_genesis(
+        &mut self,
+        request: &GenesisConsignRequest,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got CONSIGN_GENESIS {}", request);
+
+        let genesis = self.storage.genesis(&request.contract_id)?;
+        let consignment = self
+            .consign(request.contract_id, &genesis, None, &request.outpoints)
+            .map_err(|_| ServiceErrorDomain::Stash)?;
+
+        Ok(Reply::Consignment(consignment))
     }
 
     async fn rpc_validate(
@@ -279,6 +625,18 @@ impl Runtime {
             .schema(&consignment.genesis.schema_id())
             .map_err(|err| ServiceErrorDomain::Storage(err.to_string()))?;
 
+        // Incremental consignments do not contain the part of the history
+        // which we already have, so we need to add it back before validation
+        let consignment = match self.complete(consignment) {
+            Ok(consignment) => consignment,
+            Err(err) => {
+                return Ok(Reply::Failure(reply::Failure {
+                    code: 3,
+                    info: format!("{:?}", err),
+                }))
+            }
+        };
+
         // [VALIDATION]: Validate genesis node against the scheme
         let validation_status = consignment.validate(&schema, &self.electrum);
 
@@ -308,7 +666,22 @@ impl Runtime {
     ) -> Result<Reply, ServiceErrorDomain> {
         debug!("Got MERGE CONSIGNMENT");
 
+        // Consignment may be incremental, so its nodes may reference parents
+        // which are not included into it; these must be already present in
+        // the stash
+        let missing = self.missing_parents(&merge.consignment);
+        if !missing.is_empty() {
+            return Ok(Reply::Failure(reply::Failure {
+                code: 3,
+                info: format!(
+                    "Consignment references unknown parent nodes {:?}",
+                    missing
+                ),
+            }));
+        }
+
         let known_seals = &merge.reveal_outpoints;
+        let contract_id = merge.consignment.genesis.contract_id();
 
         // [PRIVACY]:
         // Update transition data with the revealed state information that we
@@ -347,6 +720,10 @@ impl Runtime {
             // Store the transition and the anchor data in the stash
             self.storage.add_anchor(&anchor)?;
             self.storage.add_transition(&transition)?;
+            self.indexer
+                .index_transition(transition.node_id(), &anchor)?;
+            self.indexer
+                .index_contract_node(contract_id, transition.node_id())?;
         }
 
         for extension in &merge.consignment.state_extensions {
@@ -356,11 +733,34 @@ impl Runtime {
                 .into_iter()
                 .for_each(reveal_known_seals);
             self.storage.add_extension(&extension)?;
+            self.indexer
+                .index_contract_node(contract_id, extension.node_id())?;
         }
+        self.indexer.store()?;
 
         Ok(Reply::Success)
     }
 
+    async fn rpc_confirmations(
+        &mut self,
+        txids: &Vec<Txid>,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got CONFIRMATIONS {:?}", txids);
+        let confirmations = txids
+            .iter()
+            .map(|txid| {
+                self.electrum
+                    .confirmations(txid)
+                    .map(|confirmations| (*txid, confirmations))
+            })
+            .collect::<Result<_, _>>()
+            .map_err(|err| {
+                error!("Electrum error: {:?}", err);
+                ServiceErrorDomain::Bitcoin
+            })?;
+        Ok(Reply::Confirmations(confirmations))
+    }
+
     async fn rpc_forget(
         &mut self,
         _removal_list: &Vec<(NodeId, u16)>,
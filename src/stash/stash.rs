@@ -11,18 +11,19 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 
 use lnpbp::bp::blind::OutpointHash;
 use lnpbp::hashes::Hash;
 use lnpbp::rgb::{
-    Anchor, AutoConceal, Consignment, ContractId, Disclosure, Extension,
-    Genesis, Node, NodeId, SchemaId, Stash, Transition,
+    Anchor, AnchorId, Assignments, AutoConceal, Consignment, ContractId,
+    Disclosure, Extension, Genesis, Node, NodeId, SchemaId, Stash, Transition,
 };
 
 use super::index::Index;
 use super::storage::Store;
 use super::Runtime;
+use crate::util::KnownHistory;
 
 #[derive(Clone, PartialEq, Eq, Debug, Display, From, Error)]
 #[display(Debug)]
@@ -35,7 +36,127 @@ pub enum Error {
 
     AnchorParameterIsRequired,
 
-    GenesisNode,
+    UnknownNodeType,
+
+    UnknownParentNode(NodeId),
+}
+
+/// Ancestor node collected during consignment construction
+enum HistoryNode {
+    Transition(AnchorId, Transition),
+    Extension(Extension),
+}
+
+impl HistoryNode {
+    fn parent_ids(&self) -> BTreeSet<NodeId> {
+        match self {
+            HistoryNode::Transition(_, transition) => parent_ids(transition),
+            HistoryNode::Extension(extension) => parent_ids(extension),
+        }
+    }
+}
+
+/// Returns ids of all nodes referenced by the given node, either via owned or
+/// public rights
+fn parent_ids(node: &impl Node) -> BTreeSet<NodeId> {
+    node.parent_owned_rights()
+        .keys()
+        .chain(node.parent_public_rights().keys())
+        .copied()
+        .collect()
+}
+
+/// Returns concealed seals of all owned rights assigned by the node
+fn node_seals(node: &impl Node) -> Vec<OutpointHash> {
+    node.owned_rights()
+        .values()
+        .flat_map(|assignments| match assignments {
+            Assignments::Declarative(set) => set
+                .iter()
+                .map(|assignment| assignment.seal_definition_confidential())
+                .collect::<Vec<_>>(),
+            Assignments::DiscreteFiniteField(set) => set
+                .iter()
+                .map(|assignment| assignment.seal_definition_confidential())
+                .collect(),
+            Assignments::CustomData(set) => set
+                .iter()
+                .map(|assignment| assignment.seal_definition_confidential())
+                .collect(),
+        })
+        .collect()
+}
+
+/// Returns ids of all nodes contained in the consignment, except genesis
+fn consignment_node_ids(consignment: &Consignment) -> BTreeSet<NodeId> {
+    consignment
+        .state_transitions
+        .iter()
+        .map(|(_, transition)| transition.node_id())
+        .chain(
+            consignment
+                .state_extensions
+                .iter()
+                .map(|extension| extension.node_id()),
+        )
+        .collect()
+}
+
+/// Returns ids of all nodes referenced as parents by the consignment nodes
+fn consignment_parent_ids(consignment: &Consignment) -> BTreeSet<NodeId> {
+    consignment
+        .state_transitions
+        .iter()
+        .flat_map(|(_, transition)| parent_ids(transition))
+        .chain(
+            consignment
+                .state_extensions
+                .iter()
+                .flat_map(|extension| parent_ids(extension)),
+        )
+        .collect()
+}
+
+/// Orders nodes topologically, such that parents always precede their
+/// children. Takes a map of node ids to the ids of their parents; parents
+/// which are not present in the map are ignored. Ties are resolved by node
+/// id, so the same set of nodes always results in the same order.
+fn topological_order(
+    parents: BTreeMap<NodeId, BTreeSet<NodeId>>,
+) -> Vec<NodeId> {
+    let mut pending = BTreeMap::<NodeId, usize>::new();
+    let mut children = BTreeMap::<NodeId, Vec<NodeId>>::new();
+    for (node_id, parent_ids) in &parents {
+        let parent_ids = parent_ids
+            .iter()
+            .filter(|id| parents.contains_key(id))
+            .collect::<Vec<_>>();
+        pending.insert(*node_id, parent_ids.len());
+        for parent_id in parent_ids {
+            children.entry(*parent_id).or_insert(vec![]).push(*node_id);
+        }
+    }
+    let mut ready = pending
+        .iter()
+        .filter(|(_, count)| **count == 0)
+        .map(|(node_id, _)| *node_id)
+        .collect::<BTreeSet<_>>();
+
+    let mut order = vec![];
+    while let Some(node_id) = ready.iter().next().copied() {
+        ready.remove(&node_id);
+        for child_id in children.remove(&node_id).unwrap_or_default() {
+            let count = pending
+                .get_mut(&child_id)
+                .expect("Every child node is registered in the map above");
+            *count -= 1;
+            if *count == 0 {
+                ready.insert(child_id);
+            }
+        }
+        order.push(node_id);
+    }
+    order
 }
 
 pub struct DumbIter<T>(std::marker::PhantomData<T>);
@@ -113,88 +234,7 @@ impl Stash for Runtime {
         anchor: Option<&Anchor>,
         expose: &Vec<OutpointHash>,
     ) -> Result<Consignment, Error> {
-        let genesis = self.storage.genesis(&contract_id)?;
-
-        let mut state_transitions = vec![];
-        let mut state_extensions: Vec<Extension> = vec![];
-        if let Some(transition) =
-            node.as_any().downcast_ref::<Transition>().clone()
-        {
-            let mut transition = transition.clone();
-            transition.conceal_except(&expose);
-            let anchor = anchor.ok_or(Error::AnchorParameterIsRequired)?;
-            state_transitions.push((anchor.clone(), transition.clone()));
-        } else if let Some(extension) =
-            node.as_any().downcast_ref::<Extension>().clone()
-        {
-            let mut extension = extension.clone();
-            extension.conceal_except(&expose);
-            state_extensions.push(extension.clone());
-        } else {
-            Err(Error::GenesisNode)?;
-        }
-
-        let mut sources = VecDeque::<NodeId>::new();
-        sources
-            .extend(node.parent_owned_rights().into_iter().map(|(id, _)| id));
-        sources
-            .extend(node.parent_public_rights().into_iter().map(|(id, _)| id));
-        while let Some(node_id) = sources.pop_front() {
-            if node_id.into_inner() == genesis.contract_id().into_inner() {
-                continue;
-            }
-            let anchor_id = self.indexer.anchor_id_by_transition_id(node_id)?;
-            let anchor = self.storage.anchor(&anchor_id)?;
-            // TODO: (new) Improve this logic
-            match (
-                self.storage.transition(&node_id),
-                self.storage.extension(&node_id),
-            ) {
-                (Ok(mut transition), Err(_)) => {
-                    transition.conceal_all();
-                    state_transitions.push((anchor, transition.clone()));
-                    sources.extend(
-                        transition
-                            .parent_owned_rights()
-                            .into_iter()
-                            .map(|(id, _)| id),
-                    );
-                    sources.extend(
-                        transition
-                            .parent_public_rights()
-                            .into_iter()
-                            .map(|(id, _)| id),
-                    );
-                }
-                (Err(_), Ok(mut extension)) => {
-                    extension.conceal_all();
-                    state_extensions.push(extension.clone());
-                    sources.extend(
-                        extension
-                            .parent_owned_rights()
-                            .into_iter()
-                            .map(|(id, _)| id),
-                    );
-                    sources.extend(
-                        extension
-                            .parent_public_rights()
-                            .into_iter()
-                            .map(|(id, _)| id),
-                    );
-                }
-                _ => Err(Error::StorageError)?,
-            }
-        }
-
-        let node_id = node.node_id();
-        let extended_endpoints =
-            expose.iter().map(|op| (node_id, *op)).collect();
-        Ok(Consignment::with(
-            genesis,
-            extended_endpoints,
-            state_transitions,
-            state_extensions,
-        ))
+        self.consign_incremental(contract_id, node, anchor, expose, None)
     }
 
     fn merge(
@@ -202,18 +242,24 @@ impl Stash for Runtime {
         consignment: Consignment,
     ) -> Result<Vec<Box<dyn Node>>, Error> {
         let mut nodes: Vec<Box<dyn Node>> = vec![];
+        let contract_id = consignment.genesis.contract_id();
         consignment.state_transitions.into_iter().try_for_each(
             |(anchor, transition)| -> Result<(), Error> {
+                self.storage.add_anchor(&anchor)?;
+                self.indexer
+                    .index_transition(transition.node_id(), &anchor)?;
+                self.indexer
+                    .index_contract_node(contract_id, transition.node_id())?;
                 if self.storage.add_transition(&transition)? {
                     nodes.push(Box::new(transition));
                 }
-                self.storage.add_anchor(&anchor)?;
-                self.indexer.index_anchor(&anchor)?;
                 Ok(())
             },
         )?;
         consignment.state_extensions.into_iter().try_for_each(
             |extension| -> Result<(), Error> {
+                self.indexer
+                    .index_contract_node(contract_id, extension.node_id())?;
                 if self.storage.add_extension(&extension)? {
                     nodes.push(Box::new(extension));
                 }
@@ -224,6 +270,7 @@ impl Stash for Runtime {
         if self.storage.add_genesis(&genesis)? {
             nodes.push(Box::new(genesis));
         }
+        self.indexer.store()?;
 
         Ok(nodes)
     }
@@ -243,3 +290,307 @@ impl Stash for Runtime {
         unimplemented!()
     }
 }
+
+impl Runtime {
+    /// Prepares consignment omitting the part of the history which is already
+    /// known to the receiver. If `known` is `None` the full history back to
+    /// the genesis is consigned.
+    pub(super) fn consign_incremental(
+        &self,
+        contract_id: ContractId,
+        node: &impl Node,
+        anchor: Option<&Anchor>,
+        expose: &Vec<OutpointHash>,
+        known: Option<&KnownHistory>,
+    ) -> Result<Consignment, Error> {
+        let genesis = self.storage.genesis(&contract_id)?;
+
+        let node_id = node.node_id();
+        let extended_endpoints =
+            expose.iter().map(|op| (node_id, *op)).collect();
+
+        // Genesis-only consignments are used for contract announcements; the
+        // genesis is kept as is, so the exposed seals are visible to everybody
+        if node.as_any().downcast_ref::<Genesis>().is_some() {
+            return Ok(Consignment::with(
+                genesis,
+                extended_endpoints,
+                vec![],
+                vec![],
+            ));
+        }
+
+        // Resolving the tip node first, so we fail early on unknown node
+        // types or missing anchor
+        let tip = if let Some(transition) =
+            node.as_any().downcast_ref::<Transition>().clone()
+        {
+            let mut transition = transition.clone();
+            transition.conceal_except(&expose);
+            let anchor = anchor.ok_or(Error::AnchorParameterIsRequired)?;
+            (Some((anchor.clone(), transition)), None)
+        } else if let Some(extension) =
+            node.as_any().downcast_ref::<Extension>().clone()
+        {
+            let mut extension = extension.clone();
+            extension.conceal_except(&expose);
+            (None, Some(extension))
+        } else {
+            Err(Error::UnknownNodeType)?
+        };
+
+        // Nodes known to the receiver are skipped together with all their
+        // ancestors, since the receiver must already have them
+        let (mut state_transitions, mut state_extensions) =
+            self.history(&genesis, parent_ids(node), |node_id| {
+                known.map(|k| k.contains(node_id)).unwrap_or(false)
+            })?;
+        state_transitions
+            .iter_mut()
+            .for_each(|(_, transition)| transition.conceal_all());
+        state_extensions
+            .iter_mut()
+            .for_each(|extension| extension.conceal_all());
+
+        // The tip node always goes last
+        match tip {
+            (Some(transition), _) => state_transitions.push(transition),
+            (_, Some(extension)) => state_extensions.push(extension),
+            _ => {}
+        }
+
+        Ok(Consignment::with(
+            genesis,
+            extended_endpoints,
+            state_transitions,
+            state_extensions,
+        ))
+    }
+
+    /// Returns ids of the nodes which are referenced as parents by the
+    /// consignment nodes, but are present neither in the consignment itself
+    /// nor in the stash
+    pub(super) fn missing_parents(
+        &self,
+        consignment: &Consignment,
+    ) -> BTreeSet<NodeId> {
+        let genesis_id = consignment.genesis.node_id();
+        let consigned = consignment_node_ids(consignment);
+        consignment_parent_ids(consignment)
+            .into_iter()
+            .filter(|node_id| {
+                *node_id != genesis_id
+                    && !consigned.contains(node_id)
+                    && self.storage.transition(node_id).is_err()
+                    && self.storage.extension(node_id).is_err()
+            })
+            .collect()
+    }
+
+    /// Extends incremental consignment with the part of the history which is
+    /// already present in the stash, so the consignment can be validated
+    pub(super) fn complete(
+        &self,
+        consignment: &Consignment,
+    ) -> Result<Consignment, Error> {
+        let consigned = consignment_node_ids(consignment);
+        let (mut state_transitions, mut state_extensions) = self.history(
+            &consignment.genesis,
+            consignment_parent_ids(consignment),
+            |node_id| consigned.contains(node_id),
+        )?;
+        if state_transitions.is_empty() && state_extensions.is_empty() {
+            return Ok(consignment.clone());
+        }
+        state_transitions.extend(consignment.state_transitions.clone());
+        state_extensions.extend(consignment.state_extensions.clone());
+        Ok(Consignment::with(
+            consignment.genesis.clone(),
+            consignment.endpoints.clone(),
+            state_transitions,
+            state_extensions,
+        ))
+    }
+
+    /// Collects all ancestors of the `sources` nodes up to the genesis from
+    /// the stash, not descending into the nodes matching `skip` predicate.
+    ///
+    /// Each node is returned once, even if it is reachable via multiple
+    /// paths. Nodes are put into a single topological order (parents always
+    /// precede their children), which is then split into transitions and
+    /// extensions, as required by the consignment format; thus each of the
+    /// lists is ordered, but the order between transitions and extensions is
+    /// not preserved. Anchors are read from the storage once, however the
+    /// consignment pairs each transition with its anchor, so an anchor shared
+    /// by several transitions is repeated for each of them.
+    pub(super) fn history(
+        &self,
+        genesis: &Genesis,
+        sources: BTreeSet<NodeId>,
+        skip: impl Fn(&NodeId) -> bool,
+    ) -> Result<(Vec<(Anchor, Transition)>, Vec<Extension>), Error> {
+        // Each node is read from the storage only once, even if it is
+        // referenced by multiple descendants, and the same goes for the
+        // anchors
+        let mut anchors = BTreeMap::<AnchorId, Anchor>::new();
+        let mut ancestors = BTreeMap::<NodeId, HistoryNode>::new();
+        let mut sources = sources.into_iter().collect::<VecDeque<_>>();
+        while let Some(node_id) = sources.pop_front() {
+            if node_id.into_inner() == genesis.contract_id().into_inner()
+                || ancestors.contains_key(&node_id)
+                || skip(&node_id)
+            {
+                continue;
+            }
+            let ancestor = match (
+                self.storage.transition(&node_id),
+                self.storage.extension(&node_id),
+            ) {
+                (Ok(transition), Err(_)) => {
+                    // Only state transitions are anchored; extensions do not
+                    // have a witness transaction
+                    let anchor_id =
+                        self.indexer.anchor_id_by_transition_id(node_id)?;
+                    if !anchors.contains_key(&anchor_id) {
+                        anchors.insert(
+                            anchor_id,
+                            self.storage.anchor(&anchor_id)?,
+                        );
+                    }
+                    HistoryNode::Transition(anchor_id, transition)
+                }
+                (Err(_), Ok(extension)) => HistoryNode::Extension(extension),
+                _ => Err(Error::UnknownParentNode(node_id))?,
+            };
+            sources.extend(ancestor.parent_ids());
+            ancestors.insert(node_id, ancestor);
+        }
+
+        let order = topological_order(
+            ancestors
+                .iter()
+                .map(|(node_id, ancestor)| (*node_id, ancestor.parent_ids()))
+                .collect(),
+        );
+        let mut state_transitions = vec![];
+        let mut state_extensions = vec![];
+        for node_id in order {
+            match ancestors
+                .remove(&node_id)
+                .expect("Topological order contains only the ancestor nodes")
+            {
+                HistoryNode::Transition(anchor_id, transition) => {
+                    state_transitions
+                        .push((anchors[&anchor_id].clone(), transition))
+                }
+                HistoryNode::Extension(extension) => {
+                    state_extensions.push(extension)
+                }
+            }
+        }
+
+        Ok((state_transitions, state_extensions))
+    }
+
+    /// Returns ids of all state transitions and extensions from the stash
+    /// belonging to the given contract. Nodes are attributed to contracts by
+    /// the index when they are merged or consigned, so nodes stored by the
+    /// stash versions predating the index are not reported.
+    pub(super) fn contract_node_ids(
+        &self,
+        contract_id: ContractId,
+    ) -> Result<BTreeSet<NodeId>, Error> {
+        Ok(self.indexer.node_ids_by_contract_id(contract_id)?)
+    }
+
+    /// Stores state transition created by the node together with its anchor,
+    /// so it becomes a part of the contract history kept by the stash. Seals
+    /// `exposed` to the receivers are concealed, leaving revealed only the
+    /// seals controlled by the node.
+    pub(super) fn store_consigned(
+        &mut self,
+        contract_id: ContractId,
+        anchor: &Anchor,
+        transition: &Transition,
+        exposed: &[OutpointHash],
+    ) -> Result<(), Error> {
+        let mut transition = transition.clone();
+        let ours = node_seals(&transition)
+            .into_iter()
+            .filter(|seal| !exposed.contains(seal))
+            .collect();
+        transition.conceal_except(&ours);
+        self.storage.add_anchor(anchor)?;
+        self.storage.add_transition(&transition)?;
+        self.indexer
+            .index_transition(transition.node_id(), anchor)?;
+        self.indexer
+            .index_contract_node(contract_id, transition.node_id())?;
+        Ok(())
+    }
+
+    /// Stores state extension created by the node, so it becomes a part of
+    /// the contract history kept by the stash. Seals `exposed` to the
+    /// receivers are concealed in the same way as for the state transitions.
+    pub(super) fn store_consigned_extension(
+        &mut self,
+        contract_id: ContractId,
+        extension: &Extension,
+        exposed: &[OutpointHash],
+    ) -> Result<(), Error> {
+        let mut extension = extension.clone();
+        let ours = node_seals(&extension)
+            .into_iter()
+            .filter(|seal| !exposed.contains(seal))
+            .collect();
+        extension.conceal_except(&ours);
+        self.storage.add_extension(&extension)?;
+        self.indexer
+            .index_contract_node(contract_id, extension.node_id())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use lnpbp::hex::FromHex;
+
+    fn node_id(byte: &str) -> NodeId {
+        NodeId::from_hex(&byte.repeat(32)).unwrap()
+    }
+
+    #[test]
+    fn test_topological_order_diamond() {
+        // Genesis is not a part of the history, so it is omitted from the map
+        let genesis = node_id("ff");
+        let root = node_id("44");
+        let left = node_id("33");
+        let right = node_id("22");
+        let tip = node_id("11");
+        let parents = bmap! {
+            root => bset!{genesis},
+            left => bset!{root},
+            right => bset!{root},
+            tip => bset!{left, right}
+        };
+
+        assert_eq!(topological_order(parents), vec![root, right, left, tip]);
+    }
+
+    #[test]
+    fn test_topological_order_ties() {
+        let first = node_id("01");
+        let second = node_id("02");
+        let child = node_id("00");
+        let parents = bmap! {
+            second => BTreeSet::new(),
+            child => bset!{second},
+            first => BTreeSet::new()
+        };
+
+        // Independent nodes go in the order of their ids, but children are
+        // always put after their parents
+        assert_eq!(topological_order(parents), vec![first, second, child]);
+    }
+}
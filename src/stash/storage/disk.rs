@@ -194,6 +194,15 @@ impl DiskStorage {
             fs::create_dir_all(transitions_dir)?;
         }
 
+        let extensions_dir = config.extensions_dir();
+        if !extensions_dir.exists() {
+            debug!(
+                "RGB state extension data directory '{:?}' is not found; creating one",
+                extensions_dir
+            );
+            fs::create_dir_all(extensions_dir)?;
+        }
+
         Ok(Self { config })
     }
 }
@@ -0,0 +1,399 @@
+// RGB standard library
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use core::fmt::{self, Debug, Formatter};
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::fs;
+use std::io::{self, Write};
+#[cfg(unix)]
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use lnpbp::bitcoin::consensus::Encodable;
+use lnpbp::bitcoin::secp256k1::rand::{thread_rng, RngCore};
+use lnpbp::bitcoin::OutPoint;
+use lnpbp::bp::blind::{OutpointHash, OutpointReveal};
+use lnpbp::client_side_validation::Conceal;
+use lnpbp::hashes::{sha256, Hash, HashEngine, Hmac, HmacEngine};
+use lnpbp::hex::{FromHex, ToHex};
+
+/// Wallet-level seed used for deterministic derivation of outpoint blinding
+/// factors. Blinding factor for an outpoint is computed as the first 8 bytes
+/// of `HMAC-SHA256(seed, txid || vout || counter)`, so a wallet restored from
+/// the seed is able to reproduce all blinded seals it has ever generated.
+#[derive(Clone, PartialEq, Eq)]
+pub struct BlindingSeed([u8; 32]);
+
+// Seed must never get into logs
+impl Debug for BlindingSeed {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("BlindingSeed(..)")
+    }
+}
+
+impl BlindingSeed {
+    /// Number of consequent counter values tried for each outpoint when
+    /// recovering blinding factors
+    pub const GAP_LIMIT: u32 = 20;
+
+    #[inline]
+    pub fn from_inner(seed: [u8; 32]) -> Self {
+        Self(seed)
+    }
+
+    pub fn random() -> Self {
+        let mut seed = [0u8; 32];
+        thread_rng().fill_bytes(&mut seed);
+        Self(seed)
+    }
+
+    /// Reads hex-encoded seed from the file. The file must not be accessible
+    /// by other users.
+    pub fn read_file(path: impl AsRef<Path>) -> Result<Self, io::Error> {
+        #[cfg(unix)]
+        {
+            let mode = fs::metadata(&path)?.permissions().mode();
+            if mode & 0o077 != 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    format!(
+                        "seed file {:?} is accessible by other users \
+                        (mode {:o}); set its mode to 600",
+                        path.as_ref(),
+                        mode & 0o777
+                    ),
+                ));
+            }
+        }
+        let data = fs::read_to_string(path)?;
+        Vec::<u8>::from_hex(data.trim())
+            .ok()
+            .and_then(|seed| seed.as_slice().try_into().ok())
+            .map(Self)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "seed file must contain 32 hex-encoded bytes",
+                )
+            })
+    }
+
+    /// Writes hex-encoded seed to the file, which is made readable and
+    /// writable only by its owner
+    pub fn write_file(&self, path: impl AsRef<Path>) -> Result<(), io::Error> {
+        write_private(path, self.0.to_hex().as_bytes())
+    }
+
+    /// Reads the seed from the file, creating the file with a new random seed
+    /// if it does not exist
+    pub fn load_or_create(path: impl AsRef<Path>) -> Result<Self, io::Error> {
+        if path.as_ref().exists() {
+            Self::read_file(path)
+        } else {
+            debug!("Generating new blinding seed at {:?}", path.as_ref());
+            let seed = Self::random();
+            seed.write_file(path)?;
+            Ok(seed)
+        }
+    }
+
+    /// Derives blinding factor for the outpoint; `counter` distinguishes
+    /// different invoices created for the same outpoint
+    pub fn blinding(&self, outpoint: OutPoint, counter: u32) -> u64 {
+        let mut engine = HmacEngine::<sha256::Hash>::new(&self.0);
+        outpoint
+            .consensus_encode(&mut engine)
+            .expect("Memory encoders can't fail");
+        engine.input(&counter.to_le_bytes());
+        let hmac = Hmac::<sha256::Hash>::from_engine(engine);
+        let mut blinding = [0u8; 8];
+        blinding.copy_from_slice(&hmac.into_inner()[..8]);
+        u64::from_le_bytes(blinding)
+    }
+
+    pub fn outpoint_reveal(
+        &self,
+        outpoint: OutPoint,
+        counter: u32,
+    ) -> OutpointReveal {
+        OutpointReveal {
+            blinding: self.blinding(outpoint, counter),
+            txid: outpoint.txid,
+            vout: outpoint.vout,
+        }
+    }
+
+    /// Recovers reveal data for the blinded `endpoints` which were derived
+    /// from this seed for any of the given `outpoints`. Counters are tried up
+    /// to [`Self::GAP_LIMIT`] values past the ones already used according to
+    /// `counters`.
+    pub fn recover(
+        &self,
+        outpoints: &[OutPoint],
+        endpoints: &[OutpointHash],
+        counters: &BlindingCounters,
+    ) -> Vec<OutpointReveal> {
+        outpoints
+            .iter()
+            .flat_map(|outpoint| {
+                let limit =
+                    counters.next(*outpoint).saturating_add(Self::GAP_LIMIT);
+                (0..limit).map(move |counter| {
+                    self.outpoint_reveal(*outpoint, counter)
+                })
+            })
+            .filter(|reveal| endpoints.contains(&reveal.conceal()))
+            .collect()
+    }
+}
+
+/// Derivation counters already used for the outpoints. Counters are kept in a
+/// file next to the wallet seed, so they never go back after the loss of the
+/// cache or reindexing, and the same blinded outpoint is never generated
+/// twice.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct BlindingCounters(BTreeMap<OutPoint, u32>);
+
+impl BlindingCounters {
+    /// Path of the counters file kept together with the given seed file
+    pub fn path_for_seed(seed: impl AsRef<Path>) -> PathBuf {
+        seed.as_ref().with_extension("counters")
+    }
+
+    /// Reads counters from the file, where each line has
+    /// `<txid>:<vout> <next counter>` format
+    pub fn read_file(path: impl AsRef<Path>) -> Result<Self, io::Error> {
+        let invalid = |line: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid blinding counter record `{}`", line),
+            )
+        };
+        let mut counters = BTreeMap::new();
+        for line in fs::read_to_string(path)?.lines() {
+            let mut fields = line.split_whitespace();
+            let outpoint = fields
+                .next()
+                .and_then(|outpoint| OutPoint::from_str(outpoint).ok())
+                .ok_or_else(|| invalid(line))?;
+            let counter = fields
+                .next()
+                .and_then(|counter| counter.parse().ok())
+                .ok_or_else(|| invalid(line))?;
+            if fields.next().is_some() {
+                Err(invalid(line))?
+            }
+            counters.insert(outpoint, counter);
+        }
+        Ok(Self(counters))
+    }
+
+    /// Writes counters to the file, which is made readable and writable only
+    /// by its owner since it lists wallet outpoints
+    pub fn write_file(&self, path: impl AsRef<Path>) -> Result<(), io::Error> {
+        let data = self
+            .0
+            .iter()
+            .map(|(outpoint, counter)| format!("{} {}\n", outpoint, counter))
+            .collect::<String>();
+        write_private(path, data.as_bytes())
+    }
+
+    /// Reads counters from the file; if the file does not exist no counters
+    /// are used yet
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, io::Error> {
+        if path.as_ref().exists() {
+            Self::read_file(path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    /// Returns the first counter not used for the outpoint yet
+    pub fn next(&self, outpoint: OutPoint) -> u32 {
+        self.0.get(&outpoint).copied().unwrap_or(0)
+    }
+
+    /// Marks all counters up to the given one as used for the outpoint
+    pub fn mark_used(&mut self, outpoint: OutPoint, counter: u32) {
+        let next = counter.saturating_add(1);
+        let entry = self.0.entry(outpoint).or_insert(0);
+        if *entry < next {
+            *entry = next;
+        }
+    }
+}
+
+/// Writes data to the file which is readable and writable only by its owner
+fn write_private(path: impl AsRef<Path>, data: &[u8]) -> Result<(), io::Error> {
+    if let Some(dir) = path.as_ref().parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options.open(&path)?;
+    // Mode is applied only to newly created files
+    #[cfg(unix)]
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    file.write_all(data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use lnpbp::bitcoin::Txid;
+    use std::env;
+
+    fn outpoint(vout: u32) -> OutPoint {
+        OutPoint {
+            txid: Txid::from_inner([0x01; 32]),
+            vout,
+        }
+    }
+
+    #[test]
+    fn test_blinding_derivation() {
+        let seed = BlindingSeed::from_inner([0x11; 32]);
+        let blinding = seed.blinding(outpoint(0), 0);
+        assert_eq!(blinding, seed.blinding(outpoint(0), 0));
+        assert_eq!(
+            blinding,
+            BlindingSeed::from_inner([0x11; 32]).blinding(outpoint(0), 0)
+        );
+        assert_ne!(blinding, seed.blinding(outpoint(0), 1));
+        assert_ne!(blinding, seed.blinding(outpoint(1), 0));
+        assert_ne!(
+            blinding,
+            BlindingSeed::from_inner([0x12; 32]).blinding(outpoint(0), 0)
+        );
+
+        let reveal = seed.outpoint_reveal(outpoint(1), 5);
+        assert_eq!(reveal.blinding, seed.blinding(outpoint(1), 5));
+        assert_eq!(reveal.txid, outpoint(1).txid);
+        assert_eq!(reveal.vout, 1);
+    }
+
+    #[test]
+    fn test_recover() {
+        let seed = BlindingSeed::from_inner([0x11; 32]);
+        let first = seed.outpoint_reveal(outpoint(0), 0);
+        let last =
+            seed.outpoint_reveal(outpoint(1), BlindingSeed::GAP_LIMIT - 1);
+        let beyond = seed.outpoint_reveal(outpoint(1), BlindingSeed::GAP_LIMIT);
+        let foreign = BlindingSeed::from_inner([0x12; 32])
+            .outpoint_reveal(outpoint(0), 0);
+        let endpoints = vec![
+            first.conceal(),
+            last.conceal(),
+            beyond.conceal(),
+            foreign.conceal(),
+        ];
+
+        let counters = BlindingCounters::default();
+        let recovered =
+            seed.recover(&[outpoint(0), outpoint(1)], &endpoints, &counters);
+        assert_eq!(recovered.len(), 2);
+        assert!(recovered.iter().any(|reveal| *reveal == first));
+        assert!(recovered.iter().any(|reveal| *reveal == last));
+
+        // Outpoints not listed are not recovered
+        assert!(seed
+            .recover(&[outpoint(2)], &endpoints, &counters)
+            .is_empty());
+
+        // Gap is counted from the last used counter
+        let mut counters = BlindingCounters::default();
+        counters.mark_used(outpoint(1), 0);
+        let recovered = seed.recover(&[outpoint(1)], &endpoints, &counters);
+        assert_eq!(recovered, vec![last, beyond]);
+    }
+
+    #[test]
+    fn test_counters() {
+        let mut counters = BlindingCounters::default();
+        assert_eq!(counters.next(outpoint(0)), 0);
+        counters.mark_used(outpoint(0), 0);
+        assert_eq!(counters.next(outpoint(0)), 1);
+        counters.mark_used(outpoint(0), 4);
+        assert_eq!(counters.next(outpoint(0)), 5);
+        // Counters never go back
+        counters.mark_used(outpoint(0), 2);
+        assert_eq!(counters.next(outpoint(0)), 5);
+        assert_eq!(counters.next(outpoint(1)), 0);
+
+        let path = env::temp_dir()
+            .join(format!("rgb-blinding-{}", thread_rng().next_u64()))
+            .join("seed.counters");
+        assert_eq!(
+            BlindingCounters::load(&path).unwrap(),
+            BlindingCounters::default()
+        );
+        counters.mark_used(outpoint(1), 0);
+        counters.write_file(&path).unwrap();
+        assert_eq!(BlindingCounters::load(&path).unwrap(), counters);
+
+        fs::write(&path, format!("{} x\n", outpoint(0))).unwrap();
+        assert_eq!(
+            BlindingCounters::read_file(&path).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_seed_file() {
+        let path = env::temp_dir()
+            .join(format!("rgb-blinding-{}", thread_rng().next_u64()))
+            .join("seed.hex");
+
+        let seed = BlindingSeed::load_or_create(&path).unwrap();
+        assert_eq!(BlindingSeed::load_or_create(&path).unwrap(), seed);
+        assert_eq!(BlindingSeed::read_file(&path).unwrap(), seed);
+
+        #[cfg(unix)]
+        {
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o644))
+                .unwrap();
+            assert_eq!(
+                BlindingSeed::read_file(&path).unwrap_err().kind(),
+                io::ErrorKind::PermissionDenied
+            );
+
+            // Rewriting the seed restricts access to the existing file
+            seed.write_file(&path).unwrap();
+            assert_eq!(BlindingSeed::read_file(&path).unwrap(), seed);
+        }
+
+        fs::write(&path, "00ff").unwrap();
+        assert_eq!(
+            BlindingSeed::read_file(&path).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_seed_debug() {
+        let seed = BlindingSeed::from_inner([0x11; 32]);
+        assert_eq!(format!("{:?}", seed), "BlindingSeed(..)");
+    }
+}
@@ -0,0 +1,34 @@
+// RGB standard library
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::BTreeSet;
+
+use lnpbp::rgb::NodeId;
+
+/// Description of the contract history already known to the receiver of a
+/// consignment, used for preparing incremental consignments. Nodes are listed
+/// explicitly, so the sender never omits a node unknown to the receiver.
+#[derive(
+    Clone, PartialEq, Eq, Debug, Display, Default, StrictEncode, StrictDecode,
+)]
+#[display(Debug)]
+pub struct KnownHistory {
+    /// Explicit list of the nodes known to the receiver
+    pub node_ids: BTreeSet<NodeId>,
+}
+
+impl KnownHistory {
+    pub fn contains(&self, node_id: &NodeId) -> bool {
+        self.node_ids.contains(node_id)
+    }
+}
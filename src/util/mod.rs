@@ -13,9 +13,17 @@
 
 #[macro_use]
 mod macros;
+mod blinding;
 pub mod file;
+mod known_history;
 mod magic_numbers;
 mod seal_spec;
+mod stats;
+mod type_names;
 
+pub use blinding::{BlindingCounters, BlindingSeed};
+pub use known_history::KnownHistory;
 pub use magic_numbers::MagicNumber;
 pub use seal_spec::SealSpec;
+pub use stats::ConsignmentStats;
+pub use type_names::SchemaTypeNames;
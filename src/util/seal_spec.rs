@@ -83,8 +83,8 @@ impl FromStr for SealSpec {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let re = Regex::new(
             r"(?x)
-                ((?P<txid>[a-f\d]{64}) # Txid
-                :)
+                ^((?P<txid>[a-f\d]{64}) # Txid
+                :)?
                 (?P<vout>\d+)$ # Vout
             ",
         )
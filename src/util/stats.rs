@@ -0,0 +1,106 @@
+// RGB standard library
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::BTreeSet;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+
+use lnpbp::rgb::Consignment;
+use lnpbp::strict_encoding::{
+    self, strict_serialize, StrictDecode, StrictEncode,
+};
+
+/// Summary information on the consignment size and structure
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ConsignmentStats {
+    /// Number of state transitions, including the tip transition
+    pub transitions: usize,
+
+    /// Number of state extensions
+    pub extensions: usize,
+
+    /// Number of unique anchors
+    pub anchors: usize,
+
+    /// Number of consignment endpoints
+    pub endpoints: usize,
+
+    /// Size of the strict-encoded consignment, in bytes
+    pub size: usize,
+}
+
+impl ConsignmentStats {
+    pub fn with(
+        consignment: &Consignment,
+    ) -> Result<Self, strict_encoding::Error> {
+        Ok(Self {
+            transitions: consignment.state_transitions.len(),
+            extensions: consignment.state_extensions.len(),
+            anchors: consignment
+                .state_transitions
+                .iter()
+                .map(|(anchor, _)| anchor.anchor_id())
+                .collect::<BTreeSet<_>>()
+                .len(),
+            endpoints: consignment.endpoints.len(),
+            size: strict_serialize(consignment)?.len(),
+        })
+    }
+}
+
+// Counters are encoded as `u64`, since the default `usize` encoding is
+// limited to 16 bits, which is not enough for the consignment size
+impl StrictEncode for ConsignmentStats {
+    fn strict_encode<E: io::Write>(
+        &self,
+        mut e: E,
+    ) -> Result<usize, strict_encoding::Error> {
+        Ok(strict_encode_list!(e;
+            self.transitions as u64,
+            self.extensions as u64,
+            self.anchors as u64,
+            self.endpoints as u64,
+            self.size as u64
+        ))
+    }
+}
+
+impl StrictDecode for ConsignmentStats {
+    fn strict_decode<D: io::Read>(
+        mut d: D,
+    ) -> Result<Self, strict_encoding::Error> {
+        Ok(Self {
+            transitions: u64::strict_decode(&mut d)? as usize,
+            extensions: u64::strict_decode(&mut d)? as usize,
+            anchors: u64::strict_decode(&mut d)? as usize,
+            endpoints: u64::strict_decode(&mut d)? as usize,
+            size: u64::strict_decode(&mut d)? as usize,
+        })
+    }
+}
+
+impl Display for ConsignmentStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} nodes ({} transitions, {} extensions), {} anchors, \
+             {} endpoints, {} bytes",
+            self.transitions + self.extensions,
+            self.transitions,
+            self.extensions,
+            self.anchors,
+            self.endpoints,
+            self.size
+        )
+    }
+}
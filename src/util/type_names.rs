@@ -0,0 +1,36 @@
+// RGB standard library
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Human-readable names for the types defined by a schema, allowing tools to
+/// render contracts of any schema without knowing its Rust type definitions
+#[derive(
+    Clone, PartialEq, Eq, Debug, Display, Default, StrictEncode, StrictDecode,
+)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+#[display(Debug)]
+pub struct SchemaTypeNames {
+    pub schema_name: String,
+    pub field_types: BTreeMap<usize, String>,
+    pub owned_right_types: BTreeMap<usize, String>,
+    pub public_right_types: BTreeMap<usize, String>,
+    pub extension_types: BTreeMap<usize, String>,
+    pub transition_types: BTreeMap<usize, String>,
+}